@@ -0,0 +1,59 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+use crate::status::TcbStatus;
+
+/// An enclave whose attestation evidence has been verified
+#[derive(Clone)]
+pub struct VerifiedEnclave {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub attributes: [u8; 16],
+    pub report_data: [u8; 64],
+    pub tcb_status: TcbStatus,
+    /// Security advisories which apply to the platform
+    pub advisory_ids: Vec<String>,
+}
+
+// Arrays longer than 32 don't implement `Debug` and `PartialEq` in the SGX toolchain.
+impl fmt::Debug for VerifiedEnclave {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VerifiedEnclave")
+            .field("mr_enclave", &self.mr_enclave)
+            .field("mr_signer", &self.mr_signer)
+            .field("isv_prod_id", &self.isv_prod_id)
+            .field("isv_svn", &self.isv_svn)
+            .field("attributes", &self.attributes)
+            .field("report_data", &&self.report_data[..])
+            .field("tcb_status", &self.tcb_status)
+            .field("advisory_ids", &self.advisory_ids)
+            .finish()
+    }
+}
+
+impl PartialEq for VerifiedEnclave {
+    fn eq(&self, other: &Self) -> bool {
+        self.mr_enclave == other.mr_enclave
+            && self.mr_signer == other.mr_signer
+            && self.isv_prod_id == other.isv_prod_id
+            && self.isv_svn == other.isv_svn
+            && self.attributes == other.attributes
+            && self.report_data[..] == other.report_data[..]
+            && self.tcb_status == other.tcb_status
+            && self.advisory_ids == other.advisory_ids
+    }
+}
+
+impl Eq for VerifiedEnclave {}
+
+/// Verifies attestation evidence of an enclave, e.g. an IAS report or a DCAP quote.
+///
+/// Verifiers reject evidence which is not authentic; judging the identity of
+/// the enclave is left to the application.
+pub trait AttestationVerifier {
+    type Evidence: ?Sized;
+    type Error;
+
+    fn verify(&self, evidence: &Self::Evidence) -> Result<VerifiedEnclave, Self::Error>;
+}
@@ -0,0 +1,11 @@
+//! What the EPID (IAS) and DCAP attestation verifiers have in common, so that
+//! application policies don't depend on the attestation scheme
+#![no_std]
+
+extern crate alloc;
+
+mod enclave;
+mod status;
+
+pub use crate::enclave::{AttestationVerifier, VerifiedEnclave};
+pub use crate::status::TcbStatus;
@@ -0,0 +1,103 @@
+//! Tests of the public API of `client`, which use it from outside the crate
+//! as dependents do.
+#![no_std]
+#[macro_use]
+extern crate sgx_tstd as std;
+
+use std::{prelude::v1::*, time::Duration};
+use test_utils::*;
+use http::{Method, header::{HeaderMap, ACCEPT, CONTENT_TYPE, CONTENT_LENGTH, USER_AGENT}};
+use url::Url;
+use client::{
+    Certificate, Client, ClientBuilder, Identity, Proxy, Request, RequestBuilder, Result,
+    TlsVersion,
+};
+
+/// Runs the tests of the public API inside an enclave.
+pub fn run_tests() -> bool {
+    run_tests!(
+        test_build_client,
+        test_new_request,
+        test_reject_url_without_host,
+        test_reject_invalid_header,
+        test_reject_invalid_proxy,
+        test_configure_tls,
+        test_reject_invalid_root_certificate,
+    )
+}
+
+fn test_build_client() {
+    let proxy = Proxy::new("http://proxy.example.com:3128").unwrap();
+    let _: Client = ClientBuilder::new()
+        .proxy(proxy.clone())
+        .max_header_len(16 * 1_024)
+        .max_body_len(1_024 * 1_024)
+        .timeout(Some(Duration::from_secs(10)))
+        .connect_timeout(None)
+        .build()
+        .unwrap();
+    let _: Client = Client::builder().build().unwrap();
+    let _: Client = Client::with_proxy(proxy);
+    let _: Client = Client::default();
+}
+
+fn test_new_request() {
+    let url = Url::parse("https://example.com/path?q=1").unwrap();
+    let mut req = Request::new(Method::POST, url.clone());
+    assert_eq!(req.method(), Method::POST);
+    assert_eq!(req.url(), &url);
+    assert!(req.headers().get(ACCEPT).is_none());
+    assert!(req.body().is_none());
+
+    *req.body_mut() = Some(b"body".to_vec());
+    req.headers_mut().insert(CONTENT_TYPE, "text/plain".parse().unwrap());
+    assert_eq!(req.body().map(Vec::as_slice), Some(&b"body"[..]));
+    assert_eq!(req.headers()[CONTENT_TYPE], "text/plain");
+    assert!(req.headers().get(CONTENT_LENGTH).is_none());
+}
+
+fn test_reject_url_without_host() {
+    let builder: RequestBuilder = Client::new().get("data:text/plain,hello");
+    let result: Result<_> = builder.send();
+    assert!(result.is_err());
+}
+
+fn test_reject_invalid_header() {
+    let err = Client::new()
+        .get("https://example.com")
+        .header("invalid header", "value")
+        .send()
+        .err()
+        .unwrap();
+    assert!(err.downcast_ref::<http::Error>().is_some());
+}
+
+fn test_reject_invalid_proxy() {
+    assert!(Proxy::new("socks5://proxy.example.com:1080").is_err());
+}
+
+fn test_configure_tls() {
+    let ca = Certificate::from_pem(include_bytes!("../../testdata/ca.pem")).unwrap();
+    let server = Certificate::from_pem(include_bytes!("../../testdata/server.pem")).unwrap();
+    let mut identity_pem = include_bytes!("../../testdata/client.pem").to_vec();
+    identity_pem.extend_from_slice(include_bytes!("../../testdata/client.key"));
+    let identity = Identity::from_pem(&identity_pem).unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, "enclave".parse().unwrap());
+
+    let _: Client = Client::builder()
+        .add_root_certificate(ca)
+        .identity(identity)
+        .alpn_protocols(&[b"http/1.1"])
+        .min_tls_version(TlsVersion::Tls13)
+        .pin_spki_sha256(server.spki_sha256())
+        .default_headers(headers)
+        .build()
+        .unwrap();
+}
+
+fn test_reject_invalid_root_certificate() {
+    assert!(Certificate::from_der(b"not a certificate").is_err());
+    assert!(Certificate::from_pem(b"").is_err());
+    assert!(Identity::from_pem(include_bytes!("../../testdata/client.pem")).is_err());
+}
@@ -1,38 +1,59 @@
 use crate::std::{
     vec::Vec,
-    sync::Arc,
-    net::TcpStream,
+    boxed::Box,
     string::ToString,
+    sync::Arc,
+    time::Duration,
+    net::{SocketAddr, ToSocketAddrs},
 };
 use crate::{
-    transport::{Message, TlsTransport},
+    transport::{self, closes, Connection, Limits, StaleConnection},
+    pool::{Key, Pool},
+    redirect::{self, RedirectPolicy},
     request::{RequestBuilder, Request},
-    response::Response,
+    response::{Response, StreamingResponse},
     into_url::IntoUrl,
+    proxy::Proxy,
+    tls::{Certificate, Identity, TlsVersion, PinnedServerVerifier},
 };
-use anyhow::{Result, anyhow};
+use anyhow::{Error, Result, anyhow, bail, ensure};
 use http::{
     Method,
-    header::{HeaderMap, HeaderValue, ACCEPT},
+    header::{HeaderMap, HeaderValue, ACCEPT, CONNECTION},
 };
+use url::{Host, Url};
 
+/// An HTTP client to make requests with, over TLS for `https` urls.
+///
+/// Clients are cheap to clone, and clones share their configuration and
+/// their pool of idle connections.
+///
+/// Servers are verified against the roots added with `ClientBuilder`, and
+/// with the `mozilla-roots` feature, the Mozilla root certificates.
 #[derive(Clone)]
 pub struct Client {
     config: Config,
 }
 
 impl Client {
+    /// Constructs a `Client` with the default configuration.
+    ///
+    /// # Panics
+    ///
+    /// Never with the default configuration, which is always valid.
     pub fn new() -> Client {
-        Client {
-            config: Config {
-                tls_config: rustls::ClientConfig::default(),
-            }
-        }
+        ClientBuilder::new().build().expect("Failed to build the default client")
     }
 
-    // pub fn builder() -> ClientBuilder {
-    //     ClientBuilder::new()
-    // }
+    /// Constructs a `Client` which connects through the proxy.
+    pub fn with_proxy(proxy: Proxy) -> Client {
+        ClientBuilder::new().proxy(proxy).build().expect("Failed to build the client")
+    }
+
+    /// Creates a `ClientBuilder` to configure a `Client`.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
 
     pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
         let req = url.into_url().map(move |url| Request::new(method, url));
@@ -47,41 +68,679 @@ impl Client {
         self.request(Method::POST, url)
     }
 
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    pub fn head<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// Sends the request, and follows the redirects the policy allows.
     pub fn execute(&self, req: Request) -> Result<Response> {
-        use webpki::DNSNameRef;
+        self.execute_streaming(req)?.buffer()
+    }
 
+    /// Same as `execute`, but leaves the body of the response to read off
+    /// the connection as it is consumed.
+    pub fn execute_streaming(&self, mut req: Request) -> Result<StreamingResponse> {
+        for name in self.config.headers.keys() {
+            if !req.headers().contains_key(name) {
+                for value in self.config.headers.get_all(name) {
+                    req.headers_mut().append(name, value.clone());
+                }
+            }
+        }
+        if !self.config.pool.is_enabled() && !req.headers().contains_key(CONNECTION) {
+            req.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+        }
+
+        let mut previous = vec![];
+        loop {
+            let resp = self.send(&req)?;
+            let next = self.config.redirect.next(resp.status(), resp.headers(), req.url(), &previous)?;
+            match next {
+                Some(next) => {
+                    let status = resp.status();
+                    // Reading the body through frees the connection for reuse.
+                    resp.buffer()?;
+                    previous.push(req.url().clone());
+                    redirect::follow(&mut req, status, next);
+                }
+                None => return Ok(resp),
+            }
+        }
+    }
+
+    fn send(&self, req: &Request) -> Result<StreamingResponse> {
         let url = req.url();
-        let host = url.host().ok_or(anyhow!("no host in url"))?.to_string();
-        let dnsname = DNSNameRef::try_from_ascii_str(&host)?;
-        let sess = rustls::ClientSession::new(&self.config_arc(), dnsname);
-        let stream = TcpStream::connect(url.as_str())?;
-        let tls_stream = rustls::StreamOwned::new(sess, stream);
-        let mut transport = TlsTransport::new(tls_stream);
+        match url.scheme() {
+            "https" => {}
+            "http" => ensure!(!self.config.https_only, "Plaintext HTTP is forbidden: {}", url),
+            scheme => bail!("Unsupported url scheme: {}", scheme),
+        }
 
-        let response = transport.send(&req.into_url())?;
+        let key = Key::new(req.url())?;
+        let timeout = req.timeout().cloned().or(self.config.timeout);
+        // Connections which the request closes are not worth pooling.
+        let pool = if closes(req.headers()) {
+            None
+        } else {
+            Some((self.config.pool.clone(), key.clone()))
+        };
+        if let Some(conn) = self.config.pool.checkout(&key) {
+            conn.set_timeout(timeout)?;
+            match conn.send(req, self.config.limits, true) {
+                Ok((head, body)) => return Ok(StreamingResponse::new(head, body, pool)),
+                // The server closed the idle connection, and the request is
+                // safe to send again on a new one.
+                Err(e) if e.downcast_ref::<StaleConnection>().is_some() => {}
+                Err(e) => return Err(e),
+            }
+        }
 
-        Ok(Response { inner: response })
+        let conn = self.connect(req.url(), timeout)?;
+        conn.set_timeout(timeout)?;
+        let (head, body) = conn.send(req, self.config.limits, false)?;
+        Ok(StreamingResponse::new(head, body, pool))
     }
 
-    pub fn config_arc(&self) -> Arc<rustls::ClientConfig> {
-        Arc::new(self.config.tls_config.clone())
+    /// Connects to the url's host, or through the proxy to it: plaintext
+    /// requests are sent to the proxy, and TLS is tunneled with `CONNECT`.
+    /// `timeout` bounds each read and write of the `CONNECT` exchange.
+    fn connect(&self, url: &Url, timeout: Option<Duration>) -> Result<Connection> {
+        use webpki::DNSNameRef;
+
+        let host = url.host_str().ok_or(anyhow!("no host in url"))?;
+        let stream = match self.config.proxy {
+            Some(ref proxy) if proxy.intercepts(host) && url.scheme() == "http" => {
+                let stream = proxy.open(self.config.connect_timeout)?;
+                return Ok(Connection::Proxied(stream, proxy.authorization().map(ToString::to_string)));
+            }
+            Some(ref proxy) if proxy.intercepts(host) => {
+                let port = url.port_or_known_default().ok_or(anyhow!("no port in url"))?;
+                proxy.connect(host, port, self.config.connect_timeout, timeout)?
+            }
+            _ => transport::connect(&socket_addrs(url)?, self.config.connect_timeout)?,
+        };
+
+        if url.scheme() == "http" {
+            return Ok(Connection::Plain(stream));
+        }
+        let dnsname = DNSNameRef::try_from_ascii_str(host)
+            .map_err(|_| anyhow!("Invalid DNS name for TLS: {}", host))?;
+        let sess = rustls::ClientSession::new(&self.config.tls_config, dnsname);
+        Ok(Connection::Tls(Box::new(rustls::StreamOwned::new(sess, stream))))
     }
 }
 
-#[derive(Clone)]
-struct Config {
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+/// A builder to configure a `Client`.
+pub struct ClientBuilder {
     tls_config: rustls::ClientConfig,
+    #[cfg(feature = "mozilla-roots")]
+    built_in_root_certs: bool,
+    min_tls_version: Option<TlsVersion>,
+    spki_pins: Vec<[u8; 32]>,
+    headers: HeaderMap,
+    https_only: bool,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+    redirect: RedirectPolicy,
+    proxy: Option<Proxy>,
+    limits: Limits,
+    /// The first error in configuring, which `build` returns
+    error: Option<Error>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> ClientBuilder {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+
+        ClientBuilder {
+            tls_config: rustls::ClientConfig::new(),
+            #[cfg(feature = "mozilla-roots")]
+            built_in_root_certs: true,
+            min_tls_version: None,
+            spki_pins: vec![],
+            headers,
+            https_only: false,
+            timeout: Some(Duration::from_secs(30)),
+            connect_timeout: Some(Duration::from_secs(30)),
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: 8,
+            redirect: RedirectPolicy::default(),
+            proxy: None,
+            limits: Limits::default(),
+            error: None,
+        }
+    }
+
+    /// Trusts the root certificate to verify servers.
+    pub fn add_root_certificate(mut self, cert: Certificate) -> ClientBuilder {
+        if let Err(e) = self.tls_config.root_store.add(&cert.to_rustls()) {
+            self.error.get_or_insert(anyhow!("Invalid root certificate: {:?}", e));
+        }
+        self
+    }
+
+    /// Trusts the roots to verify servers, e.g. `webpki_roots::TLS_SERVER_ROOTS`.
+    pub fn add_trust_anchors(mut self, anchors: &webpki::TLSServerTrustAnchors) -> ClientBuilder {
+        self.tls_config.root_store.add_server_trust_anchors(anchors);
+        self
+    }
+
+    /// Whether to trust the Mozilla root certificates bundled with
+    /// `webpki-roots`, which is the default.
+    #[cfg(feature = "mozilla-roots")]
+    pub fn tls_built_in_root_certs(mut self, enable: bool) -> ClientBuilder {
+        self.built_in_root_certs = enable;
+        self
+    }
+
+    /// Authenticates with the client certificate to servers which request one.
+    pub fn identity(mut self, identity: Identity) -> ClientBuilder {
+        self.tls_config.set_single_client_cert(identity.cert_chain, identity.key);
+        self
+    }
+
+    /// Offers the protocols with ALPN, in order of preference.
+    pub fn alpn_protocols(mut self, protocols: &[&[u8]]) -> ClientBuilder {
+        let protocols: Vec<Vec<u8>> = protocols.iter().map(|p| p.to_vec()).collect();
+        self.tls_config.set_protocols(&protocols);
+        self
+    }
+
+    /// Refuses TLS versions older than `version`.
+    pub fn min_tls_version(mut self, version: TlsVersion) -> ClientBuilder {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    /// Pins the SHA-256 digest of a server's SubjectPublicKeyInfo.
+    ///
+    /// Once any key is pinned, servers must present a certificate with one
+    /// of the pinned keys, on top of the usual verification.
+    pub fn pin_spki_sha256(mut self, sha256: [u8; 32]) -> ClientBuilder {
+        self.spki_pins.push(sha256);
+        self
+    }
+
+    /// Sends the headers with every request, unless the request sets them.
+    pub fn default_headers(mut self, headers: HeaderMap) -> ClientBuilder {
+        for (name, value) in headers.iter() {
+            self.headers.insert(name, value.clone());
+        }
+        self
+    }
+
+    /// Refuses to send requests to `http` urls, which are allowed by default.
+    pub fn https_only(mut self, enable: bool) -> ClientBuilder {
+        self.https_only = enable;
+        self
+    }
+
+    /// Fails requests when a read or write on the connection takes longer
+    /// than `timeout`, 30 seconds by default, or never with `None`.
+    /// `Request::timeout` overrides it for a request.
+    ///
+    /// The timeout bounds each read and write, not the whole request, so a
+    /// server which keeps sending slowly is only stopped by the size limits.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> ClientBuilder {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Fails to connect when establishing a TCP connection takes longer than
+    /// `timeout`, 30 seconds by default, or never with `None`. Each address
+    /// of the host is tried in turn, with the whole timeout.
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> ClientBuilder {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Closes connections which have been idle for longer than `timeout`,
+    /// 90 seconds by default, or never with `None`.
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> ClientBuilder {
+        self.pool_idle_timeout = timeout;
+        self
+    }
+
+    /// Keeps at most `max` idle connections to each host, 8 by default.
+    /// With 0, every connection is closed after its response.
+    ///
+    /// This doesn't limit the connections in use: a request that finds no
+    /// idle connection always opens a new one.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> ClientBuilder {
+        self.pool_max_idle_per_host = max;
+        self
+    }
+
+    /// Caches the TLS sessions of up to `size` servers to resume them on
+    /// new connections without a full handshake, 32 by default. With 0,
+    /// sessions are not resumed.
+    pub fn tls_session_cache_size(mut self, size: usize) -> ClientBuilder {
+        if size == 0 {
+            self.tls_config.set_persistence(Arc::new(rustls::NoClientSessionStorage {}));
+        } else {
+            self.tls_config.set_persistence(rustls::ClientSessionMemoryCache::new(size));
+        }
+        self
+    }
+
+    /// Sets how redirects are followed, at most 10 of them by default.
+    pub fn redirect(mut self, policy: RedirectPolicy) -> ClientBuilder {
+        self.redirect = policy;
+        self
+    }
+
+    /// Connects through the proxy: `https` requests are tunneled with
+    /// `CONNECT`, and `http` ones sent to the proxy.
+    pub fn proxy(mut self, proxy: Proxy) -> ClientBuilder {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Limits the status line and headers of responses to `len` bytes, 64 KiB
+    /// by default.
+    pub fn max_header_len(mut self, len: usize) -> ClientBuilder {
+        self.limits.max_header_len = len;
+        self
+    }
+
+    /// Limits the bodies of responses to `len` bytes, 8 MiB by default.
+    pub fn max_body_len(mut self, len: usize) -> ClientBuilder {
+        self.limits.max_body_len = len;
+        self
+    }
+
+    /// Returns a `Client` with this configuration.
+    pub fn build(self) -> Result<Client> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        let mut tls_config = self.tls_config;
+        #[cfg(feature = "mozilla-roots")]
+        {
+            if self.built_in_root_certs {
+                tls_config.root_store.add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+            }
+        }
+        if let Some(min) = self.min_tls_version {
+            tls_config.versions = [TlsVersion::Tls13, TlsVersion::Tls12].iter()
+                .filter(|&&version| version >= min)
+                .map(|version| version.to_rustls())
+                .collect();
+        }
+        if !self.spki_pins.is_empty() {
+            let verifier = PinnedServerVerifier { pins: self.spki_pins };
+            tls_config.dangerous().set_certificate_verifier(Arc::new(verifier));
+        }
+
+        Ok(Client {
+            config: Config {
+                tls_config: Arc::new(tls_config),
+                headers: self.headers,
+                https_only: self.https_only,
+                timeout: self.timeout,
+                connect_timeout: self.connect_timeout,
+                pool: Arc::new(Pool::new(self.pool_idle_timeout, self.pool_max_idle_per_host)),
+                redirect: self.redirect,
+                proxy: self.proxy,
+                limits: self.limits,
+            }
+        })
+    }
 }
 
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        ClientBuilder::new()
+    }
+}
 
-pub trait ClientTransport {
-    fn send(&mut self, req: &[u8]) -> Result<Vec<u8>>;
+#[derive(Clone)]
+struct Config {
+    tls_config: Arc<rustls::ClientConfig>,
+    headers: HeaderMap,
+    https_only: bool,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool: Arc<Pool>,
+    redirect: RedirectPolicy,
+    proxy: Option<Proxy>,
+    limits: Limits,
+}
+
+/// Resolves the addresses of the url's host, at its port or the default
+/// port of its scheme.
+fn socket_addrs(url: &Url) -> Result<Vec<SocketAddr>> {
+    let port = url.port_or_known_default().ok_or(anyhow!("no port in url"))?;
+    let addrs = match url.host() {
+        Some(Host::Domain(domain)) => (domain, port).to_socket_addrs()?.collect(),
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        None => bail!("no host in url"),
+    };
+    Ok(addrs)
 }
 
-impl<S: rustls::Session> ClientTransport for TlsTransport<S> {
-    fn send(&mut self, req: &[u8]) -> Result<Vec<u8>> {
-        let mut msg = Message::new(&mut self.stream);
-        msg.write(req)?;
-        msg.read()
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use crate::std::{
+        str,
+        string::{String, ToString},
+        net::{Ipv4Addr, Ipv6Addr, TcpListener},
+    };
+    use crate::test_server::{TestServer, CA_PEM, SERVER_PEM};
+    use test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_socket_addrs,
+            test_request_target_and_host,
+            test_pin_server_key,
+            test_client_certificate,
+            test_plain_http,
+            test_plain_http_through_proxy,
+            test_reject_unsupported_scheme,
+            test_reuse_connection,
+            test_close_connection,
+            test_retry_stale_connection,
+            test_disable_pool,
+            test_timeout,
+            test_request_timeout,
+            test_send_methods,
+            test_follow_redirects,
+            test_redirect_to_another_origin,
+            test_stream_body,
+            test_stream_body_limit,
+        )
+    }
+
+    const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+
+    fn test_client() -> ClientBuilder {
+        Client::builder().add_root_certificate(Certificate::from_pem(CA_PEM).unwrap())
+    }
+
+    fn test_socket_addrs() {
+        let addrs = |url: &str| socket_addrs(&Url::parse(url).unwrap()).unwrap();
+        assert_eq!(addrs("https://127.0.0.1/"), vec![SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 443)]);
+        assert_eq!(addrs("http://[::1]:8080/"), vec![SocketAddr::new(Ipv6Addr::LOCALHOST.into(), 8080)]);
+        assert!(addrs("https://localhost").iter().all(|addr| addr.port() == 443 && addr.ip().is_loopback()));
+        assert!(socket_addrs(&Url::parse("foo://localhost/").unwrap()).is_err());
+    }
+
+    fn test_request_target_and_host() {
+        let server = TestServer::tls(vec![RESPONSE.to_vec()]);
+        let port = server.port();
+        let url = format!("https://localhost:{}/path/to?q=1&r=%20", port);
+        let resp = test_client().build().unwrap().get(&url).send().unwrap();
+        assert_eq!(resp.text().unwrap(), "ok");
+
+        let requests = server.requests().unwrap();
+        let request = str::from_utf8(&requests[0]).unwrap();
+        assert!(request.starts_with("GET /path/to?q=1&r=%20 HTTP/1.1\r\n"), "{}", request);
+        assert!(request.contains(&format!("\r\nHost: localhost:{}\r\n", port)), "{}", request);
+    }
+
+    fn test_pin_server_key() {
+        let server_key = Certificate::from_pem(SERVER_PEM).unwrap().spki_sha256();
+        let server = TestServer::tls(vec![RESPONSE.to_vec()]);
+        let url = format!("https://localhost:{}/", server.port());
+        let client = test_client().pin_spki_sha256(server_key).build().unwrap();
+        assert!(client.get(&url).send().is_ok());
+        server.requests().unwrap();
+
+        let ca_key = Certificate::from_pem(CA_PEM).unwrap().spki_sha256();
+        let server = TestServer::tls(vec![RESPONSE.to_vec()]);
+        let url = format!("https://localhost:{}/", server.port());
+        let client = test_client().pin_spki_sha256(ca_key).build().unwrap();
+        let err = client.get(&url).send().unwrap_err();
+        assert!(err.to_string().contains("not pinned"), "{}", err);
+        assert!(server.requests().is_err());
+    }
+
+    fn test_client_certificate() {
+        let mut pem = include_bytes!("../testdata/client.pem").to_vec();
+        pem.extend_from_slice(include_bytes!("../testdata/client.key"));
+        let identity = Identity::from_pem(&pem).unwrap();
+
+        let server = TestServer::tls_with_client_auth(vec![RESPONSE.to_vec()]);
+        let url = format!("https://localhost:{}/", server.port());
+        let client = test_client().identity(identity).build().unwrap();
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        server.requests().unwrap();
+
+        let server = TestServer::tls_with_client_auth(vec![RESPONSE.to_vec()]);
+        let url = format!("https://localhost:{}/", server.port());
+        assert!(test_client().build().unwrap().get(&url).send().is_err());
+        assert!(server.requests().is_err());
+    }
+
+    fn test_plain_http() {
+        let server = TestServer::plain(vec![RESPONSE.to_vec()]);
+        let url = format!("http://127.0.0.1:{}/", server.port());
+        assert_eq!(Client::new().get(&url).send().unwrap().text().unwrap(), "ok");
+        let requests = server.requests().unwrap();
+        assert!(requests[0].starts_with(b"GET / HTTP/1.1\r\n"));
+
+        let client = Client::builder().https_only(true).build().unwrap();
+        let err = client.get("http://127.0.0.1:1/").send().unwrap_err();
+        assert!(err.to_string().starts_with("Plaintext HTTP is forbidden"), "{}", err);
+    }
+
+    fn test_plain_http_through_proxy() {
+        let server = TestServer::plain(vec![RESPONSE.to_vec()]);
+        let proxy = Proxy::new(&format!("http://127.0.0.1:{}", server.port()))
+            .unwrap()
+            .basic_auth("user", "secret");
+        let resp = Client::with_proxy(proxy).get("http://example.com/path?q=1").send().unwrap();
+        assert_eq!(resp.text().unwrap(), "ok");
+        let requests = server.requests().unwrap();
+        let request = str::from_utf8(&requests[0]).unwrap();
+        assert!(request.starts_with("GET http://example.com/path?q=1 HTTP/1.1\r\n"), "{}", request);
+        assert!(request.contains("\r\nHost: example.com\r\n"));
+        assert!(request.contains("\r\nProxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+    }
+
+    fn test_reject_unsupported_scheme() {
+        let err = Client::new().get("ftp://localhost/file").send().unwrap_err();
+        assert_eq!(err.to_string(), "Unsupported url scheme: ftp");
+    }
+
+    fn test_reuse_connection() {
+        let server = TestServer::tls(vec![RESPONSE.to_vec(), RESPONSE.to_vec()]);
+        let url = format!("https://localhost:{}/", server.port());
+        let client = test_client().build().unwrap();
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        assert_eq!(client.clone().get(&url).send().unwrap().text().unwrap(), "ok");
+        let connections = server.connections().unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].len(), 2);
+    }
+
+    fn test_close_connection() {
+        let closing = b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok".to_vec();
+        let server = TestServer::plain(vec![closing.clone(), closing]);
+        let url = format!("http://127.0.0.1:{}/", server.port());
+        let client = Client::new();
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        assert_eq!(server.connections().unwrap().len(), 2);
+    }
+
+    fn test_retry_stale_connection() {
+        let server = TestServer::plain_closing(vec![RESPONSE.to_vec(); 3]);
+        let url = format!("http://127.0.0.1:{}/", server.port());
+        let client = Client::new();
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        // The server may have got the POST before closing, so it's not resent.
+        assert!(client.post(&url).body("job").send().is_err());
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        let connections = server.connections().unwrap();
+        assert_eq!(connections.len(), 3);
+        assert!(connections.iter().all(|requests| requests.len() == 1 && requests[0].starts_with(b"GET ")));
+    }
+
+    fn test_disable_pool() {
+        let server = TestServer::plain(vec![RESPONSE.to_vec(), RESPONSE.to_vec()]);
+        let url = format!("http://127.0.0.1:{}/", server.port());
+        let client = Client::builder().pool_max_idle_per_host(0).build().unwrap();
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        assert_eq!(client.get(&url).send().unwrap().text().unwrap(), "ok");
+        let connections = server.connections().unwrap();
+        assert_eq!(connections.len(), 2);
+        assert!(str::from_utf8(&connections[1][0]).unwrap().contains("\r\nconnection: close\r\n"));
+    }
+
+    fn test_timeout() {
+        // Connections are accepted into the backlog but never answered.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let client = Client::builder()
+            .timeout(Some(Duration::from_millis(100)))
+            .connect_timeout(Some(Duration::from_secs(1)))
+            .build()
+            .unwrap();
+        assert!(client.get(&url).send().is_err());
+    }
+
+    fn test_request_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let client = Client::builder().timeout(None).build().unwrap();
+        assert!(client.get(&url).timeout(Duration::from_millis(100)).send().is_err());
+    }
+
+    fn test_send_methods() {
+        let no_body = b"HTTP/1.1 204 No Content\r\n\r\n".to_vec();
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n".to_vec();
+        let server = TestServer::plain(vec![RESPONSE.to_vec(), RESPONSE.to_vec(), no_body, head]);
+        let url = format!("http://127.0.0.1:{}/keys", server.port());
+        let client = Client::new();
+        client.put(&url).json(&[1, 2]).send().unwrap();
+        client.patch(&url).form(&[("a", "b")]).send().unwrap();
+        assert_eq!(client.delete(&url).send().unwrap().status(), 204);
+        assert!(client.head(&url).send().unwrap().bytes().is_empty());
+
+        let requests: Vec<String> = server.requests().unwrap().into_iter()
+            .map(|request| String::from_utf8(request).unwrap())
+            .collect();
+        assert!(requests[0].starts_with("PUT /keys HTTP/1.1\r\n"));
+        assert!(requests[0].ends_with("\r\ncontent-length: 5\r\n\r\n[1,2]"), "{}", requests[0]);
+        assert!(requests[1].starts_with("PATCH /keys HTTP/1.1\r\n"));
+        assert!(requests[1].ends_with("\r\n\r\na=b"));
+        assert!(requests[2].starts_with("DELETE /keys HTTP/1.1\r\n"));
+        assert!(requests[3].starts_with("HEAD /keys HTTP/1.1\r\n"));
+    }
+
+    fn test_follow_redirects() {
+        let see_other = b"HTTP/1.1 303 See Other\r\nLocation: /result?id=1\r\nContent-Length: 0\r\n\r\n".to_vec();
+        let server = TestServer::plain(vec![see_other.clone(), RESPONSE.to_vec()]);
+        let url = format!("http://127.0.0.1:{}/jobs", server.port());
+        let resp = Client::new().post(&url).body("job").send().unwrap();
+        assert_eq!(resp.text().unwrap(), "ok");
+        let requests = server.requests().unwrap();
+        assert!(requests[0].starts_with(b"POST /jobs HTTP/1.1\r\n"));
+        assert!(requests[1].starts_with(b"GET /result?id=1 HTTP/1.1\r\n"));
+        assert!(!str::from_utf8(&requests[1]).unwrap().contains("content-length"));
+
+        let server = TestServer::plain(vec![see_other]);
+        let url = format!("http://127.0.0.1:{}/jobs", server.port());
+        let client = Client::builder().redirect(RedirectPolicy::none()).build().unwrap();
+        assert_eq!(client.post(&url).send().unwrap().status(), 303);
+        server.requests().unwrap();
+    }
+
+    fn test_redirect_to_another_origin() {
+        let target = TestServer::plain(vec![RESPONSE.to_vec()]);
+        let temporary = format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: http://127.0.0.1:{}/b\r\nContent-Length: 0\r\n\r\n",
+            target.port(),
+        ).into_bytes();
+        let origin = TestServer::plain(vec![temporary.clone()]);
+        let url = format!("http://127.0.0.1:{}/a", origin.port());
+        let resp = Client::new().put(&url).bearer_auth("secret").body("data").send().unwrap();
+        assert_eq!(resp.text().unwrap(), "ok");
+        assert!(origin.requests().unwrap()[0].starts_with(b"PUT /a HTTP/1.1\r\n"));
+        let request = String::from_utf8(target.requests().unwrap().remove(0)).unwrap();
+        assert!(request.starts_with("PUT /b HTTP/1.1\r\n"));
+        assert!(request.ends_with("\r\n\r\ndata"));
+        assert!(!request.contains("authorization"), "{}", request);
+
+        let origin = TestServer::plain(vec![temporary]);
+        let url = format!("http://127.0.0.1:{}/a", origin.port());
+        let client = Client::builder().redirect(RedirectPolicy::same_origin(10)).build().unwrap();
+        assert_eq!(client.get(&url).send().unwrap().status(), 307);
+        origin.requests().unwrap();
+    }
+
+    /// A chunked response of `len` bytes, in chunks of 4 KiB
+    fn chunked_response(len: usize) -> (Vec<u8>, Vec<u8>) {
+        let body: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for chunk in body.chunks(4_096) {
+            response.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+            response.extend_from_slice(chunk);
+            response.extend_from_slice(b"\r\n");
+        }
+        response.extend_from_slice(b"0\r\n\r\n");
+        (response, body)
+    }
+
+    fn test_stream_body() {
+        use crate::std::io::Read;
+
+        let (response, body) = chunked_response(100_000);
+        let server = TestServer::tls(vec![response.clone(), response]);
+        let url = format!("https://localhost:{}/crl", server.port());
+        let client = test_client().build().unwrap();
+
+        let mut resp = client.get(&url).send_streaming().unwrap();
+        assert_eq!(resp.status(), 200);
+        let mut copied = vec![];
+        assert_eq!(resp.copy_to(&mut copied).unwrap(), body.len() as u64);
+        assert_eq!(copied, body);
+
+        let mut read = vec![];
+        client.get(&url).send_streaming().unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, body);
+        // Both bodies were read through, which returned the connection.
+        assert_eq!(server.connections().unwrap().len(), 1);
+    }
+
+    fn test_stream_body_limit() {
+        let (response, _) = chunked_response(100_000);
+        let server = TestServer::plain(vec![response]);
+        let url = format!("http://127.0.0.1:{}/crl", server.port());
+        let client = Client::builder().max_body_len(50_000).build().unwrap();
+
+        let mut copied = vec![];
+        let err = client.get(&url).send_streaming().unwrap().copy_to(&mut copied).unwrap_err();
+        assert_eq!(err.to_string(), "Response body exceeds 50000 bytes");
+        assert!(copied.len() <= 50_000);
+        drop(client);
+        assert!(server.requests().is_ok());
     }
 }
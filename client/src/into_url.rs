@@ -4,7 +4,7 @@ use anyhow::{Result, anyhow};
 
 /// A trait to try to convert some type into a `Url`.
 ///
-/// This trait is "sealed", such that only types within reqwest can
+/// This trait is "sealed", such that only types within this crate can
 /// implement it. The reason is that it will eventually be deprecated
 /// and removed, when `std::convert::TryFrom` is stabilized.
 pub trait IntoUrl: PolyfillTryInto {}
@@ -27,13 +27,13 @@ impl PolyfillTryInto for Url {
     }
 }
 
-impl<'a> PolyfillTryInto for &'a str {
+impl PolyfillTryInto for &str {
     fn into_url(self) -> Result<Url> {
         Url::parse(self)?.into_url()
     }
 }
 
-impl<'a> PolyfillTryInto for &'a String {
+impl PolyfillTryInto for &String {
     fn into_url(self) -> Result<Url> {
         (&**self).into_url()
     }
@@ -4,6 +4,43 @@ extern crate sgx_tstd as std;
 
 mod client;
 mod into_url;
+mod parser;
+mod pool;
+mod proxy;
+mod redirect;
 mod request;
 mod response;
+#[cfg(feature = "enclave-test")]
+#[doc(hidden)]
+pub mod test_server;
+mod tls;
 mod transport;
+mod urlencoded;
+
+pub use crate::client::{Client, ClientBuilder};
+pub use crate::into_url::IntoUrl;
+pub use crate::proxy::Proxy;
+pub use crate::redirect::RedirectPolicy;
+pub use crate::request::{Request, RequestBuilder};
+pub use crate::response::{Response, StreamingResponse};
+pub use crate::tls::{Certificate, Identity, TlsVersion};
+pub use anyhow::{Error, Result};
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use std::prelude::v1::*;
+    use test_utils::check_all_passed;
+
+    /// Runs the tests of this crate inside an enclave.
+    pub fn run_tests() -> bool {
+        check_all_passed!(
+            crate::client::tests::run_tests(),
+            crate::parser::tests::run_tests(),
+            crate::proxy::tests::run_tests(),
+            crate::redirect::tests::run_tests(),
+            crate::request::tests::run_tests(),
+            crate::tls::tests::run_tests(),
+            crate::transport::tests::run_tests(),
+        )
+    }
+}
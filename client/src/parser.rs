@@ -0,0 +1,522 @@
+use crate::std::{
+    vec::Vec,
+    string::String,
+    str,
+    mem,
+};
+use http::{
+    StatusCode, Version,
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING},
+};
+use crate::transport::{closes, Limits};
+use anyhow::{Result, anyhow, bail, ensure};
+
+/// The longest chunk size line, with chunk extensions, we are willing to read
+const MAX_CHUNK_SIZE_LINE_LEN: usize = 1_024;
+
+/// An incremental parser of an HTTP/1.1 response.
+///
+/// Bytes are fed as they arrive off the wire until the parser reports the
+/// response complete, and the decoded body can be taken as it is parsed.
+/// Bodies are delimited by `Content-Length`, by chunked transfer-encoding,
+/// or by the server closing the connection.
+pub(crate) struct ResponseParser {
+    /// Bytes received but not yet parsed
+    buf: Vec<u8>,
+    /// Set for responses to HEAD requests, which never have a body
+    head_request: bool,
+    limits: Limits,
+    /// Bytes of status lines and header fields parsed so far
+    header_len: usize,
+    state: State,
+    head: Option<Head>,
+    /// Decoded body not yet taken
+    body: Vec<u8>,
+    /// Bytes of the decoded body parsed so far
+    body_len: usize,
+    /// Whether the server keeps the connection open after the response
+    persistent: bool,
+}
+
+pub(crate) struct Head {
+    pub(crate) status: StatusCode,
+    pub(crate) version: Version,
+    pub(crate) headers: HeaderMap,
+}
+
+enum State {
+    Head,
+    Body(Framing),
+    Done,
+}
+
+enum Framing {
+    Length(usize),
+    Chunked(Chunk),
+    Close,
+}
+
+enum Chunk {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+}
+
+impl ResponseParser {
+    pub(crate) fn new(head_request: bool, limits: Limits) -> Self {
+        ResponseParser {
+            buf: vec![],
+            head_request,
+            limits,
+            header_len: 0,
+            state: State::Head,
+            head: None,
+            body: vec![],
+            body_len: 0,
+            persistent: false,
+        }
+    }
+
+    /// Parses the next bytes of the response, and returns whether it is complete.
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Result<bool> {
+        self.buf.extend_from_slice(data);
+        let mut pos = 0;
+        let result = self.advance(&mut pos);
+        self.buf.drain(..pos);
+        result?;
+        Ok(self.is_done())
+    }
+
+    /// Ends the response at the end of the connection, which only completes
+    /// close-delimited bodies.
+    pub(crate) fn eof(&mut self) -> Result<()> {
+        match self.state {
+            State::Done => Ok(()),
+            State::Body(Framing::Close) => {
+                self.state = State::Done;
+                Ok(())
+            }
+            State::Head => bail!("Connection closed before the end of the response headers"),
+            State::Body(_) => bail!("Connection closed before the end of the response body"),
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// Returns whether the connection stays open after a complete response,
+    /// as HTTP/1.1 connections do unless the server closes them.
+    pub(crate) fn keep_alive(&self) -> bool {
+        self.is_done() && self.persistent
+    }
+
+    /// Takes the head of the final response once it is parsed.
+    pub(crate) fn take_head(&mut self) -> Option<Head> {
+        self.head.take()
+    }
+
+    /// Takes the body decoded since it was last taken.
+    pub(crate) fn take_body(&mut self) -> Vec<u8> {
+        mem::take(&mut self.body)
+    }
+
+    /// Returns the head and body of a complete response.
+    #[cfg(feature = "enclave-test")]
+    pub(crate) fn finish(self) -> Result<(Head, Vec<u8>)> {
+        ensure!(self.is_done(), "Incomplete response");
+        let head = self.head.ok_or_else(|| anyhow!("Incomplete response"))?;
+        Ok((head, self.body))
+    }
+
+    fn advance(&mut self, pos: &mut usize) -> Result<()> {
+        loop {
+            let rest = &self.buf[*pos..];
+            match self.state {
+                State::Done => {
+                    ensure!(rest.is_empty(), "Unexpected data after the end of the response");
+                    return Ok(());
+                }
+                State::Head => {
+                    let end = find(rest, b"\r\n\r\n");
+                    self.check_header_len(end.map_or(rest.len(), |end| end + 4))?;
+                    let end = match end {
+                        Some(end) => end,
+                        None => return Ok(()),
+                    };
+                    let head = parse_head(&rest[..end])?;
+                    *pos += end + 4;
+                    self.header_len += end + 4;
+                    // Interim responses precede the final one.
+                    if head.status.is_informational() {
+                        continue;
+                    }
+                    self.state = match self.framing(&head)? {
+                        Some(framing) => State::Body(framing),
+                        None => State::Done,
+                    };
+                    self.persistent = head.version == Version::HTTP_11
+                        && !closes(&head.headers)
+                        && !matches!(self.state, State::Body(Framing::Close));
+                    self.head = Some(head);
+                }
+                State::Body(Framing::Close) => {
+                    self.check_body_len(rest.len())?;
+                    self.body.extend_from_slice(rest);
+                    self.body_len += rest.len();
+                    *pos += rest.len();
+                    return Ok(());
+                }
+                State::Body(Framing::Length(remaining)) => {
+                    if rest.is_empty() {
+                        return Ok(());
+                    }
+                    let n = remaining.min(rest.len());
+                    self.body.extend_from_slice(&rest[..n]);
+                    self.body_len += n;
+                    *pos += n;
+                    self.state = match remaining - n {
+                        0 => State::Done,
+                        remaining => State::Body(Framing::Length(remaining)),
+                    };
+                }
+                State::Body(Framing::Chunked(Chunk::Size)) => {
+                    let end = match find(rest, b"\r\n") {
+                        Some(end) => end,
+                        None => {
+                            ensure!(rest.len() <= MAX_CHUNK_SIZE_LINE_LEN, "Chunk size line is too long");
+                            return Ok(());
+                        }
+                    };
+                    let size = parse_chunk_size(&rest[..end])?;
+                    self.check_body_len(size)?;
+                    *pos += end + 2;
+                    self.state = State::Body(Framing::Chunked(match size {
+                        0 => Chunk::Trailers,
+                        size => Chunk::Data(size),
+                    }));
+                }
+                State::Body(Framing::Chunked(Chunk::Data(remaining))) => {
+                    if rest.is_empty() {
+                        return Ok(());
+                    }
+                    let n = remaining.min(rest.len());
+                    self.body.extend_from_slice(&rest[..n]);
+                    self.body_len += n;
+                    *pos += n;
+                    self.state = State::Body(Framing::Chunked(match remaining - n {
+                        0 => Chunk::DataEnd,
+                        remaining => Chunk::Data(remaining),
+                    }));
+                }
+                State::Body(Framing::Chunked(Chunk::DataEnd)) => {
+                    if rest.len() < 2 {
+                        return Ok(());
+                    }
+                    ensure!(&rest[..2] == b"\r\n", "Invalid end of a chunk");
+                    *pos += 2;
+                    self.state = State::Body(Framing::Chunked(Chunk::Size));
+                }
+                State::Body(Framing::Chunked(Chunk::Trailers)) => {
+                    // Trailer fields are discarded.
+                    let end = find(rest, b"\r\n");
+                    self.check_header_len(end.map_or(rest.len(), |end| end + 2))?;
+                    let end = match end {
+                        Some(end) => end,
+                        None => return Ok(()),
+                    };
+                    *pos += end + 2;
+                    self.header_len += end + 2;
+                    if end == 0 {
+                        self.state = State::Done;
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_header_len(&self, len: usize) -> Result<()> {
+        ensure!(
+            self.header_len + len <= self.limits.max_header_len,
+            "Response headers exceed {} bytes", self.limits.max_header_len,
+        );
+        Ok(())
+    }
+
+    fn check_body_len(&self, len: usize) -> Result<()> {
+        ensure!(
+            len <= self.limits.max_body_len - self.body_len,
+            "Response body exceeds {} bytes", self.limits.max_body_len,
+        );
+        Ok(())
+    }
+
+    /// Determines how the body is delimited as in RFC 7230, section 3.3.3,
+    /// or `None` if there is no body.
+    fn framing(&self, head: &Head) -> Result<Option<Framing>> {
+        if self.head_request
+            || head.status == StatusCode::NO_CONTENT
+            || head.status == StatusCode::NOT_MODIFIED
+        {
+            return Ok(None);
+        }
+
+        if head.headers.contains_key(TRANSFER_ENCODING) {
+            let mut codings = vec![];
+            for value in head.headers.get_all(TRANSFER_ENCODING) {
+                codings.extend(value.to_str()?.split(',').map(|c| c.trim()).filter(|c| !c.is_empty()));
+            }
+            return match codings.as_slice() {
+                [coding] if coding.eq_ignore_ascii_case("chunked") => Ok(Some(Framing::Chunked(Chunk::Size))),
+                _ => bail!("Unsupported transfer encoding: {:?}", codings),
+            };
+        }
+
+        let mut length = None;
+        for value in head.headers.get_all(CONTENT_LENGTH) {
+            let value = value.to_str()?.trim();
+            ensure!(!value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()), "Invalid Content-Length: {}", value);
+            let value: usize = value.parse()?;
+            if let Some(length) = length {
+                ensure!(length == value, "Conflicting Content-Length headers");
+            }
+            length = Some(value);
+        }
+        if let Some(length) = length {
+            self.check_body_len(length)?;
+        }
+        Ok(Some(match length {
+            Some(0) => return Ok(None),
+            Some(length) => Framing::Length(length),
+            None => Framing::Close,
+        }))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Parses the status line and header fields.
+fn parse_head(head: &[u8]) -> Result<Head> {
+    let mut lines = head.split(|&b| b == b'\n').map(|line| match line.last() {
+        Some(b'\r') => &line[..line.len() - 1],
+        _ => line,
+    });
+    let status_line = str::from_utf8(lines.next().unwrap_or_default())?;
+
+    let mut parts = status_line.splitn(3, ' ');
+    let version = match parts.next() {
+        Some("HTTP/1.1") => Version::HTTP_11,
+        Some("HTTP/1.0") => Version::HTTP_10,
+        _ => bail!("Invalid status line: {}", status_line),
+    };
+    let status = parts.next()
+        .filter(|code| code.len() == 3)
+        .and_then(|code| code.parse().ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .ok_or_else(|| anyhow!("Invalid status line: {}", status_line))?;
+
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        ensure!(!line.starts_with(b" ") && !line.starts_with(b"\t"), "Obsolete header line folding");
+        let colon = line.iter().position(|&b| b == b':')
+            .ok_or_else(|| anyhow!("Invalid header line: {}", String::from_utf8_lossy(line)))?;
+        let value = trim(&line[colon + 1..]);
+        headers.append(HeaderName::from_bytes(&line[..colon])?, HeaderValue::from_bytes(value)?);
+    }
+
+    Ok(Head { status, version, headers })
+}
+
+fn parse_chunk_size(line: &[u8]) -> Result<usize> {
+    // Chunk extensions are ignored.
+    let size = line.split(|&b| b == b';').next().unwrap_or_default();
+    let size = str::from_utf8(trim(size))?;
+    ensure!(!size.is_empty() && size.len() <= 16, "Invalid chunk size: {}", size);
+    Ok(usize::from_str_radix(size, 16)?)
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let is_space = |b: &u8| *b == b' ' || *b == b'\t';
+    let start = bytes.iter().position(|b| !is_space(b)).unwrap_or(bytes.len());
+    let end = bytes.iter().rposition(|b| !is_space(b)).map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use test_utils::*;
+    use http::header::CONTENT_TYPE;
+    use crate::response::Response;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_content_length_body,
+            test_chunked_body,
+            test_close_delimited_body,
+            test_keep_alive,
+            test_take_body,
+            test_response_without_body,
+            test_skip_interim_response,
+            test_response_accessors,
+            test_reject_invalid_responses,
+            test_enforce_limits,
+        )
+    }
+
+    fn parse_with(raw: &[u8], limits: Limits) -> Result<Response> {
+        let mut parser = ResponseParser::new(false, limits);
+        if !parser.feed(raw)? {
+            parser.eof()?;
+        }
+        let (head, body) = parser.finish()?;
+        Ok(Response::new(head, body))
+    }
+
+    fn parse(raw: &[u8]) -> Result<Response> {
+        parse_with(raw, Limits::default())
+    }
+
+    /// Feeds the response a byte at a time, as from a slow connection.
+    fn parse_bytewise(raw: &[u8], head_request: bool) -> Result<(Head, Vec<u8>)> {
+        let mut parser = ResponseParser::new(head_request, Limits::default());
+        for (i, b) in raw.iter().enumerate() {
+            if parser.feed(&[*b])? {
+                ensure!(i == raw.len() - 1, "Completed before the end");
+            }
+        }
+        parser.finish()
+    }
+
+    fn test_content_length_body() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-Test:  a b \r\n\r\nhello";
+        let (head, body) = parse_bytewise(raw, false).unwrap();
+        assert_eq!(head.status, StatusCode::OK);
+        assert_eq!(head.version, Version::HTTP_11);
+        assert_eq!(head.headers["x-test"], "a b");
+        assert_eq!(body, b"hello");
+    }
+
+    fn test_chunked_body() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;ext=1\r\nhello\r\nA\r\n, chunked!\r\n0\r\nX-Trailer: t\r\n\r\n";
+        let (_, body) = parse_bytewise(raw, false).unwrap();
+        assert_eq!(body, b"hello, chunked!");
+    }
+
+    fn test_close_delimited_body() {
+        let mut parser = ResponseParser::new(false, Limits::default());
+        assert!(!parser.feed(b"HTTP/1.0 200 OK\r\n\r\nuntil ").unwrap());
+        assert!(!parser.feed(b"close").unwrap());
+        parser.eof().unwrap();
+        let (head, body) = parser.finish().unwrap();
+        assert_eq!(head.version, Version::HTTP_10);
+        assert_eq!(body, b"until close");
+    }
+
+    fn test_keep_alive() {
+        let keep_alive = |raw: &[u8]| {
+            let mut parser = ResponseParser::new(false, Limits::default());
+            if !parser.feed(raw).unwrap() {
+                parser.eof().unwrap();
+            }
+            parser.keep_alive()
+        };
+        assert!(keep_alive(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"));
+        assert!(keep_alive(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n"));
+        assert!(!keep_alive(b"HTTP/1.1 200 OK\r\nConnection: keep-alive, Close\r\nContent-Length: 2\r\n\r\nok"));
+        assert!(!keep_alive(b"HTTP/1.1 200 OK\r\n\r\nuntil close"));
+        assert!(!keep_alive(b"HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\nok"));
+
+        let mut parser = ResponseParser::new(false, Limits::default());
+        parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\no").unwrap();
+        assert!(!parser.keep_alive());
+    }
+
+    fn test_take_body() {
+        let mut parser = ResponseParser::new(false, Limits { max_header_len: 1_024, max_body_len: 10 });
+        parser.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nstre").unwrap();
+        let head = parser.take_head().unwrap();
+        assert_eq!(head.status, StatusCode::OK);
+        assert!(parser.take_head().is_none());
+        assert_eq!(parser.take_body(), b"stre");
+        parser.feed(b"am\r\n4\r\ning ").unwrap();
+        assert_eq!(parser.take_body(), b"aming ");
+        // Taken bytes still count toward the limit.
+        assert!(parser.feed(b"\r\n1\r\n!").is_err());
+    }
+
+    fn test_response_without_body() {
+        let (_, body) = parse_bytewise(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n", true).unwrap();
+        assert!(body.is_empty());
+        let (head, body) = parse_bytewise(b"HTTP/1.1 204 No Content\r\n\r\n", false).unwrap();
+        assert_eq!(head.status, StatusCode::NO_CONTENT);
+        assert!(body.is_empty());
+    }
+
+    fn test_skip_interim_response() {
+        let raw = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok";
+        let (head, body) = parse_bytewise(raw, false).unwrap();
+        assert_eq!(head.status, StatusCode::CREATED);
+        assert_eq!(body, b"ok");
+    }
+
+    fn test_response_accessors() {
+        let raw = b"HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 13\r\n\r\n{\"id\":\"abc\"}\n";
+        let resp = parse(raw).unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.version(), Version::HTTP_11);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(resp.text().unwrap(), "{\"id\":\"abc\"}\n");
+        let json: serde_json::Value = resp.json().unwrap();
+        assert_eq!(json["id"], "abc");
+    }
+
+    fn test_reject_invalid_responses() {
+        let invalid: &[&[u8]] = &[
+            b"HTTP/2 200 OK\r\n\r\n",
+            b"HTTP/1.1 20 OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nno colon\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nX-Folded: a\r\n b\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: -1\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nabc",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nab",
+        ];
+        for raw in invalid {
+            assert!(ResponseParser::new(false, Limits::default()).feed(raw).is_err(), "{}", String::from_utf8_lossy(raw));
+        }
+
+        // Truncated by the end of the connection
+        assert!(parse(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort").is_err());
+        assert!(parse(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+
+    fn test_enforce_limits() {
+        let limits = Limits { max_header_len: 64, max_body_len: 4 };
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n";
+        assert!(parse_with(&[&head[..], b"body"].concat(), limits).is_ok());
+
+        let long_header = format!("HTTP/1.1 200 OK\r\nX-Long: {}\r\n\r\n", "a".repeat(64));
+        assert!(parse_with(long_header.as_bytes(), limits).is_err());
+        // The limit applies before the end of the headers is seen.
+        let mut parser = ResponseParser::new(false, limits);
+        assert!(parser.feed(&long_header.as_bytes()[..65]).is_err());
+
+        let bodies: &[&[u8]] = &[
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\nclose delimited",
+        ];
+        for raw in bodies {
+            assert!(ResponseParser::new(false, limits).feed(raw).is_err(), "{}", String::from_utf8_lossy(raw));
+        }
+        let trailers = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\nX-Trailer: 0123456789\r\n";
+        assert!(ResponseParser::new(false, limits).feed(trailers).is_err());
+    }
+}
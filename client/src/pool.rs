@@ -0,0 +1,83 @@
+use crate::std::{
+    vec::Vec,
+    string::{String, ToString},
+    collections::HashMap,
+    sync::SgxMutex,
+    time::{Duration, Instant},
+    untrusted::time::InstantEx,
+};
+use crate::transport::Connection;
+use anyhow::{Result, anyhow};
+use url::Url;
+
+/// Idle connections kept alive for later requests, which the clones of a
+/// `Client` share. Only idle connections are counted against the limit per
+/// host; those in use are not tracked.
+pub(crate) struct Pool {
+    idle: SgxMutex<HashMap<Key, Vec<Idle>>>,
+    idle_timeout: Option<Duration>,
+    max_idle_per_host: usize,
+}
+
+/// Connections are only reused for the same scheme, host and port.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Key {
+    scheme: String,
+    host: String,
+    port: u16,
+}
+
+impl Key {
+    pub fn new(url: &Url) -> Result<Self> {
+        Ok(Key {
+            scheme: url.scheme().to_string(),
+            host: url.host_str().ok_or(anyhow!("no host in url"))?.to_string(),
+            port: url.port_or_known_default().ok_or(anyhow!("no port in url"))?,
+        })
+    }
+}
+
+struct Idle {
+    conn: Connection,
+    since: Instant,
+}
+
+impl Pool {
+    pub fn new(idle_timeout: Option<Duration>, max_idle_per_host: usize) -> Self {
+        Pool {
+            idle: SgxMutex::new(HashMap::new()),
+            idle_timeout,
+            max_idle_per_host,
+        }
+    }
+
+    /// Whether connections are kept alive at all
+    pub fn is_enabled(&self) -> bool {
+        self.max_idle_per_host > 0
+    }
+
+    /// Takes the most recently used connection to `key`, dropping the ones
+    /// idle for longer than the timeout.
+    pub fn checkout(&self, key: &Key) -> Option<Connection> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let conns = idle.get_mut(key)?;
+        if let Some(timeout) = self.idle_timeout {
+            conns.retain(|conn| conn.since.elapsed() < timeout);
+        }
+        let conn = conns.pop().map(|idle| idle.conn);
+        if conns.is_empty() {
+            idle.remove(key);
+        }
+        conn
+    }
+
+    /// Keeps the connection for later requests, unless there are enough
+    /// idle connections to `key` already.
+    pub fn checkin(&self, key: Key, conn: Connection) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let conns = idle.entry(key).or_default();
+        if conns.len() < self.max_idle_per_host {
+            conns.push(Idle { conn, since: Instant::now() });
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::std::vec::Vec;
+use crate::request::Request;
+use http::{
+    Method, StatusCode,
+    header::{
+        HeaderMap, HeaderName, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION,
+        TRANSFER_ENCODING,
+    },
+};
+use url::Url;
+use anyhow::{Result, bail};
+
+/// Headers carrying credentials, which are not sent on to another origin
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "ocp-apim-subscription-key",
+];
+
+/// How a `Client` follows redirects, at most 10 of them by default.
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectPolicy {
+    max: usize,
+    same_origin: bool,
+}
+
+impl RedirectPolicy {
+    /// Returns redirects as responses instead of following them.
+    pub fn none() -> Self {
+        RedirectPolicy::limited(0)
+    }
+
+    /// Follows at most `max` redirects, and fails on more.
+    pub fn limited(max: usize) -> Self {
+        RedirectPolicy { max, same_origin: false }
+    }
+
+    /// Follows at most `max` redirects within the origin, and returns those
+    /// to another scheme, host or port as responses.
+    pub fn same_origin(max: usize) -> Self {
+        RedirectPolicy { max, same_origin: true }
+    }
+
+    /// Returns where to follow a response to `url`, or `None` to return it.
+    ///
+    /// `previous` are the urls requested before, which a redirect to any of
+    /// them would loop through.
+    pub(crate) fn next(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        url: &Url,
+        previous: &[Url],
+    ) -> Result<Option<Url>> {
+        if !is_redirect(status) || self.max == 0 {
+            return Ok(None);
+        }
+        let location = match headers.get(LOCATION) {
+            Some(location) => location.to_str()?,
+            None => return Ok(None),
+        };
+        let next = url.join(location)?;
+        if self.same_origin && !same_origin(url, &next) {
+            return Ok(None);
+        }
+        if next == *url || previous.contains(&next) {
+            bail!("Redirect loop at {}", next);
+        }
+        if previous.len() >= self.max {
+            bail!("Too many redirects, the last to {}", next);
+        }
+        Ok(Some(next))
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy::limited(10)
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+/// Turns the request into the one to follow a redirect to `next`.
+///
+/// 301 and 302 change POST to GET as browsers do, and 303 changes all but
+/// HEAD to GET, without the body. 307 and 308 repeat the request as is.
+pub(crate) fn follow(req: &mut Request, status: StatusCode, next: Url) {
+    let to_get = match status {
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *req.method() == Method::POST,
+        StatusCode::SEE_OTHER => *req.method() != Method::HEAD,
+        _ => false,
+    };
+    if to_get {
+        *req.method_mut() = Method::GET;
+        *req.body_mut() = None;
+        for name in &[CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING, TRANSFER_ENCODING] {
+            req.headers_mut().remove(name);
+        }
+    }
+
+    if !same_origin(req.url(), &next) {
+        let sensitive: Vec<HeaderName> = req.headers().keys()
+            .filter(|name| SENSITIVE_HEADERS.contains(&name.as_str()))
+            .cloned()
+            .collect();
+        for name in sensitive {
+            req.headers_mut().remove(name);
+        }
+    }
+    *req.url_mut() = next;
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use crate::std::string::{String, ToString};
+    use http::header::{HeaderValue, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION};
+    use test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_redirect_policy,
+            test_redirect_loop,
+            test_rewrite_method,
+            test_strip_sensitive_headers,
+        )
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn location(location: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, HeaderValue::from_str(location).unwrap());
+        headers
+    }
+
+    fn test_redirect_policy() {
+        let from = url("https://example.com/a/b");
+        let next = |policy: RedirectPolicy, status: u16, headers: &HeaderMap| {
+            policy.next(StatusCode::from_u16(status).unwrap(), headers, &from, &[])
+        };
+        let relative = location("../c?d=e");
+        let other = location("https://example.com:8443/");
+
+        assert_eq!(next(RedirectPolicy::default(), 302, &relative).unwrap(), Some(url("https://example.com/c?d=e")));
+        assert_eq!(next(RedirectPolicy::default(), 200, &relative).unwrap(), None);
+        assert_eq!(next(RedirectPolicy::default(), 304, &relative).unwrap(), None);
+        assert_eq!(next(RedirectPolicy::default(), 301, &HeaderMap::new()).unwrap(), None);
+        assert_eq!(next(RedirectPolicy::none(), 308, &relative).unwrap(), None);
+        assert_eq!(next(RedirectPolicy::same_origin(5), 307, &relative).unwrap(), Some(url("https://example.com/c?d=e")));
+        assert_eq!(next(RedirectPolicy::same_origin(5), 307, &other).unwrap(), None);
+        assert_eq!(next(RedirectPolicy::limited(5), 307, &other).unwrap(), Some(url("https://example.com:8443/")));
+
+        let previous = [url("https://example.com/1"), url("https://example.com/2")];
+        assert!(RedirectPolicy::limited(2).next(StatusCode::FOUND, &relative, &from, &previous).is_err());
+        assert!(RedirectPolicy::limited(3).next(StatusCode::FOUND, &relative, &from, &previous).is_ok());
+    }
+
+    fn test_redirect_loop() {
+        let from = url("https://example.com/b");
+        let previous = [url("https://example.com/a")];
+        let err = RedirectPolicy::default()
+            .next(StatusCode::FOUND, &location("/a"), &from, &previous)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Redirect loop at https://example.com/a");
+        assert!(RedirectPolicy::default().next(StatusCode::FOUND, &location("/b"), &from, &[]).is_err());
+    }
+
+    fn test_rewrite_method() {
+        let followed = |method: Method, status: StatusCode| {
+            let mut req = Request::new(method, url("https://example.com/a"));
+            *req.body_mut() = Some(b"body".to_vec());
+            req.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+            follow(&mut req, status, url("https://example.com/b"));
+            assert_eq!(req.url().path(), "/b");
+            req
+        };
+
+        let req = followed(Method::POST, StatusCode::FOUND);
+        assert_eq!(*req.method(), Method::GET);
+        assert!(req.body().is_none());
+        assert!(req.headers().get(CONTENT_TYPE).is_none());
+        assert_eq!(*followed(Method::PUT, StatusCode::MOVED_PERMANENTLY).method(), Method::PUT);
+        assert_eq!(*followed(Method::DELETE, StatusCode::SEE_OTHER).method(), Method::GET);
+        assert_eq!(*followed(Method::HEAD, StatusCode::SEE_OTHER).method(), Method::HEAD);
+        for &status in &[StatusCode::TEMPORARY_REDIRECT, StatusCode::PERMANENT_REDIRECT] {
+            let req = followed(Method::POST, status);
+            assert_eq!(*req.method(), Method::POST);
+            assert_eq!(req.body().unwrap(), b"body");
+            assert_eq!(req.headers()[CONTENT_TYPE], "text/plain");
+        }
+    }
+
+    fn test_strip_sensitive_headers() {
+        let followed = |next: &str| {
+            let mut req = Request::new(Method::GET, url("https://example.com/a"));
+            req.headers_mut().insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+            req.headers_mut().insert(COOKIE, HeaderValue::from_static("session=1"));
+            req.headers_mut().insert(PROXY_AUTHORIZATION, HeaderValue::from_static("Basic cHJveHk="));
+            req.headers_mut().insert("Ocp-Apim-Subscription-Key", HeaderValue::from_static("key"));
+            req.headers_mut().insert("X-Request-Id", HeaderValue::from_static("1"));
+            follow(&mut req, StatusCode::TEMPORARY_REDIRECT, url(next));
+            req.headers().keys().map(|name| String::from(name.as_str())).collect::<Vec<_>>()
+        };
+
+        assert_eq!(followed("https://example.com/b").len(), 5);
+        for next in &["https://example.org/", "http://example.com/a", "https://example.com:8443/a"] {
+            assert_eq!(followed(next), vec!["x-request-id"]);
+        }
+    }
+}
@@ -1,7 +1,194 @@
 use crate::std::{
+    fmt,
     vec::Vec,
+    string::String,
+    sync::Arc,
+    io::{self, ErrorKind, Read, Write},
 };
+use crate::{
+    parser::Head,
+    pool::{Key, Pool},
+    transport::{Connection, Message},
+};
+use http::{
+    StatusCode, Version,
+    header::HeaderMap,
+};
+use serde::de::DeserializeOwned;
+use anyhow::Result;
 
+/// Size of the buffer bodies are copied through
+const COPY_BUF_LEN: usize = 8 * 1_024;
+
+/// A response to a submitted `Request`.
+#[derive(Debug)]
 pub struct Response {
-    pub inner: Vec<u8>,
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub(crate) fn new(head: Head, body: Vec<u8>) -> Self {
+        Response {
+            status: head.status,
+            version: head.version,
+            headers: head.headers,
+            body,
+        }
+    }
+
+    /// Get the status code.
+    #[inline]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the HTTP version.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Get the headers.
+    #[inline]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the body.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        &self.body[..]
+    }
+
+    /// Consumes the response, returning the body.
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Get the body as UTF-8 text.
+    pub fn text(&self) -> Result<String> {
+        Ok(String::from_utf8(self.body.clone())?)
+    }
+
+    /// Deserializes the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// A response whose body is read off the connection as it is consumed,
+/// for bodies too large to buffer in the enclave.
+///
+/// Chunked transfer-encoding is decoded, and the body is still limited to
+/// `ClientBuilder::max_body_len`, which large downloads may need to raise.
+pub struct StreamingResponse {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    /// `None` once the body is complete
+    body: Option<Message<Connection>>,
+    /// Where the connection goes back to once the body is complete
+    pool: Option<(Arc<Pool>, Key)>,
+}
+
+impl StreamingResponse {
+    pub(crate) fn new(head: Head, body: Message<Connection>, pool: Option<(Arc<Pool>, Key)>) -> Self {
+        StreamingResponse {
+            status: head.status,
+            version: head.version,
+            headers: head.headers,
+            body: Some(body),
+            pool,
+        }
+    }
+
+    /// Get the status code.
+    #[inline]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the HTTP version.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Get the headers.
+    #[inline]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Copies the rest of the body to the writer, and returns the number of
+    /// bytes copied.
+    pub fn copy_to<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<u64> {
+        let mut buf = [0u8; COPY_BUF_LEN];
+        let mut copied = 0;
+        loop {
+            let n = self.read_body(&mut buf)?;
+            if n == 0 {
+                return Ok(copied);
+            }
+            writer.write_all(&buf[..n])?;
+            copied += n as u64;
+        }
+    }
+
+    /// Reads the rest of the body into a buffered `Response`.
+    pub fn buffer(mut self) -> Result<Response> {
+        let body = match self.body {
+            Some(ref mut msg) => msg.read_to_end()?,
+            None => vec![],
+        };
+        self.release();
+        let head = Head {
+            status: self.status,
+            version: self.version,
+            headers: self.headers,
+        };
+        Ok(Response::new(head, body))
+    }
+
+    fn read_body(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = match self.body {
+            Some(ref mut msg) => msg.read_body(buf)?,
+            None => return Ok(0),
+        };
+        if n == 0 {
+            self.release();
+        }
+        Ok(n)
+    }
+
+    /// Returns the connection to the pool once the body is complete, if the
+    /// server keeps it open.
+    fn release(&mut self) {
+        if let Some(msg) = self.body.take() {
+            if let (true, Some((pool, key))) = (msg.keep_alive(), self.pool.take()) {
+                pool.checkin(key, msg.into_transport());
+            }
+        }
+    }
+}
+
+impl Read for StreamingResponse {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Responses which fail to parse or exceed the limits are invalid.
+        self.read_body(buf).map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{:#}", e)))
+    }
+}
+
+impl fmt::Debug for StreamingResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamingResponse")
+            .field("status", &self.status)
+            .field("version", &self.version)
+            .field("headers", &self.headers)
+            .finish()
+    }
 }
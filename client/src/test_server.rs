@@ -0,0 +1,159 @@
+//! A local server which serves canned responses to tests, and records the
+//! requests it receives.
+use crate::std::{
+    vec::Vec,
+    string::String,
+    sync::Arc,
+    io::{Read, Write, BufReader},
+    net::{TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+    time::Duration,
+    boxed::Box,
+    str,
+};
+use rustls::{
+    ServerConfig, ServerSession, StreamOwned, NoClientAuth, AllowAnyAuthenticatedClient,
+    RootCertStore, internal::pemfile,
+};
+use anyhow::{Result, anyhow, ensure};
+
+pub const CA_PEM: &[u8] = include_bytes!("../testdata/ca.pem");
+pub const SERVER_PEM: &[u8] = include_bytes!("../testdata/server.pem");
+const SERVER_KEY: &[u8] = include_bytes!("../testdata/server.key");
+
+/// How long the server waits for a request before giving up, so a client
+/// which fails to reuse a connection fails the test instead of hanging it
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct TestServer {
+    port: u16,
+    /// Requests grouped by the connection they arrived on
+    handle: JoinHandle<Result<Vec<Vec<Vec<u8>>>>>,
+}
+
+impl TestServer {
+    /// Serves the responses over plain TCP.
+    ///
+    /// Connections are kept alive until a request or response has
+    /// `Connection: close`.
+    pub fn plain(responses: Vec<Vec<u8>>) -> Self {
+        TestServer::start(responses, false, |stream| Box::new(stream))
+    }
+
+    /// Serves the responses over plain TCP, and closes the connection after
+    /// each response without announcing it.
+    pub fn plain_closing(responses: Vec<Vec<u8>>) -> Self {
+        TestServer::start(responses, true, |stream| Box::new(stream))
+    }
+
+    /// Serves the responses over TLS as `localhost`.
+    pub fn tls(responses: Vec<Vec<u8>>) -> Self {
+        TestServer::tls_with_config(server_config(false), responses)
+    }
+
+    /// Serves the responses over TLS to clients presenting a certificate
+    /// issued by the test CA.
+    pub fn tls_with_client_auth(responses: Vec<Vec<u8>>) -> Self {
+        TestServer::tls_with_config(server_config(true), responses)
+    }
+
+    fn tls_with_config(config: Arc<ServerConfig>, responses: Vec<Vec<u8>>) -> Self {
+        TestServer::start(responses, false, move |stream| {
+            Box::new(StreamOwned::new(ServerSession::new(&config), stream))
+        })
+    }
+
+    fn start<F>(responses: Vec<Vec<u8>>, close_each: bool, wrap: F) -> Self
+    where
+        F: Fn(TcpStream) -> Box<dyn Stream> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let mut connections = vec![];
+            let mut responses = responses.into_iter().peekable();
+            while responses.peek().is_some() {
+                let (stream, _) = listener.accept()?;
+                stream.set_read_timeout(Some(READ_TIMEOUT))?;
+                let mut stream = wrap(stream);
+                let mut requests = vec![];
+                for response in responses.by_ref() {
+                    let request = serve(&mut stream, &response)?;
+                    let close = close_each || closes(&request) || closes(&response);
+                    requests.push(request);
+                    if close {
+                        break;
+                    }
+                }
+                connections.push(requests);
+            }
+            Ok(connections)
+        });
+
+        TestServer { port, handle }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Waits until all responses are served, returning the requests.
+    pub fn requests(self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.connections()?.into_iter().flatten().collect())
+    }
+
+    /// Waits until all responses are served, returning the requests of
+    /// each connection.
+    pub fn connections(self) -> Result<Vec<Vec<Vec<u8>>>> {
+        self.handle.join().map_err(|_| anyhow!("Test server panicked"))?
+    }
+}
+
+pub(crate) trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+fn server_config(client_auth: bool) -> Arc<ServerConfig> {
+    let mut config = if client_auth {
+        let mut roots = RootCertStore::empty();
+        roots.add_pem_file(&mut BufReader::new(CA_PEM)).unwrap();
+        ServerConfig::new(AllowAnyAuthenticatedClient::new(roots))
+    } else {
+        ServerConfig::new(NoClientAuth::new())
+    };
+    let certs = pemfile::certs(&mut BufReader::new(SERVER_PEM)).unwrap();
+    let mut keys = pemfile::pkcs8_private_keys(&mut BufReader::new(SERVER_KEY)).unwrap();
+    config.set_single_cert(certs, keys.remove(0)).unwrap();
+    Arc::new(config)
+}
+
+/// Returns whether the head of the message has `Connection: close`.
+fn closes(message: &[u8]) -> bool {
+    let message = String::from_utf8_lossy(message).to_lowercase();
+    let head = message.split("\r\n\r\n").next().unwrap_or("");
+    head.lines().any(|line| line.starts_with("connection:") && line.contains("close"))
+}
+
+/// Reads a request with an optional `Content-Length` body, and writes the
+/// response.
+fn serve(stream: &mut Box<dyn Stream>, response: &[u8]) -> Result<Vec<u8>> {
+    let mut request = vec![];
+    let mut byte = [0u8; 1];
+    while !request.ends_with(b"\r\n\r\n") {
+        ensure!(stream.read(&mut byte)? == 1, "Client closed the connection");
+        request.push(byte[0]);
+    }
+    let head = str::from_utf8(&request)?.to_lowercase();
+    let content_length = head.lines()
+        .find(|line| line.starts_with("content-length:"))
+        .map(|line| line["content-length:".len()..].trim().parse::<usize>())
+        .transpose()?
+        .unwrap_or(0);
+    let mut body = vec![0u8; content_length];
+    stream.read_exact(&mut body)?;
+    request.extend_from_slice(&body);
+
+    stream.write_all(response)?;
+    stream.flush()?;
+    Ok(request)
+}
@@ -0,0 +1,264 @@
+use crate::std::{
+    vec::Vec,
+    io::BufReader,
+    time::SystemTime,
+    untrusted::time::SystemTimeEx,
+};
+use rustls::{
+    RootCertStore, ServerCertVerifier, ServerCertVerified, TLSError,
+    internal::pemfile,
+};
+use anyhow::{Result, anyhow, ensure};
+
+type SignatureAlgorithms = &'static [&'static webpki::SignatureAlgorithm];
+static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+    &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+    &webpki::RSA_PKCS1_2048_8192_SHA256,
+    &webpki::RSA_PKCS1_2048_8192_SHA384,
+    &webpki::RSA_PKCS1_2048_8192_SHA512,
+    &webpki::RSA_PKCS1_3072_8192_SHA384,
+];
+
+/// A DER-encoded X.509 certificate
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    der: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        // Fails early on anything which isn't a certificate.
+        spki(der)?;
+        Ok(Certificate { der: der.to_vec() })
+    }
+
+    /// Reads the first certificate of PEM-encoded data.
+    pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        Certificate::from_pem_bundle(pem)?
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("No certificate in PEM"))
+    }
+
+    /// Reads all certificates of PEM-encoded data, e.g. a CA bundle.
+    pub fn from_pem_bundle(pem: &[u8]) -> Result<Vec<Self>> {
+        pemfile::certs(&mut BufReader::new(pem))
+            .map_err(|_| anyhow!("Invalid PEM certificates"))?
+            .iter()
+            .map(|cert| Certificate::from_der(&cert.0))
+            .collect()
+    }
+
+    /// SHA-256 digest of the DER-encoded SubjectPublicKeyInfo, as pinned
+    /// by `ClientBuilder::pin_spki_sha256`
+    pub fn spki_sha256(&self) -> [u8; 32] {
+        spki_sha256(&self.der).expect("Certificate is checked on construction")
+    }
+
+    pub fn as_der(&self) -> &[u8] {
+        &self.der
+    }
+
+    pub(crate) fn to_rustls(&self) -> rustls::Certificate {
+        rustls::Certificate(self.der.clone())
+    }
+}
+
+/// A client certificate chain and its private key, for mutual TLS
+#[derive(Clone)]
+pub struct Identity {
+    pub(crate) cert_chain: Vec<rustls::Certificate>,
+    pub(crate) key: rustls::PrivateKey,
+}
+
+impl Identity {
+    /// Reads the certificate chain, leaf first, and a PKCS#8 or RSA private
+    /// key from PEM-encoded data.
+    pub fn from_pem(pem: &[u8]) -> Result<Self> {
+        let cert_chain = pemfile::certs(&mut BufReader::new(pem))
+            .map_err(|_| anyhow!("Invalid PEM certificates"))?;
+        ensure!(!cert_chain.is_empty(), "No certificate in PEM");
+
+        let mut keys = pemfile::pkcs8_private_keys(&mut BufReader::new(pem))
+            .map_err(|_| anyhow!("Invalid PEM private key"))?;
+        if keys.is_empty() {
+            keys = pemfile::rsa_private_keys(&mut BufReader::new(pem))
+                .map_err(|_| anyhow!("Invalid PEM private key"))?;
+        }
+        ensure!(keys.len() == 1, "Expected one private key in PEM, found {}", keys.len());
+
+        Ok(Identity {
+            cert_chain,
+            key: keys.remove(0),
+        })
+    }
+}
+
+/// Versions of TLS, which rustls supports from 1.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+impl TlsVersion {
+    pub(crate) fn to_rustls(self) -> rustls::ProtocolVersion {
+        match self {
+            TlsVersion::Tls12 => rustls::ProtocolVersion::TLSv1_2,
+            TlsVersion::Tls13 => rustls::ProtocolVersion::TLSv1_3,
+        }
+    }
+}
+
+/// Verifies server certificates as usual, and then requires the public key
+/// of the server's certificate to be one of the pinned ones.
+pub(crate) struct PinnedServerVerifier {
+    pub(crate) pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedServerVerifier {
+    fn verify_server_cert(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[rustls::Certificate],
+        dns_name: webpki::DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        let (end_entity, intermediates) = presented_certs.split_first()
+            .ok_or(TLSError::NoCertificatesPresented)?;
+        let cert = webpki::EndEntityCert::from(&end_entity.0)
+            .map_err(TLSError::WebPKIError)?;
+        let chain: Vec<&[u8]> = intermediates.iter().map(|cert| cert.0.as_ref()).collect();
+        let trust_anchors: Vec<webpki::TrustAnchor> = roots.roots.iter()
+            .map(|cert| cert.to_trust_anchor())
+            .collect();
+        let now = webpki::Time::try_from(SystemTime::now())
+            .map_err(|_| TLSError::FailedToGetCurrentTime)?;
+
+        cert.verify_is_valid_tls_server_cert(
+            SUPPORTED_SIG_ALGS,
+            &webpki::TLSServerTrustAnchors(&trust_anchors),
+            &chain,
+            now,
+        ).map_err(TLSError::WebPKIError)?;
+        cert.verify_is_valid_for_dns_name(dns_name)
+            .map_err(TLSError::WebPKIError)?;
+
+        let spki_sha256 = spki_sha256(&end_entity.0)
+            .map_err(|_| TLSError::WebPKIError(webpki::Error::BadDER))?;
+        if !self.pins.contains(&spki_sha256) {
+            return Err(TLSError::General("Server public key is not pinned".into()));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn spki_sha256(cert_der: &[u8]) -> Result<[u8; 32]> {
+    let digest = ring::digest::digest(&ring::digest::SHA256, spki(cert_der)?);
+    let mut sha256 = [0u8; 32];
+    sha256.copy_from_slice(digest.as_ref());
+    Ok(sha256)
+}
+
+/// Finds the DER-encoded SubjectPublicKeyInfo, with its tag and length, in a
+/// certificate.
+fn spki(cert_der: &[u8]) -> Result<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let (cert, rest) = der_value(cert_der, SEQUENCE)?;
+    ensure!(rest.is_empty(), "Trailing data after certificate");
+    let (mut tbs, _) = der_value(cert, SEQUENCE)?;
+    if tbs.first() == Some(&VERSION) {
+        tbs = der_tlv(tbs)?.1;
+    }
+    // serialNumber, signature, issuer, validity and subject
+    for _ in 0..5 {
+        tbs = der_tlv(tbs)?.1;
+    }
+    let (spki, _) = der_tlv(tbs)?;
+    ensure!(spki.first() == Some(&SEQUENCE), "Invalid SubjectPublicKeyInfo");
+    Ok(spki)
+}
+
+/// Splits a DER element of the tag off `input`, returning its value.
+fn der_value(input: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    let (tlv, rest) = der_tlv(input)?;
+    ensure!(tlv[0] == tag, "Unexpected DER tag: {:#x}", tlv[0]);
+    let header_len = tlv.len() - der_len(&tlv[1..])?.0;
+    Ok((&tlv[header_len..], rest))
+}
+
+/// Splits the next DER element, with its tag and length, off `input`.
+fn der_tlv(input: &[u8]) -> Result<(&[u8], &[u8])> {
+    ensure!(!input.is_empty(), "Unexpected end of DER");
+    let (len, len_len) = der_len(&input[1..])?;
+    let end = 1usize.checked_add(len_len).and_then(|n| n.checked_add(len))
+        .filter(|&end| end <= input.len())
+        .ok_or(anyhow!("Unexpected end of DER"))?;
+    Ok(input.split_at(end))
+}
+
+/// Decodes a definite length, returning it and the bytes it takes.
+fn der_len(input: &[u8]) -> Result<(usize, usize)> {
+    let first = *input.first().ok_or(anyhow!("Unexpected end of DER"))?;
+    if first < 0x80 {
+        return Ok((first as usize, 1));
+    }
+    let n = (first & 0x7f) as usize;
+    ensure!((1..=4).contains(&n) && input.len() > n, "Invalid DER length");
+    let len = input[1..=n].iter().fold(0usize, |len, &b| len << 8 | b as usize);
+    Ok((len, 1 + n))
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use crate::std::string::String;
+    use test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_spki_sha256,
+            test_read_pem_bundle,
+            test_read_identity,
+        )
+    }
+
+    fn test_spki_sha256() {
+        // openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | sha256sum
+        const SERVER_SPKI_SHA256: [u8; 32] = [
+            0x95, 0x40, 0xd8, 0x70, 0x64, 0xcf, 0x13, 0xb2, 0x25, 0x95, 0xc9, 0xf4, 0xd8, 0x70, 0xf1, 0x06,
+            0x58, 0x1d, 0x56, 0x5b, 0xf8, 0x3b, 0x6b, 0xbb, 0x44, 0xf8, 0x9f, 0x56, 0x66, 0xf1, 0x57, 0x71,
+        ];
+        let cert = Certificate::from_pem(include_bytes!("../testdata/server.pem")).unwrap();
+        assert_eq!(cert.spki_sha256(), SERVER_SPKI_SHA256);
+        assert!(spki(&cert.as_der()[..cert.as_der().len() - 1]).is_err());
+    }
+
+    fn test_read_pem_bundle() {
+        let mut bundle = include_bytes!("../testdata/ca.pem").to_vec();
+        bundle.extend_from_slice(include_bytes!("../testdata/server.pem"));
+        let certs = Certificate::from_pem_bundle(&bundle).unwrap();
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[1].as_der(), Certificate::from_pem(include_bytes!("../testdata/server.pem")).unwrap().as_der());
+    }
+
+    fn test_read_identity() {
+        let mut pem = include_bytes!("../testdata/client.pem").to_vec();
+        pem.extend_from_slice(include_bytes!("../testdata/client.key"));
+        let identity = Identity::from_pem(&pem).unwrap();
+        assert_eq!(identity.cert_chain.len(), 1);
+
+        // Two keys are ambiguous.
+        pem.extend_from_slice(include_bytes!("../testdata/server.key"));
+        assert!(Identity::from_pem(&pem).is_err());
+    }
+}
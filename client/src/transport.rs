@@ -1,53 +1,439 @@
 use crate::std::{
-    io::{Read, Write},
+    fmt,
     vec::Vec,
-    net::TcpStream,
+    string::String,
+    boxed::Box,
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::Duration,
 };
-use anyhow::{ensure, Result};
+use crate::{
+    parser::{Head, ResponseParser},
+    request::Request,
+};
+use http::{
+    Method,
+    header::{HeaderMap, CONNECTION},
+};
+use anyhow::{Result, anyhow, ensure};
 
-pub struct TlsTransport<S: rustls::Session> {
-    pub stream: rustls::StreamOwned<S, TcpStream>,
+/// Size of the buffer responses are read through
+const READ_BUF_LEN: usize = 8 * 1_024;
+
+/// Bounds on a response, which protect the enclave's memory from servers
+/// sending endless headers or bodies.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    /// Status lines and header fields, including those of interim responses
+    /// and trailers
+    pub max_header_len: usize,
+    /// The body, after decoding chunked transfer-encoding
+    pub max_body_len: usize,
 }
 
-impl<S: rustls::Session> TlsTransport<S> {
-    pub fn new(stream: rustls::StreamOwned<S, TcpStream>) -> Self {
-        TlsTransport { stream }
+impl Limits {
+    /// Bytes read off the wire, which allows for the framing of chunked bodies
+    fn max_frame_len(&self) -> usize {
+        self.max_header_len.saturating_add(self.max_body_len.saturating_mul(2))
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_header_len: 64 * 1_024,
+            max_body_len: 8 * 1_024 * 1_024,
+        }
     }
 }
 
-pub struct Message<'a, T>
+/// A connection to a server, which is kept alive between requests unless
+/// either side closes it.
+pub(crate) enum Connection {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientSession, TcpStream>>),
+    /// A connection to an HTTP proxy, which is sent plaintext requests in
+    /// absolute-form with the proxy's `Proxy-Authorization`, if any.
+    Proxied(TcpStream, Option<String>),
+}
+
+/// Connects to the first of `addrs` that accepts, giving each `timeout`.
+pub fn connect(addrs: &[SocketAddr], timeout: Option<Duration>) -> Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addrs {
+        let result = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    match last_err {
+        Some(e) => Err(e.into()),
+        None => Err(anyhow!("no addresses to connect to")),
+    }
+}
+
+/// Bounds each read and write on `stream` by `timeout`, or lifts the
+/// bound with `None`.
+pub fn set_timeout(stream: &TcpStream, timeout: Option<Duration>) -> Result<()> {
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    Ok(())
+}
+
+impl Connection {
+    /// Bounds each read and write on the connection by `timeout`.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        match self {
+            Connection::Plain(stream) => set_timeout(stream, timeout),
+            Connection::Tls(stream) => set_timeout(&stream.sock, timeout),
+            Connection::Proxied(stream, _) => set_timeout(stream, timeout),
+        }
+    }
+
+    /// Sends the request and reads the head of its response, leaving the
+    /// body to read off the returned message.
+    ///
+    /// Fails with `StaleConnection` as the context if the connection was
+    /// `reused` and ended before any of the response arrived, and the request
+    /// is safe to send again: either writing it failed, or its method is
+    /// idempotent. The server may have acted on a request it got in full.
+    pub fn send(self, req: &Request, limits: Limits, reused: bool) -> Result<(Head, Message<Connection>)> {
+        let bytes = match self {
+            Connection::Proxied(_, ref auth) => req.to_proxy_bytes(auth.as_deref()),
+            _ => req.to_bytes(),
+        };
+        let mut msg = Message::new(self, limits);
+        if let Err(e) = msg.write(&bytes) {
+            return Err(if reused { e.context(StaleConnection) } else { e });
+        }
+        match msg.read_head(req.method() == Method::HEAD) {
+            Ok(head) => Ok((head, msg)),
+            Err(e) if reused && msg.received == 0 && is_idempotent(req.method()) => {
+                Err(e.context(StaleConnection))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.read(buf),
+            Connection::Tls(stream) => stream.read(buf),
+            Connection::Proxied(stream, _) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.write(buf),
+            Connection::Tls(stream) => stream.write(buf),
+            Connection::Proxied(stream, _) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Plain(stream) => stream.flush(),
+            Connection::Tls(stream) => stream.flush(),
+            Connection::Proxied(stream, _) => stream.flush(),
+        }
+    }
+}
+
+/// The connection ended before any of the response arrived, as when the
+/// server closed it while it was idle.
+#[derive(Debug)]
+pub(crate) struct StaleConnection;
+
+impl fmt::Display for StaleConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Connection closed before the response")
+    }
+}
+
+/// Returns whether sending a request of the method twice has the same effect
+/// as sending it once.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Returns whether the `Connection` header asks to close the connection.
+pub(crate) fn closes(headers: &HeaderMap) -> bool {
+    headers.get_all(CONNECTION).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|option| option.trim().eq_ignore_ascii_case("close"))
+}
+
+/// An exchange of a request and its response over a transport, which reads
+/// the response body as it is consumed.
+pub(crate) struct Message<T>
 where
     T: Read + Write,
 {
-    transport: &'a mut T,
+    transport: T,
+    limits: Limits,
     max_frame_len: usize,
+    /// Bytes of the response read so far
+    received: usize,
+    parser: Option<ResponseParser>,
+    /// Decoded body not yet consumed, from `pos`
+    body: Vec<u8>,
+    pos: usize,
 }
 
-impl<T> Message<'_, T>
+impl<T> Message<T>
 where
     T: Read + Write,
 {
-    pub fn new(transport: &'_ mut T) -> Message<'_, T> {
+    pub fn new(transport: T, limits: Limits) -> Message<T> {
         Message {
             transport,
-            max_frame_len: 8 * 1_024 * 1_024,
+            limits,
+            max_frame_len: limits.max_frame_len(),
+            received: 0,
+            parser: None,
+            body: vec![],
+            pos: 0,
         }
     }
 
-    pub fn read(&mut self) -> Result<Vec<u8>> {
-        let mut buf = vec![];
-        self.transport.read_exact(&mut buf)?;
+    pub fn write(&mut self, buf: &[u8]) -> Result<()>
+    {
+        self.transport.write_all(buf)?;
+        self.transport.flush()?;
 
-        ensure!(buf.len() < self.max_frame_len, "Exceed max frame length");
+        Ok(())
+    }
 
-        Ok(buf)
+    /// Reads the response until the end of its head.
+    pub fn read_head(&mut self, head_request: bool) -> Result<Head> {
+        self.parser = Some(ResponseParser::new(head_request, self.limits));
+        loop {
+            if let Some(head) = self.parser_mut()?.take_head() {
+                return Ok(head);
+            }
+            self.fill()?;
+        }
     }
 
-    pub fn write(&mut self, buf: &[u8]) -> Result<()>
-    {
-        self.transport.write_all(&buf)?;
-        self.transport.flush()?;
+    /// Reads the next bytes of the body into `buf`, or returns 0 once the
+    /// body is complete.
+    pub fn read_body(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while self.pos == self.body.len() {
+            if self.parser_mut()?.is_done() {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = buf.len().min(self.body.len() - self.pos);
+        buf[..n].copy_from_slice(&self.body[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    /// Reads the rest of the body.
+    pub fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        let mut body = self.body.split_off(self.pos);
+        while !self.parser_mut()?.is_done() {
+            self.fill()?;
+            body.append(&mut self.body);
+        }
+        self.pos = self.body.len();
+        Ok(body)
+    }
+
+    /// Returns whether the server keeps the connection open for further
+    /// requests, once the response is complete.
+    pub fn keep_alive(&self) -> bool {
+        matches!(self.parser, Some(ref parser) if parser.keep_alive())
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
 
+    fn parser_mut(&mut self) -> Result<&mut ResponseParser> {
+        self.parser.as_mut().ok_or_else(|| anyhow!("No response to read"))
+    }
+
+    /// Reads off the transport once, and parses what arrived. Bodies
+    /// delimited by the end of the connection end at EOF.
+    fn fill(&mut self) -> Result<()> {
+        let mut buf = [0u8; READ_BUF_LEN];
+        // Reading one byte past the limit tells an over-long response
+        // from one which ends right at it.
+        let len = (self.max_frame_len - self.received).saturating_add(1).min(buf.len());
+        let n = loop {
+            match self.transport.read(&mut buf[..len]) {
+                Ok(n) => break n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        let parser = self.parser.as_mut().ok_or_else(|| anyhow!("No response to read"))?;
+        if n == 0 {
+            parser.eof()?;
+        } else {
+            self.received += n;
+            ensure!(self.received <= self.max_frame_len, "Exceed max frame length");
+            parser.feed(&buf[..n])?;
+        }
+
+        let mut body = parser.take_body();
+        if self.pos == self.body.len() {
+            self.body = body;
+            self.pos = 0;
+        } else {
+            self.body.append(&mut body);
+        }
         Ok(())
     }
 }
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use crate::std::{io, string::{String, ToString}, vec::Vec};
+    use test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_read_until_complete,
+            test_read_until_eof,
+            test_enforce_max_frame_len,
+            test_read_body_incrementally,
+        )
+    }
+
+    /// A connection which returns the response a few bytes per read, and
+    /// fails reads past its end unless the server closed it.
+    struct MockStream {
+        response: Vec<u8>,
+        pos: usize,
+        closed: bool,
+        written: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(response: &[u8], closed: bool) -> Self {
+            MockStream {
+                response: response.to_vec(),
+                pos: 0,
+                closed,
+                written: vec![],
+            }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pos == self.response.len() && !self.closed {
+                return Err(io::Error::new(ErrorKind::WouldBlock, "read past the response"));
+            }
+            let n = buf.len().min(self.response.len() - self.pos).min(7);
+            buf[..n].copy_from_slice(&self.response[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_read_until_complete() {
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world", false);
+        let mut msg = Message::new(&mut stream, Limits::default());
+        msg.write(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(msg.read_head(false).unwrap().status, 200);
+        assert!(!msg.keep_alive());
+        assert_eq!(msg.read_to_end().unwrap(), b"hello world");
+        assert!(msg.keep_alive());
+        assert_eq!(stream.written, b"GET / HTTP/1.1\r\n\r\n");
+    }
+
+    fn test_read_until_eof() {
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\n\r\nuntil the end", true);
+        let mut msg = Message::new(&mut stream, Limits::default());
+        msg.read_head(false).unwrap();
+        assert_eq!(msg.read_to_end().unwrap(), b"until the end");
+        assert!(!msg.keep_alive());
+
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\nContent-Length: 20\r\n\r\ntruncated", true);
+        let mut msg = Message::new(&mut stream, Limits::default());
+        msg.read_head(false).unwrap();
+        assert!(msg.read_to_end().is_err());
+
+        let mut stream = MockStream::new(b"HTTP/1.1 200 OK\r\nContent-", true);
+        assert!(Message::new(&mut stream, Limits::default()).read_head(false).is_err());
+    }
+
+    fn test_enforce_max_frame_len() {
+        // Chunk framing of one-byte chunks takes more than the body itself.
+        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for _ in 0..8 {
+            response.extend_from_slice(b"1\r\na\r\n");
+        }
+        response.extend_from_slice(b"0\r\n\r\n");
+        let limits = Limits { max_header_len: 64, max_body_len: 8 };
+
+        let mut stream = MockStream::new(&response, false);
+        let mut msg = Message::new(&mut stream, limits);
+        let err = msg.read_head(false).and_then(|_| msg.read_to_end()).unwrap_err();
+        assert_eq!(err.to_string(), "Exceed max frame length");
+        assert!(stream.pos <= limits.max_frame_len() + 1);
+    }
+
+    fn test_read_body_incrementally() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n1;ext=1\r\n \r\n5\r\nworld\r\n0\r\n\r\n";
+        let mut stream = MockStream::new(response, false);
+        let mut msg = Message::new(&mut stream, Limits::default());
+        msg.read_head(false).unwrap();
+        let mut body = vec![];
+        let mut buf = [0u8; 3];
+        loop {
+            let n = msg.read_body(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(body, b"hello world");
+        assert!(msg.keep_alive());
+        assert_eq!(msg.read_body(&mut buf).unwrap(), 0);
+
+        let limits = Limits { max_header_len: 1_024, max_body_len: 8 };
+        let mut stream = MockStream::new(response, false);
+        let mut msg = Message::new(&mut stream, limits);
+        msg.read_head(false).unwrap();
+        let mut read = 0;
+        let err = loop {
+            match msg.read_body(&mut buf) {
+                Ok(n) => read += n,
+                Err(e) => break e,
+            }
+        };
+        assert!(read <= 8);
+        assert_eq!(err.to_string(), "Response body exceeds 8 bytes");
+    }
+}
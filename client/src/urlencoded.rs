@@ -0,0 +1,461 @@
+use crate::std::{
+    fmt,
+    vec::Vec,
+    string::{String, ToString},
+};
+use serde::ser::{self, Impossible, Serialize};
+
+/// Flattens a value into name-value pairs to url-encode, in the order they
+/// are serialized: a struct of scalars in the order its fields are declared
+/// in, a map in its iteration order, or a sequence of pairs. `None` values
+/// are left out.
+pub(crate) fn to_pairs<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<Vec<(String, String)>> {
+    let mut pairs = vec![];
+    value.serialize(PairsSerializer { pairs: &mut pairs })?;
+    Ok(pairs)
+}
+
+#[derive(Debug)]
+struct Error(String);
+
+type Result<T> = crate::std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl crate::std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str, kind: &str) -> Result<T> {
+    Err(Error(format!("Cannot url-encode {} as {}", kind, what)))
+}
+
+/// Serializes the pairs of a struct, a map or a sequence.
+struct PairsSerializer<'a> {
+    pairs: &'a mut Vec<(String, String)>,
+}
+
+/// Serializes a scalar into `Some` string, or `None` for a unit or `None`.
+struct ScalarSerializer;
+
+/// Serializes a name-value pair in a sequence of pairs.
+struct PairSerializer<'a> {
+    pairs: &'a mut Vec<(String, String)>,
+}
+
+struct MapSerializer<'a> {
+    pairs: &'a mut Vec<(String, String)>,
+    name: Option<String>,
+}
+
+struct PairElements<'a> {
+    pairs: &'a mut Vec<(String, String)>,
+    name: Option<String>,
+    value: Option<Option<String>>,
+    len: usize,
+}
+
+macro_rules! reject {
+    ($what:expr; $($method:ident($($ty:ty),*) -> $ok:ty: $kind:expr;)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<$ok> {
+                unsupported($what, $kind)
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for PairsSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    reject! { "pairs";
+        serialize_bool(bool) -> (): "a boolean";
+        serialize_i8(i8) -> (): "an integer";
+        serialize_i16(i16) -> (): "an integer";
+        serialize_i32(i32) -> (): "an integer";
+        serialize_i64(i64) -> (): "an integer";
+        serialize_u8(u8) -> (): "an integer";
+        serialize_u16(u16) -> (): "an integer";
+        serialize_u32(u32) -> (): "an integer";
+        serialize_u64(u64) -> (): "an integer";
+        serialize_f32(f32) -> (): "a float";
+        serialize_f64(f64) -> (): "a float";
+        serialize_char(char) -> (): "a character";
+        serialize_str(&str) -> (): "a string";
+        serialize_bytes(&[u8]) -> (): "bytes";
+        serialize_none() -> (): "none";
+        serialize_unit() -> (): "a unit";
+        serialize_unit_struct(&'static str) -> (): "a unit struct";
+        serialize_unit_variant(&'static str, u32, &'static str) -> (): "an enum";
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant: "an enum";
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant: "an enum";
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<()> {
+        unsupported("pairs", "an enum")
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<MapSerializer<'a>> {
+        Ok(MapSerializer { pairs: self.pairs, name: None })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self> {
+        Ok(self)
+    }
+}
+
+impl<'a> ser::SerializeSeq for PairsSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(PairSerializer { pairs: self.pairs })
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for PairsSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for PairsSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for PairsSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<()> {
+        if let Some(value) = value.serialize(ScalarSerializer)? {
+            self.pairs.push((name.to_string(), value));
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for MapSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        let name = key.serialize(ScalarSerializer)?
+            .ok_or_else(|| Error("Cannot url-encode a null name".to_string()))?;
+        self.name = Some(name);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let name = self.name.take()
+            .ok_or_else(|| Error("Map value serialized before its key".to_string()))?;
+        if let Some(value) = value.serialize(ScalarSerializer)? {
+            self.pairs.push((name, value));
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for PairSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = PairElements<'a>;
+    type SerializeTuple = PairElements<'a>;
+    type SerializeTupleStruct = PairElements<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    reject! { "a pair";
+        serialize_bool(bool) -> (): "a boolean";
+        serialize_i8(i8) -> (): "an integer";
+        serialize_i16(i16) -> (): "an integer";
+        serialize_i32(i32) -> (): "an integer";
+        serialize_i64(i64) -> (): "an integer";
+        serialize_u8(u8) -> (): "an integer";
+        serialize_u16(u16) -> (): "an integer";
+        serialize_u32(u32) -> (): "an integer";
+        serialize_u64(u64) -> (): "an integer";
+        serialize_f32(f32) -> (): "a float";
+        serialize_f64(f64) -> (): "a float";
+        serialize_char(char) -> (): "a character";
+        serialize_str(&str) -> (): "a string";
+        serialize_bytes(&[u8]) -> (): "bytes";
+        serialize_none() -> (): "none";
+        serialize_unit() -> (): "a unit";
+        serialize_unit_struct(&'static str) -> (): "a unit struct";
+        serialize_unit_variant(&'static str, u32, &'static str) -> (): "an enum";
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant: "an enum";
+        serialize_map(Option<usize>) -> Self::SerializeMap: "a map";
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct: "a struct";
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant: "an enum";
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<()> {
+        unsupported("a pair", "an enum")
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<PairElements<'a>> {
+        Ok(PairElements { pairs: self.pairs, name: None, value: None, len: 0 })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<PairElements<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<PairElements<'a>> {
+        self.serialize_seq(Some(len))
+    }
+}
+
+impl<'a> ser::SerializeSeq for PairElements<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, element: &T) -> Result<()> {
+        let element = element.serialize(ScalarSerializer)?;
+        match self.len {
+            0 => {
+                let name = element.ok_or_else(|| Error("Cannot url-encode a null name".to_string()))?;
+                self.name = Some(name);
+            }
+            1 => self.value = Some(element),
+            _ => {}
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        match (self.name, self.value) {
+            (Some(name), Some(value)) if self.len == 2 => {
+                if let Some(value) = value {
+                    self.pairs.push((name, value));
+                }
+                Ok(())
+            }
+            _ => Err(Error(format!("Cannot url-encode a sequence of {} as a pair", self.len))),
+        }
+    }
+}
+
+impl<'a> ser::SerializeTuple for PairElements<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, element: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, element)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for PairElements<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, element: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, element)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::Serializer for ScalarSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+    type SerializeSeq = Impossible<Option<String>, Error>;
+    type SerializeTuple = Impossible<Option<String>, Error>;
+    type SerializeTupleStruct = Impossible<Option<String>, Error>;
+    type SerializeTupleVariant = Impossible<Option<String>, Error>;
+    type SerializeMap = Impossible<Option<String>, Error>;
+    type SerializeStruct = Impossible<Option<String>, Error>;
+    type SerializeStructVariant = Impossible<Option<String>, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Option<String>> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    // Formatted as in JSON, e.g. `1.0`, and left out if not finite.
+    fn serialize_f64(self, v: f64) -> Result<Option<String>> {
+        Ok(serde_json::Number::from_f64(v).map(|n| n.to_string()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Option<String>> {
+        Ok(Some(v.to_string()))
+    }
+
+    fn serialize_none(self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<String>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Option<String>> {
+        Ok(Some(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<Option<String>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Option<String>> {
+        unsupported("a value", "an enum")
+    }
+
+    reject! { "a value";
+        serialize_bytes(&[u8]) -> Option<String>: "bytes";
+        serialize_seq(Option<usize>) -> Self::SerializeSeq: "a nested sequence";
+        serialize_tuple(usize) -> Self::SerializeTuple: "a nested tuple";
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct: "a nested tuple";
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant: "an enum";
+        serialize_map(Option<usize>) -> Self::SerializeMap: "a nested map";
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct: "a nested struct";
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant: "an enum";
+    }
+}
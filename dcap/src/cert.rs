@@ -0,0 +1,72 @@
+use alloc::{string::String, vec::Vec};
+use crate::der::{self, DerReader, TAG_INTEGER};
+use crate::error::{Error, Result};
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// Decodes concatenated PEM certificates into DER, keeping their order.
+pub fn pem_to_der_chain(pem: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let pem = core::str::from_utf8(pem).map_err(|_| Error::InvalidPem)?;
+    // Certification data may be NUL-terminated.
+    let mut rest = pem.trim_end_matches('\0').trim();
+    let mut chain = vec![];
+    while !rest.is_empty() {
+        if !rest.starts_with(PEM_BEGIN) {
+            return Err(Error::InvalidPem);
+        }
+        let end = rest.find(PEM_END).ok_or(Error::InvalidPem)?;
+        let body: String = rest[PEM_BEGIN.len()..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        chain.push(base64::decode(&body).map_err(|_| Error::InvalidPem)?);
+        rest = rest[end + PEM_END.len()..].trim_start();
+    }
+    if chain.is_empty() {
+        return Err(Error::InvalidPem);
+    }
+
+    Ok(chain)
+}
+
+static SUPPORTED_SIG_ALGS: &[&webpki::SignatureAlgorithm] = &[&webpki::ECDSA_P256_SHA256];
+
+/// Verifies that `chain`, from the end-entity certificate up, leads to
+/// `root_ca` and is valid at `now`, in seconds since the Unix epoch.
+pub(crate) fn verify_cert_chain(
+    chain: &[Vec<u8>],
+    root_ca: &[u8],
+    now: u64,
+) -> core::result::Result<(), webpki::Error> {
+    let anchors = [webpki::trust_anchor_util::cert_der_as_trust_anchor(root_ca)?];
+    let intermediates: Vec<&[u8]> = chain[1..].iter().map(|c| &c[..]).collect();
+    webpki::EndEntityCert::from(&chain[0])?.verify_is_valid_tls_server_cert(
+        SUPPORTED_SIG_ALGS,
+        &webpki::TLSServerTrustAnchors(&anchors),
+        &intermediates,
+        webpki::Time::from_seconds_since_unix_epoch(now),
+    )
+}
+
+/// Verifies a raw `r || s` ECDSA P-256 signature with the key of `cert`.
+pub(crate) fn verify_signature(cert: &[u8], msg: &[u8], signature: &[u8]) -> bool {
+    verify_asn1_signature(cert, msg, &der::ecdsa_signature(signature))
+}
+
+/// Verifies an ASN.1 `Ecdsa-Sig-Value` P-256 signature with the key of
+/// `cert`, which may be a CA certificate.
+pub(crate) fn verify_asn1_signature(cert: &[u8], msg: &[u8], signature: &[u8]) -> bool {
+    webpki::EndEntityCert::from(cert)
+        .and_then(|cert| cert.verify_signature(&webpki::ECDSA_P256_SHA256, msg, signature))
+        .is_ok()
+}
+
+/// The content of the serial number INTEGER of a DER-encoded certificate
+pub(crate) fn serial_number(cert: &[u8]) -> Result<&[u8]> {
+    let mut tbs = DerReader::new(cert)
+        .read_sequence("certificate")?
+        .read_sequence("tbsCertificate")?;
+    tbs.read_optional(0xa0, "version")?;
+    tbs.read(TAG_INTEGER, "serialNumber")
+}
@@ -0,0 +1,576 @@
+use alloc::{string::{String, ToString}, vec::Vec};
+use serde::Deserialize;
+use serde_json::{Map, Value, value::RawValue};
+use crate::cert::{pem_to_der_chain, verify_cert_chain, verify_signature};
+use crate::error::{Error, Result};
+use crate::quote::TeeType;
+use crate::tcb::TcbStatus;
+
+pub(crate) const TCB_INFO: &str = "TCB info";
+pub(crate) const QE_IDENTITY: &str = "QE identity";
+pub(crate) const PCK_CRL: &str = "PCK CRL";
+pub(crate) const ROOT_CA_CRL: &str = "Root CA CRL";
+
+/// The SVNs a platform must have to be at a TCB level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcbComponents {
+    pub sgx_tcb_comp_svn: [u8; 16],
+    pub pce_svn: u16,
+    /// Only in TDX TCB info
+    pub tdx_tcb_comp_svn: Option<[u8; 16]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcbLevel {
+    pub tcb: TcbComponents,
+    pub tcb_date: String,
+    pub tcb_status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+/// TCB info of a platform model (FMSPC), version 2 or 3.
+///
+/// Dates are in seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcbInfo {
+    pub tee_type: TeeType,
+    pub version: u32,
+    pub issue_date: u64,
+    pub next_update: u64,
+    pub fmspc: [u8; 6],
+    pub pce_id: [u8; 2],
+    pub tcb_type: u32,
+    pub tcb_evaluation_data_number: u32,
+    /// TCB levels, from the highest
+    pub tcb_levels: Vec<TcbLevel>,
+}
+
+#[derive(Deserialize)]
+struct SignedTcbInfo<'a> {
+    #[serde(rename = "tcbInfo", borrow)]
+    tcb_info: &'a RawValue,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTcbInfo {
+    id: Option<String>,
+    version: u32,
+    issue_date: String,
+    next_update: String,
+    fmspc: String,
+    pce_id: String,
+    tcb_type: u32,
+    tcb_evaluation_data_number: u32,
+    tcb_levels: Vec<RawTcbLevel>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTcbLevel {
+    tcb: Map<String, Value>,
+    tcb_date: String,
+    tcb_status: String,
+    #[serde(rename = "advisoryIDs", default)]
+    advisory_ids: Vec<String>,
+}
+
+impl TcbInfo {
+    /// Decodes TCB info JSON as served by PCS, without verifying it.
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        let signed: SignedTcbInfo = from_json(TCB_INFO, json)?;
+        Self::parse_body(signed.tcb_info.get())
+    }
+
+    /// Verifies the signature with the TCB signing certificate chain, which
+    /// must lead to `root_ca`, and that the TCB info is current at `now`.
+    ///
+    /// `issuer_chain` is the PEM-encoded `TCB-Info-Issuer-Chain`.
+    pub fn verify(json: &[u8], issuer_chain: &[u8], root_ca: &[u8], now: u64) -> Result<Self> {
+        let signed: SignedTcbInfo = from_json(TCB_INFO, json)?;
+        let body = signed.tcb_info.get();
+        verify_collateral_signature(TCB_INFO, body, &signed.signature, issuer_chain, root_ca, now)?;
+        let tcb_info = Self::parse_body(body)?;
+        check_dates(TCB_INFO, tcb_info.issue_date, tcb_info.next_update, now)?;
+
+        Ok(tcb_info)
+    }
+
+    fn parse_body(body: &str) -> Result<Self> {
+        let raw: RawTcbInfo = from_json(TCB_INFO, body.as_bytes())?;
+        if raw.version != 2 && raw.version != 3 {
+            return Err(Error::UnsupportedCollateralVersion { collateral: TCB_INFO, version: raw.version });
+        }
+        // Version 2 is for SGX only.
+        let tee_type = match raw.id.as_deref() {
+            None | Some("SGX") => TeeType::Sgx,
+            Some("TDX") => TeeType::Tdx,
+            Some(_) => return Err(invalid(TCB_INFO, "id")),
+        };
+        let tcb_levels = raw.tcb_levels.into_iter()
+            .map(|level| Ok(TcbLevel {
+                tcb: tcb_components(&level.tcb)?,
+                tcb_date: level.tcb_date,
+                tcb_status: TcbStatus::from(level.tcb_status.as_str()),
+                advisory_ids: level.advisory_ids,
+            }))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TcbInfo {
+            tee_type,
+            version: raw.version,
+            issue_date: parse_date(TCB_INFO, &raw.issue_date)?,
+            next_update: parse_date(TCB_INFO, &raw.next_update)?,
+            fmspc: array!(&hex_field(TCB_INFO, "fmspc", &raw.fmspc, 6)?, 6),
+            pce_id: array!(&hex_field(TCB_INFO, "pceId", &raw.pce_id, 2)?, 2),
+            tcb_type: raw.tcb_type,
+            tcb_evaluation_data_number: raw.tcb_evaluation_data_number,
+            tcb_levels,
+        })
+    }
+}
+
+/// Decodes `tcb` of a TCB level, which lists the SVNs as `sgxtcbcompNNsvn`
+/// in version 2, and as `sgxtcbcomponents` and `tdxtcbcomponents` arrays in
+/// version 3.
+fn tcb_components(tcb: &Map<String, Value>) -> Result<TcbComponents> {
+    let svn = |value: Option<&Value>| {
+        value.and_then(Value::as_u64).filter(|svn| *svn <= 0xff).map(|svn| svn as u8)
+            .ok_or_else(|| invalid(TCB_INFO, "TCB component SVN"))
+    };
+    let components = |value: &Value| {
+        let array = value.as_array().filter(|a| a.len() == 16)
+            .ok_or_else(|| invalid(TCB_INFO, "TCB components"))?;
+        let mut svns = [0; 16];
+        for (svn_out, component) in svns.iter_mut().zip(array) {
+            *svn_out = svn(component.get("svn"))?;
+        }
+        Ok(svns)
+    };
+
+    let sgx_tcb_comp_svn = match tcb.get("sgxtcbcomponents") {
+        Some(value) => components(value)?,
+        None => {
+            let mut svns = [0; 16];
+            for (i, svn_out) in svns.iter_mut().enumerate() {
+                *svn_out = svn(tcb.get(&format!("sgxtcbcomp{:02}svn", i + 1)))?;
+            }
+            svns
+        }
+    };
+    let tdx_tcb_comp_svn = match tcb.get("tdxtcbcomponents") {
+        Some(value) => Some(components(value)?),
+        None => None,
+    };
+    let pce_svn = tcb.get("pcesvn").and_then(Value::as_u64).filter(|svn| *svn <= 0xffff)
+        .ok_or_else(|| invalid(TCB_INFO, "pcesvn"))? as u16;
+
+    Ok(TcbComponents {
+        sgx_tcb_comp_svn,
+        pce_svn,
+        tdx_tcb_comp_svn,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QeTcbLevel {
+    pub isv_svn: u16,
+    pub tcb_date: String,
+    pub tcb_status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+/// Identity of the Quoting Enclave, version 2.
+///
+/// Dates are in seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QeIdentity {
+    /// `QE` for SGX, `TD_QE` for TDX
+    pub id: String,
+    pub version: u32,
+    pub issue_date: u64,
+    pub next_update: u64,
+    pub tcb_evaluation_data_number: u32,
+    pub miscselect: u32,
+    pub miscselect_mask: u32,
+    pub attributes: [u8; 16],
+    pub attributes_mask: [u8; 16],
+    pub mrsigner: [u8; 32],
+    pub isv_prod_id: u16,
+    /// TCB levels, from the highest
+    pub tcb_levels: Vec<QeTcbLevel>,
+}
+
+#[derive(Deserialize)]
+struct SignedQeIdentity<'a> {
+    #[serde(rename = "enclaveIdentity", borrow)]
+    enclave_identity: &'a RawValue,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawQeIdentity {
+    id: String,
+    version: u32,
+    issue_date: String,
+    next_update: String,
+    tcb_evaluation_data_number: u32,
+    miscselect: String,
+    miscselect_mask: String,
+    attributes: String,
+    attributes_mask: String,
+    mrsigner: String,
+    isvprodid: u16,
+    tcb_levels: Vec<RawQeTcbLevel>,
+}
+
+#[derive(Deserialize)]
+struct RawQeTcb {
+    isvsvn: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawQeTcbLevel {
+    tcb: RawQeTcb,
+    tcb_date: String,
+    tcb_status: String,
+    #[serde(rename = "advisoryIDs", default)]
+    advisory_ids: Vec<String>,
+}
+
+impl QeIdentity {
+    /// Decodes QE identity JSON as served by PCS, without verifying it.
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        let signed: SignedQeIdentity = from_json(QE_IDENTITY, json)?;
+        Self::parse_body(signed.enclave_identity.get())
+    }
+
+    /// Verifies the signature with the TCB signing certificate chain, which
+    /// must lead to `root_ca`, and that the QE identity is current at `now`.
+    ///
+    /// `issuer_chain` is the PEM-encoded `SGX-Enclave-Identity-Issuer-Chain`.
+    pub fn verify(json: &[u8], issuer_chain: &[u8], root_ca: &[u8], now: u64) -> Result<Self> {
+        let signed: SignedQeIdentity = from_json(QE_IDENTITY, json)?;
+        let body = signed.enclave_identity.get();
+        verify_collateral_signature(QE_IDENTITY, body, &signed.signature, issuer_chain, root_ca, now)?;
+        let qe_identity = Self::parse_body(body)?;
+        check_dates(QE_IDENTITY, qe_identity.issue_date, qe_identity.next_update, now)?;
+
+        Ok(qe_identity)
+    }
+
+    fn parse_body(body: &str) -> Result<Self> {
+        let raw: RawQeIdentity = from_json(QE_IDENTITY, body.as_bytes())?;
+        if raw.version != 2 {
+            return Err(Error::UnsupportedCollateralVersion { collateral: QE_IDENTITY, version: raw.version });
+        }
+        // MISCSELECT is hex-encoded big endian.
+        let u32_field = |field, value: &str| {
+            hex_field(QE_IDENTITY, field, value, 4).map(|b| u32::from_be_bytes(array!(&b, 4)))
+        };
+
+        Ok(QeIdentity {
+            id: raw.id,
+            version: raw.version,
+            issue_date: parse_date(QE_IDENTITY, &raw.issue_date)?,
+            next_update: parse_date(QE_IDENTITY, &raw.next_update)?,
+            tcb_evaluation_data_number: raw.tcb_evaluation_data_number,
+            miscselect: u32_field("miscselect", &raw.miscselect)?,
+            miscselect_mask: u32_field("miscselectMask", &raw.miscselect_mask)?,
+            attributes: array!(&hex_field(QE_IDENTITY, "attributes", &raw.attributes, 16)?, 16),
+            attributes_mask: array!(&hex_field(QE_IDENTITY, "attributesMask", &raw.attributes_mask, 16)?, 16),
+            mrsigner: array!(&hex_field(QE_IDENTITY, "mrsigner", &raw.mrsigner, 32)?, 32),
+            isv_prod_id: raw.isvprodid,
+            tcb_levels: raw.tcb_levels.into_iter()
+                .map(|level| QeTcbLevel {
+                    isv_svn: level.tcb.isvsvn,
+                    tcb_date: level.tcb_date,
+                    tcb_status: TcbStatus::from(level.tcb_status.as_str()),
+                    advisory_ids: level.advisory_ids,
+                })
+                .collect(),
+        })
+    }
+}
+
+fn invalid(collateral: &'static str, reason: &str) -> Error {
+    Error::InvalidCollateral {
+        collateral,
+        reason: reason.to_string(),
+    }
+}
+
+fn from_json<'a, T: Deserialize<'a>>(collateral: &'static str, json: &'a [u8]) -> Result<T> {
+    serde_json::from_slice(json).map_err(|e| invalid(collateral, &e.to_string()))
+}
+
+/// Verifies the signature over the exact JSON text of the body.
+fn verify_collateral_signature(
+    collateral: &'static str,
+    body: &str,
+    signature: &str,
+    issuer_chain: &[u8],
+    root_ca: &[u8],
+    now: u64,
+) -> Result<()> {
+    let chain = pem_to_der_chain(issuer_chain)?;
+    verify_cert_chain(&chain, root_ca, now)
+        .map_err(|e| Error::InvalidCollateralCertChain { collateral, reason: format!("{:?}", e) })?;
+    let signature = hex_field(collateral, "signature", signature, 64)?;
+    if !verify_signature(&chain[0], body.as_bytes(), &signature) {
+        return Err(Error::InvalidCollateralSignature(collateral));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn check_dates(collateral: &'static str, issue_date: u64, next_update: u64, now: u64) -> Result<()> {
+    if now < issue_date {
+        return Err(Error::CollateralNotYetValid(collateral));
+    }
+    if now >= next_update {
+        return Err(Error::CollateralExpired(collateral));
+    }
+    Ok(())
+}
+
+fn hex_field(collateral: &'static str, field: &str, value: &str, len: usize) -> Result<Vec<u8>> {
+    hex_decode(value).filter(|b| b.len() == len).ok_or_else(|| invalid(collateral, field))
+}
+
+pub(crate) fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    fn nibble(c: u8) -> Option<u8> {
+        match c {
+            b'0'..=b'9' => Some(c - b'0'),
+            b'a'..=b'f' => Some(c - b'a' + 10),
+            b'A'..=b'F' => Some(c - b'A' + 10),
+            _ => None,
+        }
+    }
+    s.as_bytes().chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => Some(nibble(*hi)? << 4 | nibble(*lo)?),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parses an RFC 3339 UTC date such as `2020-01-01T00:00:00Z` into seconds
+/// since the Unix epoch. Fractional seconds are truncated.
+fn parse_date(collateral: &'static str, date: &str) -> Result<u64> {
+    let invalid_date = || invalid(collateral, "date");
+    let b = date.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[10] != b'T' || b[13] != b':' || b[16] != b':'
+        || b[b.len() - 1] != b'Z'
+    {
+        return Err(invalid_date());
+    }
+    let fraction = &b[19..b.len() - 1];
+    if !fraction.is_empty() && (fraction[0] != b'.' || !fraction[1..].iter().all(u8::is_ascii_digit)) {
+        return Err(invalid_date());
+    }
+    let num = |range: core::ops::Range<usize>| {
+        date.get(range).filter(|s| s.bytes().all(|c| c.is_ascii_digit()))
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(invalid_date)
+    };
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if year < 1970 || month == 0 || month > 12 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid_date());
+    }
+
+    Ok(unix_time(year, month, day, hour, minute, second))
+}
+
+/// Seconds since the Unix epoch of a UTC date and time in 1970 or later
+pub(crate) fn unix_time(year: u64, month: u64, day: u64, hour: u64, minute: u64, second: u64) -> u64 {
+    // Days from civil, after Howard Hinnant's `days_from_civil`
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    days * 86400 + hour * 3600 + minute * 60 + second
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::sim::signed;
+    use crate::test_certs::{TestKey, TestPki, NOW};
+
+    /// 2023-11-01T00:00:00Z and 2023-12-01T00:00:00Z, around `NOW`
+    const ISSUE_DATE: u64 = 1_698_796_800;
+    const NEXT_UPDATE: u64 = 1_701_388_800;
+
+    /// A platform TCB level: SGX TCB component SVNs, PCESVN, status and advisories
+    pub(crate) type Level<'a> = ([u8; 16], u16, &'a str, &'a [&'a str]);
+
+    pub(crate) const LEVELS: &[Level] = &[
+        ([3, 3, 3, 3, 4, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0], 13, "UpToDate", &[]),
+        ([2, 2, 2, 2, 4, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0], 11, "SWHardeningNeeded", &["INTEL-SA-00615"]),
+        ([1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0], 7, "OutOfDate", &["INTEL-SA-00334", "INTEL-SA-00615"]),
+    ];
+
+    pub(crate) const QE_LEVELS: &[(u16, &str, &[&str])] = &[
+        (8, "UpToDate", &[]),
+        (6, "OutOfDate", &["INTEL-SA-00615", "INTEL-SA-00477"]),
+    ];
+
+    fn advisory_ids(ids: &[&str]) -> String {
+        let ids: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id)).collect();
+        ids.join(",")
+    }
+
+    /// Encodes the body of TCB info, version 2 or 3, for the sample FMSPC.
+    pub(crate) fn tcb_info_body(version: u32, levels: &[Level]) -> String {
+        let levels: Vec<String> = levels.iter()
+            .map(|(svns, pce_svn, status, advisories)| {
+                let components = if version == 2 {
+                    let svns: Vec<String> = svns.iter().enumerate()
+                        .map(|(i, svn)| format!("\"sgxtcbcomp{:02}svn\":{}", i + 1, svn))
+                        .collect();
+                    svns.join(",")
+                } else {
+                    let svns: Vec<String> = svns.iter().map(|svn| format!("{{\"svn\":{}}}", svn)).collect();
+                    format!("\"sgxtcbcomponents\":[{}]", svns.join(","))
+                };
+                format!(
+                    "{{\"tcb\":{{{},\"pcesvn\":{}}},\"tcbDate\":\"2023-08-09T00:00:00Z\",\"tcbStatus\":\"{}\",\"advisoryIDs\":[{}]}}",
+                    components, pce_svn, status, advisory_ids(advisories),
+                )
+            })
+            .collect();
+        let id = if version == 3 { "\"id\":\"SGX\"," } else { "" };
+        format!(
+            "{{{}\"version\":{},\"issueDate\":\"2023-11-01T00:00:00Z\",\"nextUpdate\":\"2023-12-01T00:00:00Z\",\
+             \"fmspc\":\"00906EA10000\",\"pceId\":\"0000\",\"tcbType\":0,\"tcbEvaluationDataNumber\":16,\
+             \"tcbLevels\":[{}]}}",
+            id, version, levels.join(","),
+        )
+    }
+
+    pub(crate) fn qe_identity_body(levels: &[(u16, &str, &[&str])]) -> String {
+        let levels: Vec<String> = levels.iter()
+            .map(|(isv_svn, status, advisories)| format!(
+                "{{\"tcb\":{{\"isvsvn\":{}}},\"tcbDate\":\"2023-08-09T00:00:00Z\",\"tcbStatus\":\"{}\",\"advisoryIDs\":[{}]}}",
+                isv_svn, status, advisory_ids(advisories),
+            ))
+            .collect();
+        format!(
+            "{{\"id\":\"QE\",\"version\":2,\"issueDate\":\"2023-11-01T00:00:00Z\",\"nextUpdate\":\"2023-12-01T00:00:00Z\",\
+             \"tcbEvaluationDataNumber\":16,\"miscselect\":\"00000000\",\"miscselectMask\":\"FFFFFFFF\",\
+             \"attributes\":\"11000000000000000000000000000000\",\"attributesMask\":\"FBFFFFFFFFFFFFFF0000000000000000\",\
+             \"mrsigner\":\"{}\",\"isvprodid\":1,\"tcbLevels\":[{}]}}",
+            "8C".repeat(32), levels.join(","),
+        )
+    }
+
+    #[test]
+    fn test_parse_tcb_info() {
+        for version in 2..=3 {
+            let json = signed("tcbInfo", &tcb_info_body(version, LEVELS), &TestKey::generate());
+            let tcb_info = TcbInfo::parse(&json).unwrap();
+
+            assert_eq!(tcb_info.version, version);
+            assert_eq!(tcb_info.tee_type, TeeType::Sgx);
+            assert_eq!(tcb_info.issue_date, ISSUE_DATE);
+            assert_eq!(tcb_info.next_update, NEXT_UPDATE);
+            assert_eq!(tcb_info.fmspc, [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00]);
+            assert_eq!(tcb_info.tcb_levels.len(), 3);
+            let level = &tcb_info.tcb_levels[1];
+            assert_eq!(level.tcb.sgx_tcb_comp_svn, LEVELS[1].0);
+            assert_eq!(level.tcb.pce_svn, 11);
+            assert_eq!(level.tcb.tdx_tcb_comp_svn, None);
+            assert_eq!(level.tcb_status, TcbStatus::SwHardeningNeeded);
+            assert_eq!(level.advisory_ids, vec!["INTEL-SA-00615".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_parse_qe_identity() {
+        let json = signed("enclaveIdentity", &qe_identity_body(QE_LEVELS), &TestKey::generate());
+        let qe_identity = QeIdentity::parse(&json).unwrap();
+
+        assert_eq!(qe_identity.id, "QE");
+        assert_eq!(qe_identity.miscselect_mask, 0xffff_ffff);
+        assert_eq!(qe_identity.attributes[0], 0x11);
+        assert_eq!(qe_identity.mrsigner, [0x8c; 32]);
+        assert_eq!(qe_identity.isv_prod_id, 1);
+        assert_eq!(qe_identity.tcb_levels[1].isv_svn, 6);
+        assert_eq!(qe_identity.tcb_levels[1].tcb_status, TcbStatus::OutOfDate);
+    }
+
+    #[test]
+    fn test_verify_collateral() {
+        let pki = TestPki::new();
+        let chain = pki.tcb_signing_chain_pem();
+        let tcb_info = signed("tcbInfo", &tcb_info_body(3, LEVELS), &pki.tcb_signing_key);
+        let qe_identity = signed("enclaveIdentity", &qe_identity_body(QE_LEVELS), &pki.tcb_signing_key);
+
+        assert!(TcbInfo::verify(&tcb_info, &chain, &pki.root_ca, NOW).is_ok());
+        assert!(QeIdentity::verify(&qe_identity, &chain, &pki.root_ca, NOW).is_ok());
+
+        assert_eq!(TcbInfo::verify(&tcb_info, &chain, &pki.root_ca, NEXT_UPDATE), Err(Error::CollateralExpired(TCB_INFO)));
+        assert_eq!(
+            QeIdentity::verify(&qe_identity, &chain, &pki.root_ca, ISSUE_DATE - 1),
+            Err(Error::CollateralNotYetValid(QE_IDENTITY)),
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_collateral_signature() {
+        let pki = TestPki::new();
+        let chain = pki.tcb_signing_chain_pem();
+
+        // Signed by a key other than the TCB signing key
+        let json = signed("tcbInfo", &tcb_info_body(2, LEVELS), &pki.pck_key);
+        assert_eq!(TcbInfo::verify(&json, &chain, &pki.root_ca, NOW), Err(Error::InvalidCollateralSignature(TCB_INFO)));
+
+        // Tampered after signing
+        let json = signed("tcbInfo", &tcb_info_body(2, LEVELS), &pki.tcb_signing_key);
+        let json = String::from_utf8(json).unwrap().replace("OutOfDate", "UpToDate").into_bytes();
+        assert_eq!(TcbInfo::verify(&json, &chain, &pki.root_ca, NOW), Err(Error::InvalidCollateralSignature(TCB_INFO)));
+
+        // Issued under another root CA
+        let other = TestPki::new();
+        match TcbInfo::verify(&json, &chain, &other.root_ca, NOW) {
+            Err(Error::InvalidCollateralCertChain { .. }) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_reject_invalid_tcb_info() {
+        let body = tcb_info_body(2, LEVELS).replace("\"version\":2", "\"version\":1");
+        let json = signed("tcbInfo", &body, &TestKey::generate());
+        assert_eq!(
+            TcbInfo::parse(&json),
+            Err(Error::UnsupportedCollateralVersion { collateral: TCB_INFO, version: 1 }),
+        );
+
+        let body = tcb_info_body(2, LEVELS).replace("\"sgxtcbcomp16svn\":0,", "");
+        let json = signed("tcbInfo", &body, &TestKey::generate());
+        assert!(TcbInfo::parse(&json).is_err());
+
+        let body = tcb_info_body(2, LEVELS).replace("00906EA10000", "00906EA100");
+        let json = signed("tcbInfo", &body, &TestKey::generate());
+        assert_eq!(TcbInfo::parse(&json), Err(invalid(TCB_INFO, "fmspc")));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date(TCB_INFO, "1970-01-01T00:00:00Z"), Ok(0));
+        assert_eq!(parse_date(TCB_INFO, "2023-11-01T00:00:00Z"), Ok(ISSUE_DATE));
+        assert_eq!(parse_date(TCB_INFO, "2024-02-29T12:34:56.789Z"), Ok(1_709_210_096));
+        assert!(parse_date(TCB_INFO, "2024-02-29 12:34:56Z").is_err());
+        assert!(parse_date(TCB_INFO, "2024-02-29T12:34:56+09:00").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+//! Certificate revocation lists of the PCK CAs and the root CA
+use alloc::{string::String, vec::Vec};
+use crate::cert;
+use crate::collateral::{check_dates, hex_decode, unix_time};
+use crate::der::{
+    DerReader, TAG_BIT_STRING, TAG_GENERALIZED_TIME, TAG_INTEGER, TAG_OID, TAG_SEQUENCE, TAG_UTC_TIME,
+};
+use crate::error::{Error, Result};
+
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const PEM_BEGIN: &str = "-----BEGIN X509 CRL-----";
+const PEM_END: &str = "-----END X509 CRL-----";
+
+/// A CRL whose signature and dates are verified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Crl {
+    pub this_update: u64,
+    pub next_update: u64,
+    /// Serial numbers of the revoked certificates, as INTEGER contents
+    pub revoked: Vec<Vec<u8>>,
+}
+
+impl Crl {
+    /// Verifies a CRL signed by the DER-encoded `issuer` and valid at `now`.
+    ///
+    /// The CRL may be DER, PEM, or hex-encoded DER as served by PCCS.
+    pub(crate) fn verify(collateral: &'static str, crl: &[u8], issuer: &[u8], now: u64) -> Result<Crl> {
+        let der = decode(collateral, crl)?;
+        let (tbs, signature) = split_signed(collateral, &der)?;
+        if !cert::verify_asn1_signature(issuer, tbs, signature) {
+            return Err(Error::InvalidCollateralSignature(collateral));
+        }
+        let crl = parse_tbs(collateral, tbs)?;
+        check_dates(collateral, crl.this_update, crl.next_update, now)?;
+
+        Ok(crl)
+    }
+
+    /// Returns whether the certificate with `serial`, the content of its
+    /// serial number INTEGER, is revoked.
+    pub(crate) fn is_revoked(&self, serial: &[u8]) -> bool {
+        self.revoked.iter().any(|s| s[..] == *serial)
+    }
+}
+
+fn invalid(collateral: &'static str, reason: &str) -> Error {
+    Error::InvalidCollateral {
+        collateral,
+        reason: reason.into(),
+    }
+}
+
+fn decode(collateral: &'static str, crl: &[u8]) -> Result<Vec<u8>> {
+    if crl.first() == Some(&TAG_SEQUENCE) {
+        return Ok(crl.to_vec());
+    }
+    let text = core::str::from_utf8(crl).map_err(|_| invalid(collateral, "encoding"))?.trim();
+    if text.starts_with(PEM_BEGIN) {
+        let end = text.find(PEM_END).ok_or_else(|| invalid(collateral, "PEM"))?;
+        let body: String = text[PEM_BEGIN.len()..end].chars().filter(|c| !c.is_whitespace()).collect();
+        base64::decode(&body).map_err(|_| invalid(collateral, "PEM"))
+    } else {
+        hex_decode(text).ok_or_else(|| invalid(collateral, "encoding"))
+    }
+}
+
+/// Splits a `CertificateList` into the DER-encoded `TBSCertList` and the
+/// ASN.1 signature.
+fn split_signed<'a>(collateral: &'static str, der: &'a [u8]) -> Result<(&'a [u8], &'a [u8])> {
+    let mut reader = DerReader::new(der);
+    let mut list = reader.read_sequence("CertificateList")?;
+    reader.finish("CertificateList")?;
+    let tbs = list.read_raw(TAG_SEQUENCE, "tbsCertList")?;
+    check_algorithm(collateral, list.read_sequence("signatureAlgorithm")?)?;
+    let signature = match list.read(TAG_BIT_STRING, "signature")? {
+        [0, signature @ ..] => signature,
+        _ => return Err(Error::InvalidDer("signature")),
+    };
+    list.finish("CertificateList")?;
+
+    Ok((tbs, signature))
+}
+
+fn check_algorithm(collateral: &'static str, mut alg: DerReader) -> Result<()> {
+    if alg.read(TAG_OID, "algorithm")? != OID_ECDSA_WITH_SHA256 {
+        return Err(invalid(collateral, "signature algorithm"));
+    }
+    Ok(())
+}
+
+fn parse_tbs(collateral: &'static str, tbs: &[u8]) -> Result<Crl> {
+    let mut tbs = DerReader::new(tbs).read_sequence("tbsCertList")?;
+    // Only v2 has the version, which is encoded as 1.
+    if let Some(version) = tbs.read_optional(TAG_INTEGER, "version")? {
+        if version != [1] {
+            return Err(invalid(collateral, "version"));
+        }
+    }
+    check_algorithm(collateral, tbs.read_sequence("signature")?)?;
+    tbs.read_sequence("issuer")?;
+    let this_update = read_time(collateral, &mut tbs, "thisUpdate")?;
+    let next_update = read_time(collateral, &mut tbs, "nextUpdate")?;
+
+    let mut revoked = vec![];
+    if tbs.peek_tag() == Some(TAG_SEQUENCE) {
+        let mut entries = tbs.read_sequence("revokedCertificates")?;
+        while !entries.is_empty() {
+            let mut entry = entries.read_sequence("revokedCertificate")?;
+            revoked.push(entry.read(TAG_INTEGER, "userCertificate")?.to_vec());
+            read_time(collateral, &mut entry, "revocationDate")?;
+            entry.read_optional(TAG_SEQUENCE, "crlEntryExtensions")?;
+            entry.finish("revokedCertificate")?;
+        }
+    }
+    tbs.read_optional(0xa0, "crlExtensions")?;
+    tbs.finish("tbsCertList")?;
+
+    Ok(Crl {
+        this_update,
+        next_update,
+        revoked,
+    })
+}
+
+/// Reads a `UTCTime` or `GeneralizedTime` in UTC, as seconds since the Unix epoch.
+fn read_time(collateral: &'static str, reader: &mut DerReader, field: &'static str) -> Result<u64> {
+    let invalid_time = || invalid(collateral, field);
+    let (tag, value) = reader.read_any(field)?;
+    let digits = match (tag, value.split_last()) {
+        (TAG_UTC_TIME, Some((b'Z', digits))) if digits.len() == 12 => digits,
+        (TAG_GENERALIZED_TIME, Some((b'Z', digits))) if digits.len() == 14 => digits,
+        _ => return Err(invalid_time()),
+    };
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return Err(invalid_time());
+    }
+    let num = |i: usize| (digits[i] - b'0') as u64 * 10 + (digits[i + 1] - b'0') as u64;
+    let (year, rest) = if tag == TAG_UTC_TIME {
+        // Two-digit years from 50 are in the 1900s.
+        let yy = num(0);
+        (if yy < 50 { 2000 + yy } else { 1900 + yy }, 2)
+    } else {
+        (num(0) * 100 + num(2), 4)
+    };
+    let (month, day) = (num(rest), num(rest + 2));
+    let (hour, minute, second) = (num(rest + 4), num(rest + 6), num(rest + 8));
+    if year < 1970 || month == 0 || month > 12 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid_time());
+    }
+
+    Ok(unix_time(year, month, day, hour, minute, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collateral::{PCK_CRL, ROOT_CA_CRL};
+    use crate::test_certs::{TestPki, crl, pem_crl, NOW};
+
+    #[test]
+    fn test_verify_crl() {
+        let pki = TestPki::new();
+        let crl = pki.root_ca_crl(NOW - 60, NOW + 60, &[&pki.intermediate_ca]);
+        let expected = Crl {
+            this_update: NOW - 60,
+            next_update: NOW + 60,
+            revoked: vec![cert::serial_number(&pki.intermediate_ca).unwrap().to_vec()],
+        };
+
+        let verified = Crl::verify(ROOT_CA_CRL, &crl, &pki.root_ca, NOW).unwrap();
+        assert_eq!(verified, expected);
+        assert!(verified.is_revoked(cert::serial_number(&pki.intermediate_ca).unwrap()));
+        assert!(!verified.is_revoked(cert::serial_number(&pki.tcb_signing_cert).unwrap()));
+
+        // As served by PCCS and by some mirrors
+        let hex = crate::provider::hex(&crl);
+        assert_eq!(Crl::verify(ROOT_CA_CRL, hex.as_bytes(), &pki.root_ca, NOW), Ok(expected.clone()));
+        assert_eq!(Crl::verify(ROOT_CA_CRL, &pem_crl(&crl), &pki.root_ca, NOW), Ok(expected));
+    }
+
+    #[test]
+    fn test_reject_crl_of_other_issuer() {
+        let pki = TestPki::new();
+        let crl = pki.pck_crl(NOW - 60, NOW + 60, &[]);
+
+        assert_eq!(
+            Crl::verify(PCK_CRL, &crl, &pki.root_ca, NOW),
+            Err(Error::InvalidCollateralSignature(PCK_CRL)),
+        );
+        assert!(Crl::verify(PCK_CRL, &crl, &pki.intermediate_ca, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_crl_dates() {
+        let pki = TestPki::new();
+        let crl = pki.pck_crl(NOW, NOW + 60, &[]);
+
+        assert_eq!(
+            Crl::verify(PCK_CRL, &crl, &pki.intermediate_ca, NOW - 1),
+            Err(Error::CollateralNotYetValid(PCK_CRL)),
+        );
+        assert_eq!(
+            Crl::verify(PCK_CRL, &crl, &pki.intermediate_ca, NOW + 60),
+            Err(Error::CollateralExpired(PCK_CRL)),
+        );
+    }
+
+    #[test]
+    fn test_utc_time() {
+        let mut reader = DerReader::new(b"\x17\x0d491231235959Z\x17\x0d700101000000Z\x17\x0c7001010000Z0");
+        assert_eq!(read_time(PCK_CRL, &mut reader, "thisUpdate"), Ok(2_524_607_999));
+        assert_eq!(read_time(PCK_CRL, &mut reader, "thisUpdate"), Ok(0));
+        assert!(read_time(PCK_CRL, &mut reader, "thisUpdate").is_err());
+    }
+
+    #[test]
+    fn test_reject_tampered_crl() {
+        let pki = TestPki::new();
+        let mut crl = crl("Test SGX Root CA", &pki.root_key, NOW - 60, NOW + 60, &[]);
+        let len = crl.len();
+        crl[len - 1] ^= 1;
+
+        assert_eq!(
+            Crl::verify(ROOT_CA_CRL, &crl, &pki.root_ca, NOW),
+            Err(Error::InvalidCollateralSignature(ROOT_CA_CRL)),
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Minimal DER encoding
+use alloc::vec::Vec;
+use crate::error::{Error, Result};
+
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+
+/// Encodes a tag-length-value.
+pub(crate) fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = value.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes = (len as u32).to_be_bytes();
+        let skip = len_bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (4 - skip) as u8);
+        out.extend_from_slice(&len_bytes[skip..]);
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+pub(crate) fn sequence(items: &[&[u8]]) -> Vec<u8> {
+    tlv(TAG_SEQUENCE, &items.concat())
+}
+
+/// Encodes a big-endian unsigned integer.
+pub(crate) fn unsigned_integer(bytes: &[u8]) -> Vec<u8> {
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    let bytes = &bytes[skip..];
+    let mut value = vec![];
+    // Zero is encoded as one byte, and a set high bit needs a zero pad.
+    match bytes.first() {
+        Some(b) if b & 0x80 == 0 => {}
+        _ => value.push(0),
+    }
+    value.extend_from_slice(bytes);
+    tlv(TAG_INTEGER, &value)
+}
+
+/// Converts a raw `r || s` ECDSA signature to `Ecdsa-Sig-Value`.
+pub(crate) fn ecdsa_signature(raw: &[u8]) -> Vec<u8> {
+    let (r, s) = raw.split_at(raw.len() / 2);
+    sequence(&[&unsigned_integer(r), &unsigned_integer(s)])
+}
+
+pub(crate) const TAG_BOOLEAN: u8 = 0x01;
+pub(crate) const TAG_BIT_STRING: u8 = 0x03;
+pub(crate) const TAG_OCTET_STRING: u8 = 0x04;
+pub(crate) const TAG_OID: u8 = 0x06;
+pub(crate) const TAG_ENUMERATED: u8 = 0x0a;
+pub(crate) const TAG_SET: u8 = 0x31;
+pub(crate) const TAG_UTC_TIME: u8 = 0x17;
+pub(crate) const TAG_GENERALIZED_TIME: u8 = 0x18;
+
+/// Reads DER values one by one, without going into constructed values.
+pub(crate) struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        DerReader { data }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn peek_tag(&self) -> Option<u8> {
+        self.data.first().cloned()
+    }
+
+    /// Reads a value of any tag, returning the tag and the content.
+    pub(crate) fn read_any(&mut self, field: &'static str) -> Result<(u8, &'a [u8])> {
+        let invalid = Error::InvalidDer(field);
+        let (&tag, rest) = self.data.split_first().ok_or_else(|| invalid.clone())?;
+        // High tag numbers aren't used in certificates.
+        if tag & 0x1f == 0x1f {
+            return Err(invalid);
+        }
+        let (&first, mut rest) = rest.split_first().ok_or_else(|| invalid.clone())?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            // Lengths must be definite, minimal and fit in 32 bits.
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n || rest[0] == 0 {
+                return Err(invalid);
+            }
+            let len = rest[..n].iter().fold(0usize, |acc, b| acc << 8 | *b as usize);
+            rest = &rest[n..];
+            if len < 0x80 {
+                return Err(invalid);
+            }
+            len
+        };
+        if rest.len() < len {
+            return Err(invalid);
+        }
+        let (value, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok((tag, value))
+    }
+
+    /// Reads a value which must have `tag`.
+    pub(crate) fn read(&mut self, tag: u8, field: &'static str) -> Result<&'a [u8]> {
+        match self.read_any(field)? {
+            (t, value) if t == tag => Ok(value),
+            _ => Err(Error::InvalidDer(field)),
+        }
+    }
+
+    /// Reads a value if the next one has `tag`.
+    pub(crate) fn read_optional(&mut self, tag: u8, field: &'static str) -> Result<Option<&'a [u8]>> {
+        if self.peek_tag() == Some(tag) {
+            self.read(tag, field).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads a value which must have `tag`, returning it with its tag and length.
+    pub(crate) fn read_raw(&mut self, tag: u8, field: &'static str) -> Result<&'a [u8]> {
+        let data = self.data;
+        self.read(tag, field)?;
+        Ok(&data[..data.len() - self.data.len()])
+    }
+
+    pub(crate) fn read_sequence(&mut self, field: &'static str) -> Result<DerReader<'a>> {
+        self.read(TAG_SEQUENCE, field).map(DerReader::new)
+    }
+
+    /// Reads a non-negative INTEGER which fits in `max`.
+    pub(crate) fn read_uint(&mut self, max: u64, field: &'static str) -> Result<u64> {
+        let value = self.read(TAG_INTEGER, field)?;
+        uint(value, max, field)
+    }
+
+    pub(crate) fn read_boolean(&mut self, field: &'static str) -> Result<bool> {
+        match self.read(TAG_BOOLEAN, field)? {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(Error::InvalidDer(field)),
+        }
+    }
+
+    pub(crate) fn finish(&self, field: &'static str) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidDer(field))
+        }
+    }
+}
+
+/// Decodes the content of a non-negative INTEGER or ENUMERATED which fits in `max`.
+pub(crate) fn uint(value: &[u8], max: u64, field: &'static str) -> Result<u64> {
+    let invalid = Error::InvalidDer(field);
+    match value {
+        [] => return Err(invalid),
+        [b, ..] if b & 0x80 != 0 => return Err(invalid),
+        [0, b, ..] if b & 0x80 == 0 => return Err(invalid),
+        _ => {}
+    }
+    let value = match value {
+        [0, rest @ ..] => rest,
+        _ => value,
+    };
+    if value.len() > 8 {
+        return Err(invalid);
+    }
+    let n = value.iter().fold(0u64, |acc, b| acc << 8 | *b as u64);
+    if n > max {
+        return Err(invalid);
+    }
+    Ok(n)
+}
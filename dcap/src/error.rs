@@ -0,0 +1,172 @@
+use alloc::string::String;
+use core::fmt;
+use attestation::TcbStatus;
+use sgx_types::sgx_quote3_error_t;
+use crate::qve::QvResult;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Errors in decoding and verifying DCAP quotes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input ended before the field could be read.
+    UnexpectedEof {
+        field: &'static str,
+        offset: usize,
+    },
+    /// A length prefix doesn't match the data it describes.
+    InvalidLength {
+        field: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// Bytes were left over after the last field of a section.
+    TrailingBytes {
+        field: &'static str,
+        len: usize,
+    },
+    UnsupportedQuoteVersion(u16),
+    UnsupportedAttestationKeyType(u16),
+    UnsupportedTeeType(u32),
+    UnsupportedBodyType(u16),
+    /// The certification data is not of the type required by the quote version.
+    UnexpectedCertificationDataType(u16),
+    /// The quote doesn't carry the PCK certificate chain.
+    MissingPckCertChain,
+    InvalidPem,
+    /// The PCK certificate chain doesn't lead to the trusted root CA.
+    InvalidPckCertChain(String),
+    /// The ISV enclave report is not signed by the attestation key.
+    InvalidIsvEnclaveReportSignature,
+    /// The QE report is not signed by the PCK certificate.
+    InvalidQeReportSignature,
+    /// The QE report data is not `SHA256(attestation key || QE auth data) || 0*32`.
+    QeReportDataMismatch,
+    /// A certificate or extension is not valid DER.
+    InvalidDer(&'static str),
+    /// The PCK certificate lacks a required SGX extension.
+    MissingSgxExtension(&'static str),
+    /// The PCK certificate is not issued by the Processor or Platform CA.
+    UnknownPckCa(String),
+    /// TCB info or QE identity can't be decoded.
+    InvalidCollateral {
+        collateral: &'static str,
+        reason: String,
+    },
+    UnsupportedCollateralVersion {
+        collateral: &'static str,
+        version: u32,
+    },
+    /// The signing certificate chain of the collateral doesn't lead to the trusted root CA.
+    InvalidCollateralCertChain {
+        collateral: &'static str,
+        reason: String,
+    },
+    InvalidCollateralSignature(&'static str),
+    /// The issue date of the collateral is in the future.
+    CollateralNotYetValid(&'static str),
+    /// The next update of the collateral has passed.
+    CollateralExpired(&'static str),
+    /// A certificate of the PCK chain is revoked.
+    CertificateRevoked(&'static str),
+    /// The collateral is not for the platform or the TEE of the quote.
+    CollateralMismatch(&'static str),
+    /// The QE report doesn't match the QE identity.
+    QeIdentityMismatch(&'static str),
+    /// No TCB level in the collateral is reached by the platform or the QE.
+    TcbLevelNotFound(&'static str),
+    /// The TCB status is neither `UpToDate` nor tolerated by the policy.
+    TcbStatusNotTolerated(TcbStatus),
+    /// The collateral provider failed to get the collateral.
+    CollateralUnavailable {
+        collateral: &'static str,
+        reason: String,
+    },
+    /// The HTTP client to fetch collateral with can't be built.
+    HttpClient(String),
+    /// The PCS API version doesn't serve collateral of the TEE.
+    UnsupportedPcsCollateral {
+        tee: &'static str,
+        version: &'static str,
+    },
+    /// No verification time is given, and inside an enclave, or the system
+    /// clock is before the Unix epoch.
+    UnknownTime,
+    /// A function of the DCAP quote library failed.
+    QuotingEnclave {
+        function: &'static str,
+        error: sgx_quote3_error_t,
+    },
+    /// The QvE report is not from the QvE to trust.
+    QveIdentityMismatch(&'static str),
+    /// The QvE report data doesn't bind the nonce, the quote and the result.
+    QveReportDataMismatch,
+    /// The QvE report is not targeted at this enclave.
+    InvalidQveReport,
+    /// The QvE checked collateral expiration at a date earlier than required.
+    StaleQveExpirationCheck(i64),
+    /// The QvE failed to verify the quote.
+    QuoteVerificationFailed(QvResult),
+    UnsupportedSupplementalDataVersion(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof { field, offset } =>
+                write!(f, "Unexpected end of input reading {} at offset {}", field, offset),
+            Error::InvalidLength { field, expected, actual } =>
+                write!(f, "Invalid length of {}: expected {}, actual {}", field, expected, actual),
+            Error::TrailingBytes { field, len } =>
+                write!(f, "{} trailing bytes after {}", len, field),
+            Error::UnsupportedQuoteVersion(v) => write!(f, "Unsupported quote version: {}", v),
+            Error::UnsupportedAttestationKeyType(t) =>
+                write!(f, "Unsupported attestation key type: {}", t),
+            Error::UnsupportedTeeType(t) => write!(f, "Unsupported TEE type: {:#x}", t),
+            Error::UnsupportedBodyType(t) => write!(f, "Unsupported quote body type: {}", t),
+            Error::UnexpectedCertificationDataType(t) =>
+                write!(f, "Unexpected certification data type: {}", t),
+            Error::MissingPckCertChain => write!(f, "PCK certificate chain is not found in the quote"),
+            Error::InvalidPem => write!(f, "Invalid PEM certificate"),
+            Error::InvalidPckCertChain(e) => write!(f, "Invalid PCK certificate chain: {}", e),
+            Error::InvalidIsvEnclaveReportSignature => write!(f, "Invalid ISV enclave report signature"),
+            Error::InvalidQeReportSignature => write!(f, "Invalid QE report signature"),
+            Error::QeReportDataMismatch => write!(f, "QE report data doesn't match the attestation key"),
+            Error::InvalidDer(field) => write!(f, "Invalid DER encoding of {}", field),
+            Error::MissingSgxExtension(field) => write!(f, "Missing SGX extension: {}", field),
+            Error::UnknownPckCa(issuer) => write!(f, "Unknown PCK certificate issuer: {}", issuer),
+            Error::InvalidCollateral { collateral, reason } => write!(f, "Invalid {}: {}", collateral, reason),
+            Error::UnsupportedCollateralVersion { collateral, version } =>
+                write!(f, "Unsupported {} version: {}", collateral, version),
+            Error::InvalidCollateralCertChain { collateral, reason } =>
+                write!(f, "Invalid {} issuer certificate chain: {}", collateral, reason),
+            Error::InvalidCollateralSignature(collateral) => write!(f, "Invalid {} signature", collateral),
+            Error::CollateralNotYetValid(collateral) => write!(f, "{} is not yet valid", collateral),
+            Error::CollateralExpired(collateral) => write!(f, "{} has expired", collateral),
+            Error::CertificateRevoked(cert) => write!(f, "{} is revoked", cert),
+            Error::CollateralMismatch(field) => write!(f, "Collateral doesn't match the quote: {}", field),
+            Error::QeIdentityMismatch(field) => write!(f, "QE report doesn't match the QE identity: {}", field),
+            Error::TcbLevelNotFound(tcb) => write!(f, "No TCB level is reached by the {}", tcb),
+            Error::TcbStatusNotTolerated(status) => write!(f, "TCB status is not tolerated: {}", status),
+            Error::CollateralUnavailable { collateral, reason } =>
+                write!(f, "{} is unavailable: {}", collateral, reason),
+            Error::HttpClient(e) => write!(f, "Failed to build the HTTP client: {}", e),
+            Error::UnsupportedPcsCollateral { tee, version } => {
+                write!(f, "PCS API {} doesn't serve {} collateral", version, tee)
+            }
+            Error::UnknownTime => write!(f, "Verification time is unknown"),
+            Error::QuotingEnclave { function, error } => write!(f, "{} failed: {:?}", function, error),
+            Error::QveIdentityMismatch(field) => write!(f, "QvE report doesn't match the QvE identity: {}", field),
+            Error::QveReportDataMismatch => write!(f, "QvE report data doesn't match the quote verification"),
+            Error::InvalidQveReport => write!(f, "Invalid QvE report"),
+            Error::StaleQveExpirationCheck(date) =>
+                write!(f, "QvE checked collateral expiration at a stale date: {}", date),
+            Error::QuoteVerificationFailed(result) =>
+                write!(f, "QvE failed to verify the quote: {:?} ({:#x})", result, result.as_u32()),
+            Error::UnsupportedSupplementalDataVersion(v) =>
+                write!(f, "Unsupported supplemental data version: {:#x}", v),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
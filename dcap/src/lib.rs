@@ -1,7 +1,86 @@
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+//! Intel SGX DCAP (ECDSA) attestation
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(all(feature = "std", feature = "sgx", not(feature = "pcs")))]
+compile_error!("features `std` and `sgx` can't be enabled together");
+#[cfg(all(feature = "std", feature = "pcs"))]
+compile_error!("feature `pcs` works only inside SGX enclaves, without the default feature `std`");
+#[cfg(not(any(feature = "std", feature = "sgx")))]
+compile_error!("either feature `std` or `sgx` is required");
+
+#[cfg(feature = "sgx")]
+extern crate sgx_tstd as std;
+
+#[macro_use]
+extern crate alloc;
+
+#[macro_use]
+mod reader;
+mod cert;
+mod collateral;
+mod crl;
+mod der;
+mod error;
+mod pck;
+#[cfg(any(test, feature = "pcs"))]
+mod pcs;
+mod provider;
+mod quote;
+mod qve;
+mod quoting;
+mod report;
+#[cfg(any(test, feature = "sim"))]
+mod sim;
+mod tcb;
+mod verifier;
+mod verify;
+#[cfg(any(test, feature = "sim", feature = "enclave-test"))]
+mod test_certs;
+
+pub use crate::cert::pem_to_der_chain;
+pub use crate::collateral::{TcbInfo, TcbLevel, TcbComponents, QeIdentity, QeTcbLevel};
+pub use crate::error::{Error, Result};
+pub use crate::pck::{
+    PckCert, PckCa, SgxExtensions, PckTcb, SgxType, PlatformConfiguration, SGX_EXTENSIONS_OID,
+};
+#[cfg(feature = "pcs")]
+pub use crate::pcs::{PcsProvider, PcsVersion, INTEL_PCS_URL, INTEL_ROOT_CA_CRL_URL};
+pub use crate::provider::{CollateralProvider, Collateral, SignedCollateral};
+#[cfg(feature = "std")]
+pub use crate::provider::LocalDirProvider;
+pub use crate::quote::{
+    Quote, QuoteHeader, QuoteBody, QuoteSignatureData, CertificationData, CertificationDataType,
+    AttestationKeyType, TeeType, EcdsaSignature, EcdsaPublicKey, QUOTE_HEADER_LEN, INTEL_QE_VENDOR_ID,
+};
+pub use crate::qve::{
+    QvResult, QveOutput, SupplementalData, INTEL_QVE_MR_SIGNER, INTEL_QVE_ISV_PROD_ID,
+};
+#[cfg(feature = "sgx")]
+pub use crate::qve::{QveVerifier, QveEvidence};
+pub use crate::quoting::QuotingEnclave;
+#[cfg(feature = "dcap-ql")]
+pub use crate::quoting::DcapQl;
+pub use crate::report::{
+    EnclaveReportBody, TdReportBody,
+    ENCLAVE_REPORT_BODY_LEN, TD_REPORT10_BODY_LEN, TD_REPORT15_BODY_LEN,
+};
+#[cfg(feature = "sim")]
+pub use crate::sim::{SimulatedQe, SimulatedCollateral, SIM_QE_MR_ENCLAVE, SIM_QE_MR_SIGNER};
+pub use crate::tcb::{TcbStatus, TcbEvaluation, TcbPolicy, evaluate_tcb};
+pub use crate::verifier::DcapVerifier;
+pub use crate::verify::{verify_quote, VerifiedQuote};
+pub use attestation::{AttestationVerifier, VerifiedEnclave};
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use alloc::vec::Vec;
+    use test_utils::check_all_passed;
+
+    /// Runs the tests which need an enclave, i.e. those of `PcsProvider`,
+    /// inside one.
+    pub fn run_tests() -> bool {
+        check_all_passed!(
+            crate::pcs::enclave_tests::run_tests(),
+        )
     }
 }
@@ -0,0 +1,404 @@
+use alloc::{string::String, vec::Vec};
+use crate::der::{self, DerReader, TAG_SEQUENCE, TAG_OCTET_STRING, TAG_OID, TAG_ENUMERATED, TAG_INTEGER, TAG_SET};
+use crate::error::{Error, Result};
+
+/// DER encoding of OID 1.2.840.113741.1.13.1, the SGX extensions
+pub const SGX_EXTENSIONS_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01];
+/// DER encoding of OID 2.5.4.3, the common name attribute
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+/// Common names of the CAs which issue PCK certificates
+const PROCESSOR_CA_NAME: &[u8] = b"Intel SGX PCK Processor CA";
+const PLATFORM_CA_NAME: &[u8] = b"Intel SGX PCK Platform CA";
+
+// Arcs under the SGX extensions OID
+const PPID: u8 = 1;
+const TCB: u8 = 2;
+const PCE_ID: u8 = 3;
+const FMSPC: u8 = 4;
+const SGX_TYPE: u8 = 5;
+const PLATFORM_INSTANCE_ID: u8 = 6;
+const CONFIGURATION: u8 = 7;
+
+// Arcs under the TCB OID
+const PCESVN: u8 = 17;
+const CPUSVN: u8 = 18;
+
+// Arcs under the configuration OID
+const DYNAMIC_PLATFORM: u8 = 1;
+const CACHED_KEYS: u8 = 2;
+const SMT_ENABLED: u8 = 3;
+
+/// The TCB level the PCK certificate was issued for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PckTcb {
+    /// SGX TCB component SVNs 1 to 16
+    pub sgx_tcb_comp_svn: [u8; 16],
+    pub pce_svn: u16,
+    pub cpu_svn: [u8; 16],
+}
+
+/// Type of the SGX platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgxType {
+    Standard,
+    Scalable,
+    ScalableWithIntegrity,
+}
+
+/// Platform configuration, only in certificates issued by the Platform CA
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlatformConfiguration {
+    pub dynamic_platform: Option<bool>,
+    pub cached_keys: Option<bool>,
+    pub smt_enabled: Option<bool>,
+}
+
+/// The SGX extensions of a PCK certificate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SgxExtensions {
+    pub ppid: [u8; 16],
+    pub tcb: PckTcb,
+    pub pce_id: [u8; 2],
+    pub fmspc: [u8; 6],
+    pub sgx_type: SgxType,
+    /// Only in certificates issued by the Platform CA
+    pub platform_instance_id: Option<[u8; 16]>,
+    /// Only in certificates issued by the Platform CA
+    pub configuration: Option<PlatformConfiguration>,
+}
+
+impl SgxExtensions {
+    /// Decodes the value of the SGX extensions.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = DerReader::new(bytes);
+        let mut entries = reader.read_sequence("SGX extensions")?;
+        reader.finish("SGX extensions")?;
+
+        let mut ppid = None;
+        let mut tcb = None;
+        let mut pce_id = None;
+        let mut fmspc = None;
+        let mut sgx_type = None;
+        let mut platform_instance_id = None;
+        let mut configuration = None;
+        while !entries.is_empty() {
+            let (arc, mut value) = read_entry(&mut entries, SGX_EXTENSIONS_OID, "SGX extension")?;
+            match arc {
+                PPID => ppid = Some(read_octets(&mut value, 16, "PPID")?),
+                TCB => tcb = Some(read_tcb(&mut value)?),
+                PCE_ID => pce_id = Some(read_octets(&mut value, 2, "PCE-ID")?),
+                FMSPC => fmspc = Some(read_octets(&mut value, 6, "FMSPC")?),
+                SGX_TYPE => {
+                    let t = value.read(TAG_ENUMERATED, "SGX type")?;
+                    sgx_type = Some(match der::uint(t, 2, "SGX type")? {
+                        0 => SgxType::Standard,
+                        1 => SgxType::Scalable,
+                        _ => SgxType::ScalableWithIntegrity,
+                    });
+                }
+                PLATFORM_INSTANCE_ID =>
+                    platform_instance_id = Some(read_octets(&mut value, 16, "platform instance ID")?),
+                CONFIGURATION => configuration = Some(read_configuration(&mut value)?),
+                // Newer extensions are ignored.
+                _ => continue,
+            }
+            value.finish("SGX extension")?;
+        }
+
+        Ok(SgxExtensions {
+            ppid: array!(&ppid.ok_or(Error::MissingSgxExtension("PPID"))?, 16),
+            tcb: tcb.ok_or(Error::MissingSgxExtension("TCB"))?,
+            pce_id: array!(&pce_id.ok_or(Error::MissingSgxExtension("PCE-ID"))?, 2),
+            fmspc: array!(&fmspc.ok_or(Error::MissingSgxExtension("FMSPC"))?, 6),
+            sgx_type: sgx_type.ok_or(Error::MissingSgxExtension("SGX type"))?,
+            platform_instance_id: platform_instance_id.map(|id| array!(&id, 16)),
+            configuration,
+        })
+    }
+}
+
+/// Reads `SEQUENCE { OID, value }` whose OID is one arc under `parent`.
+fn read_entry<'a>(
+    reader: &mut DerReader<'a>,
+    parent: &[u8],
+    field: &'static str,
+) -> Result<(u8, DerReader<'a>)> {
+    let mut entry = reader.read_sequence(field)?;
+    let oid = entry.read(TAG_OID, field)?;
+    match oid.split_last() {
+        // Arcs below 128 are encoded in one byte.
+        Some((&arc, prefix)) if prefix == parent && arc < 0x80 => Ok((arc, entry)),
+        _ => Err(Error::InvalidDer(field)),
+    }
+}
+
+fn read_octets(reader: &mut DerReader, len: usize, field: &'static str) -> Result<Vec<u8>> {
+    let value = reader.read(TAG_OCTET_STRING, field)?;
+    if value.len() != len {
+        return Err(Error::InvalidLength { field, expected: len, actual: value.len() });
+    }
+    Ok(value.to_vec())
+}
+
+fn read_tcb(reader: &mut DerReader) -> Result<PckTcb> {
+    let tcb_oid = [SGX_EXTENSIONS_OID, &[TCB]].concat();
+    let mut components = reader.read_sequence("TCB")?;
+    let mut comp_svn = [None; 16];
+    let mut pce_svn = None;
+    let mut cpu_svn = None;
+    while !components.is_empty() {
+        let (arc, mut value) = read_entry(&mut components, &tcb_oid, "TCB component")?;
+        match arc {
+            1..=16 => comp_svn[arc as usize - 1] = Some(value.read_uint(0xff, "SGX TCB component SVN")? as u8),
+            PCESVN => pce_svn = Some(value.read_uint(0xffff, "PCESVN")? as u16),
+            CPUSVN => cpu_svn = Some(read_octets(&mut value, 16, "CPUSVN")?),
+            _ => continue,
+        }
+        value.finish("TCB component")?;
+    }
+
+    let mut sgx_tcb_comp_svn = [0; 16];
+    for (svn, comp) in sgx_tcb_comp_svn.iter_mut().zip(comp_svn.iter()) {
+        *svn = comp.ok_or(Error::MissingSgxExtension("SGX TCB component SVN"))?;
+    }
+    Ok(PckTcb {
+        sgx_tcb_comp_svn,
+        pce_svn: pce_svn.ok_or(Error::MissingSgxExtension("PCESVN"))?,
+        cpu_svn: array!(&cpu_svn.ok_or(Error::MissingSgxExtension("CPUSVN"))?, 16),
+    })
+}
+
+fn read_configuration(reader: &mut DerReader) -> Result<PlatformConfiguration> {
+    let config_oid = [SGX_EXTENSIONS_OID, &[CONFIGURATION]].concat();
+    let mut entries = reader.read_sequence("configuration")?;
+    let mut config = PlatformConfiguration::default();
+    while !entries.is_empty() {
+        let (arc, mut value) = read_entry(&mut entries, &config_oid, "configuration")?;
+        match arc {
+            DYNAMIC_PLATFORM => config.dynamic_platform = Some(value.read_boolean("dynamic platform")?),
+            CACHED_KEYS => config.cached_keys = Some(value.read_boolean("cached keys")?),
+            SMT_ENABLED => config.smt_enabled = Some(value.read_boolean("SMT enabled")?),
+            _ => continue,
+        }
+        value.finish("configuration")?;
+    }
+    Ok(config)
+}
+
+/// The CA which issues PCK certificates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PckCa {
+    Processor,
+    Platform,
+}
+
+impl PckCa {
+    /// The name used in the `ca` parameter of PCS APIs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PckCa::Processor => "processor",
+            PckCa::Platform => "platform",
+        }
+    }
+}
+
+/// A PCK certificate with its SGX extensions decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PckCert {
+    der: Vec<u8>,
+    ca: PckCa,
+    sgx_extensions: SgxExtensions,
+}
+
+impl PckCert {
+    /// Decodes a DER-encoded PCK certificate. The signature is not verified.
+    pub fn parse(der: &[u8]) -> Result<Self> {
+        let (issuer, extensions) = read_tbs(der)?;
+        let extension = find_extension(extensions, SGX_EXTENSIONS_OID)?
+            .ok_or(Error::MissingSgxExtension("SGX extensions"))?;
+        let ca = match common_name(issuer)? {
+            Some(PROCESSOR_CA_NAME) => PckCa::Processor,
+            Some(PLATFORM_CA_NAME) => PckCa::Platform,
+            name => {
+                let name = name.map(|n| String::from_utf8_lossy(n).into_owned()).unwrap_or_default();
+                return Err(Error::UnknownPckCa(name));
+            }
+        };
+
+        Ok(PckCert {
+            der: der.to_vec(),
+            ca,
+            sgx_extensions: SgxExtensions::parse(extension)?,
+        })
+    }
+
+    pub fn as_der(&self) -> &[u8] {
+        &self.der[..]
+    }
+
+    pub fn sgx_extensions(&self) -> &SgxExtensions {
+        &self.sgx_extensions
+    }
+
+    pub fn fmspc(&self) -> [u8; 6] {
+        self.sgx_extensions.fmspc
+    }
+
+    pub fn tcb(&self) -> &PckTcb {
+        &self.sgx_extensions.tcb
+    }
+
+    /// The issuing CA, told by the common name of the issuer
+    pub fn ca(&self) -> PckCa {
+        self.ca
+    }
+}
+
+/// Reads the issuer name and the extensions, if any, of an X.509 certificate.
+fn read_tbs(cert: &[u8]) -> Result<(&[u8], Option<&[u8]>)> {
+    let mut reader = DerReader::new(cert);
+    let mut cert = reader.read_sequence("certificate")?;
+    reader.finish("certificate")?;
+    let mut tbs = cert.read_sequence("TBS certificate")?;
+
+    tbs.read_optional(0xa0, "version")?;
+    tbs.read(TAG_INTEGER, "serial number")?;
+    tbs.read(TAG_SEQUENCE, "signature")?;
+    let issuer = tbs.read(TAG_SEQUENCE, "issuer")?;
+    for &field in &["validity", "subject", "subject public key info"] {
+        tbs.read(TAG_SEQUENCE, field)?;
+    }
+    tbs.read_optional(0x81, "issuer unique ID")?;
+    tbs.read_optional(0x82, "subject unique ID")?;
+    let extensions = tbs.read_optional(0xa3, "extensions")?;
+    tbs.finish("TBS certificate")?;
+
+    Ok((issuer, extensions))
+}
+
+/// Finds the common name in the contents of a `Name`.
+fn common_name(name: &[u8]) -> Result<Option<&[u8]>> {
+    let mut rdns = DerReader::new(name);
+    while !rdns.is_empty() {
+        let mut rdn = DerReader::new(rdns.read(TAG_SET, "relative distinguished name")?);
+        while !rdn.is_empty() {
+            let mut attr = rdn.read_sequence("attribute")?;
+            let oid = attr.read(TAG_OID, "attribute type")?;
+            let (_, value) = attr.read_any("attribute value")?;
+            attr.finish("attribute")?;
+            if oid == OID_COMMON_NAME {
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds the value of the extension `oid` in the `extensions` of an X.509
+/// certificate.
+fn find_extension<'a>(extensions: Option<&'a [u8]>, oid: &[u8]) -> Result<Option<&'a [u8]>> {
+    let extensions = match extensions {
+        Some(extensions) => extensions,
+        None => return Ok(None),
+    };
+    let mut outer = DerReader::new(extensions);
+    let mut extensions = outer.read_sequence("extensions")?;
+    outer.finish("extensions")?;
+    while !extensions.is_empty() {
+        let mut extension = extensions.read_sequence("extension")?;
+        let ext_oid = extension.read(TAG_OID, "extension ID")?;
+        extension.read_optional(der::TAG_BOOLEAN, "critical")?;
+        let value = extension.read(TAG_OCTET_STRING, "extension value")?;
+        extension.finish("extension")?;
+        if ext_oid == oid {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_certs::{TestPki, sample_sgx_extensions, sgx_extension, entry};
+
+    #[test]
+    fn test_parse_processor_pck_cert() {
+        let pki = TestPki::new();
+        let cert = PckCert::parse(&pki.pck_cert).unwrap();
+
+        assert_eq!(cert.sgx_extensions(), &sample_sgx_extensions());
+        assert_eq!(cert.fmspc(), [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00]);
+        assert_eq!(cert.tcb().pce_svn, 11);
+        assert_eq!(cert.ca(), PckCa::Processor);
+        assert_eq!(cert.as_der(), &pki.pck_cert[..]);
+    }
+
+    #[test]
+    fn test_parse_platform_pck_cert() {
+        let mut ext = sample_sgx_extensions();
+        ext.sgx_type = SgxType::Scalable;
+        ext.tcb.pce_svn = 0x1234;
+        ext.platform_instance_id = Some([0x3c; 16]);
+        ext.configuration = Some(PlatformConfiguration {
+            dynamic_platform: Some(true),
+            cached_keys: Some(false),
+            smt_enabled: None,
+        });
+        let pki = TestPki::with_pck_ca("Intel SGX PCK Platform CA", &[sgx_extension(&ext)]);
+
+        let cert = PckCert::parse(&pki.pck_cert).unwrap();
+        assert_eq!(cert.sgx_extensions(), &ext);
+        assert_eq!(cert.ca(), PckCa::Platform);
+    }
+
+    #[test]
+    fn test_ca_of_issuer() {
+        // The issuer decides, not whether the platform instance ID is present.
+        let mut ext = sample_sgx_extensions();
+        ext.platform_instance_id = Some([0x3c; 16]);
+        let pki = TestPki::with_pck_ca("Intel SGX PCK Processor CA", &[sgx_extension(&ext)]);
+        assert_eq!(PckCert::parse(&pki.pck_cert).unwrap().ca(), PckCa::Processor);
+
+        let pki = TestPki::with_pck_ca("Intel SGX PCK Platform CA", &[sgx_extension(&sample_sgx_extensions())]);
+        assert_eq!(PckCert::parse(&pki.pck_cert).unwrap().ca(), PckCa::Platform);
+    }
+
+    #[test]
+    fn test_reject_unknown_issuer() {
+        let pki = TestPki::with_pck_ca("Test SGX PCK Platform CA", &[sgx_extension(&sample_sgx_extensions())]);
+        assert_eq!(
+            PckCert::parse(&pki.pck_cert),
+            Err(Error::UnknownPckCa("Test SGX PCK Platform CA".into())),
+        );
+    }
+
+    #[test]
+    fn test_reject_missing_sgx_extensions() {
+        let pki = TestPki::with_pck_extensions(&[]);
+        assert_eq!(PckCert::parse(&pki.pck_cert), Err(Error::MissingSgxExtension("SGX extensions")));
+        assert_eq!(PckCert::parse(&pki.root_ca), Err(Error::MissingSgxExtension("SGX extensions")));
+    }
+
+    #[test]
+    fn test_reject_invalid_sgx_extensions() {
+        let ext = sgx_extension(&sample_sgx_extensions());
+        let mut reader = DerReader::new(&ext);
+        let mut ext = reader.read_sequence("extension").unwrap();
+        ext.read(TAG_OID, "extension ID").unwrap();
+        let value = ext.read(TAG_OCTET_STRING, "extension value").unwrap();
+        assert!(SgxExtensions::parse(value).is_ok());
+        assert!(SgxExtensions::parse(&value[..value.len() - 1]).is_err());
+
+        let short_fmspc = der::sequence(&[&entry(&[FMSPC], &der::tlv(TAG_OCTET_STRING, &[0; 5]))]);
+        assert_eq!(
+            SgxExtensions::parse(&short_fmspc),
+            Err(Error::InvalidLength { field: "FMSPC", expected: 6, actual: 5 }),
+        );
+        let fmspc_only = der::sequence(&[&entry(&[FMSPC], &der::tlv(TAG_OCTET_STRING, &[0; 6]))]);
+        assert_eq!(SgxExtensions::parse(&fmspc_only), Err(Error::MissingSgxExtension("PPID")));
+    }
+}
@@ -0,0 +1,362 @@
+use alloc::{string::{String, ToString}, vec::Vec};
+#[cfg(feature = "pcs")]
+use client::Client;
+#[cfg(feature = "pcs")]
+use crate::collateral::{TCB_INFO, QE_IDENTITY, PCK_CRL, ROOT_CA_CRL};
+use crate::error::{Error, Result};
+use crate::pck::PckCa;
+#[cfg(feature = "pcs")]
+use crate::provider::{CollateralProvider, SignedCollateral};
+use crate::provider::{tee_name, hex};
+use crate::quote::TeeType;
+
+/// Base URL of Intel PCS
+pub const INTEL_PCS_URL: &str = "https://api.trustedservices.intel.com";
+/// Where Intel PCS publishes the CRL of the Intel SGX Root CA
+pub const INTEL_ROOT_CA_CRL_URL: &str = "https://certificates.trustedservices.intel.com/IntelSGXRootCA.der";
+
+/// Version of the PCS REST API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcsVersion {
+    V3,
+    /// Adds TDX collateral and TCB info v3
+    V4,
+}
+
+impl PcsVersion {
+    fn as_str(self) -> &'static str {
+        match self {
+            PcsVersion::V3 => "v3",
+            PcsVersion::V4 => "v4",
+        }
+    }
+}
+
+/// Builds the URLs of the collateral served by Intel PCS or a PCCS.
+#[derive(Debug, Clone)]
+struct Endpoint {
+    base_url: String,
+    version: PcsVersion,
+    pccs: bool,
+}
+
+impl Endpoint {
+    fn intel(version: PcsVersion) -> Self {
+        Endpoint {
+            base_url: INTEL_PCS_URL.to_string(),
+            version,
+            pccs: false,
+        }
+    }
+
+    fn pccs(base_url: &str, version: PcsVersion) -> Self {
+        Endpoint {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            version,
+            pccs: true,
+        }
+    }
+
+    /// Fails for TDX collateral from v3, which serves only SGX collateral.
+    fn url(&self, tee_type: TeeType, path: &str) -> Result<String> {
+        if tee_type == TeeType::Tdx && self.version == PcsVersion::V3 {
+            return Err(Error::UnsupportedPcsCollateral {
+                tee: tee_name(tee_type),
+                version: self.version.as_str(),
+            });
+        }
+        Ok(format!("{}/{}/certification/{}/{}", self.base_url, tee_name(tee_type), self.version.as_str(), path))
+    }
+
+    fn tcb_info_url(&self, tee_type: TeeType, fmspc: &[u8; 6]) -> Result<String> {
+        self.url(tee_type, &format!("tcb?fmspc={}", hex(fmspc)))
+    }
+
+    fn qe_identity_url(&self, tee_type: TeeType) -> Result<String> {
+        self.url(tee_type, "qe/identity")
+    }
+
+    fn pck_crl_url(&self, ca: PckCa) -> Result<String> {
+        let encoding = match self.version {
+            PcsVersion::V3 => "",
+            PcsVersion::V4 => "&encoding=der",
+        };
+        self.url(TeeType::Sgx, &format!("pckcrl?ca={}{}", ca.as_str(), encoding))
+    }
+
+    fn root_ca_crl_url(&self) -> Result<String> {
+        if self.pccs {
+            self.url(TeeType::Sgx, "rootcacrl")
+        } else {
+            Ok(INTEL_ROOT_CA_CRL_URL.to_string())
+        }
+    }
+}
+
+/// Fetches collateral from Intel PCS, or from a PCCS which caches PCS and
+/// serves the same API.
+#[cfg(feature = "pcs")]
+pub struct PcsProvider {
+    client: Client,
+    endpoint: Endpoint,
+}
+
+#[cfg(feature = "pcs")]
+impl PcsProvider {
+    pub fn intel(version: PcsVersion) -> Result<Self> {
+        let client = Client::builder()
+            .https_only(true)
+            .build()
+            .map_err(|e| Error::HttpClient(e.to_string()))?;
+        Ok(PcsProvider {
+            client,
+            endpoint: Endpoint::intel(version),
+        })
+    }
+
+    /// A self-hosted PCCS at `base_url`, e.g. `https://localhost:8081`, or
+    /// `http://` for one on the same host
+    pub fn pccs(base_url: &str, version: PcsVersion) -> Result<Self> {
+        let client = Client::builder()
+            .build()
+            .map_err(|e| Error::HttpClient(e.to_string()))?;
+        Ok(PcsProvider {
+            client,
+            endpoint: Endpoint::pccs(base_url, version),
+        })
+    }
+
+    /// Sends requests with `client`, e.g. to go through a proxy.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn get(&self, collateral: &'static str, url: &str) -> Result<client::Response> {
+        let resp = self.client.get(url).send().map_err(|e| unavailable(collateral, e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(unavailable(collateral, format!("{} responded with {}", url, resp.status())));
+        }
+        Ok(resp)
+    }
+
+    /// Gets collateral with its issuer chain, which is URL-encoded PEM in
+    /// one of `headers`.
+    fn get_signed(&self, collateral: &'static str, url: &str, headers: &[&str]) -> Result<SignedCollateral> {
+        let resp = self.get(collateral, url)?;
+        let chain = headers.iter()
+            .filter_map(|name| resp.headers().get(*name))
+            .next()
+            .ok_or_else(|| unavailable(collateral, format!("{} header is missing", headers[0])))?;
+        let issuer_chain = chain.to_str().ok()
+            .and_then(percent_decode)
+            .ok_or_else(|| unavailable(collateral, format!("invalid {} header", headers[0])))?;
+
+        Ok(SignedCollateral {
+            data: resp.into_bytes(),
+            issuer_chain,
+        })
+    }
+}
+
+#[cfg(feature = "pcs")]
+impl CollateralProvider for PcsProvider {
+    fn tcb_info(&self, tee_type: TeeType, fmspc: &[u8; 6]) -> Result<SignedCollateral> {
+        // The header was renamed in v4.
+        let headers = ["TCB-Info-Issuer-Chain", "SGX-TCB-Info-Issuer-Chain"];
+        self.get_signed(TCB_INFO, &self.endpoint.tcb_info_url(tee_type, fmspc)?, &headers)
+    }
+
+    fn qe_identity(&self, tee_type: TeeType) -> Result<SignedCollateral> {
+        let headers = ["SGX-Enclave-Identity-Issuer-Chain"];
+        self.get_signed(QE_IDENTITY, &self.endpoint.qe_identity_url(tee_type)?, &headers)
+    }
+
+    fn pck_crl(&self, ca: PckCa) -> Result<SignedCollateral> {
+        let headers = ["SGX-PCK-CRL-Issuer-Chain"];
+        self.get_signed(PCK_CRL, &self.endpoint.pck_crl_url(ca)?, &headers)
+    }
+
+    fn root_ca_crl(&self) -> Result<Vec<u8>> {
+        Ok(self.get(ROOT_CA_CRL, &self.endpoint.root_ca_crl_url()?)?.into_bytes())
+    }
+}
+
+#[cfg(feature = "pcs")]
+fn unavailable(collateral: &'static str, reason: String) -> Error {
+    Error::CollateralUnavailable { collateral, reason }
+}
+
+fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FMSPC: [u8; 6] = [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00];
+
+    #[test]
+    fn test_intel_pcs_urls() {
+        let pcs = Endpoint::intel(PcsVersion::V4);
+        assert_eq!(
+            pcs.tcb_info_url(TeeType::Tdx, &FMSPC).unwrap(),
+            "https://api.trustedservices.intel.com/tdx/certification/v4/tcb?fmspc=00906ea10000",
+        );
+        assert_eq!(
+            pcs.qe_identity_url(TeeType::Sgx).unwrap(),
+            "https://api.trustedservices.intel.com/sgx/certification/v4/qe/identity",
+        );
+        assert_eq!(
+            pcs.pck_crl_url(PckCa::Platform).unwrap(),
+            "https://api.trustedservices.intel.com/sgx/certification/v4/pckcrl?ca=platform&encoding=der",
+        );
+        assert_eq!(pcs.root_ca_crl_url().unwrap(), INTEL_ROOT_CA_CRL_URL);
+    }
+
+    #[test]
+    fn test_pccs_urls() {
+        let pccs = Endpoint::pccs("https://localhost:8081/", PcsVersion::V3);
+        assert_eq!(
+            pccs.tcb_info_url(TeeType::Sgx, &FMSPC).unwrap(),
+            "https://localhost:8081/sgx/certification/v3/tcb?fmspc=00906ea10000",
+        );
+        assert_eq!(pccs.pck_crl_url(PckCa::Processor).unwrap(), "https://localhost:8081/sgx/certification/v3/pckcrl?ca=processor");
+        assert_eq!(pccs.root_ca_crl_url().unwrap(), "https://localhost:8081/sgx/certification/v3/rootcacrl");
+    }
+
+    #[test]
+    fn test_reject_tdx_from_v3() {
+        let pcs = Endpoint::intel(PcsVersion::V3);
+        let err = Error::UnsupportedPcsCollateral { tee: "tdx", version: "v3" };
+        assert_eq!(pcs.tcb_info_url(TeeType::Tdx, &FMSPC), Err(err.clone()));
+        assert_eq!(pcs.qe_identity_url(TeeType::Tdx), Err(err));
+        assert!(pcs.tcb_info_url(TeeType::Sgx, &FMSPC).is_ok());
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("-----BEGIN%20CERTIFICATE-----%0AMIIC%2B%2F==%0A").unwrap(),
+            b"-----BEGIN CERTIFICATE-----\nMIIC+/==\n".to_vec(),
+        );
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+    }
+}
+
+/// Tests of the HTTP path, against a local server, which run only inside an
+/// enclave like the client's
+#[cfg(feature = "enclave-test")]
+pub mod enclave_tests {
+    use super::*;
+    use core::str;
+    use client::test_server::TestServer;
+    use test_utils::*;
+    use crate::crl::Crl;
+    use crate::test_certs::{TestPki, pem_crl, NOW};
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_get_tcb_info,
+            test_renamed_issuer_chain_header,
+            test_missing_issuer_chain,
+            test_error_status,
+            test_der_and_pem_crl,
+        )
+    }
+
+    const FMSPC: [u8; 6] = [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00];
+    const CHAIN: &str = "-----BEGIN%20CERTIFICATE-----%0AMIIB%0A-----END%20CERTIFICATE-----%0A";
+    const CHAIN_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+
+    fn response(status: &str, header: &str, body: &[u8]) -> Vec<u8> {
+        let mut resp = format!("HTTP/1.1 {}\r\n{}Content-Length: {}\r\n\r\n", status, header, body.len()).into_bytes();
+        resp.extend_from_slice(body);
+        resp
+    }
+
+    fn pccs(server: &TestServer, version: PcsVersion) -> PcsProvider {
+        PcsProvider::pccs(&format!("http://127.0.0.1:{}", server.port()), version).unwrap()
+    }
+
+    fn test_get_tcb_info() {
+        let header = format!("TCB-Info-Issuer-Chain: {}\r\n", CHAIN);
+        let server = TestServer::plain(vec![response("200 OK", &header, b"{}")]);
+        let tcb_info = pccs(&server, PcsVersion::V3).tcb_info(TeeType::Sgx, &FMSPC).unwrap();
+        assert_eq!(tcb_info.data, b"{}".to_vec());
+        assert_eq!(tcb_info.issuer_chain, CHAIN_PEM.to_vec());
+
+        let requests = server.requests().unwrap();
+        let request = str::from_utf8(&requests[0]).unwrap();
+        assert!(request.starts_with("GET /sgx/certification/v3/tcb?fmspc=00906ea10000 HTTP/1.1\r\n"), "{}", request);
+    }
+
+    fn test_renamed_issuer_chain_header() {
+        let header = format!("SGX-TCB-Info-Issuer-Chain: {}\r\n", CHAIN);
+        let server = TestServer::plain(vec![response("200 OK", &header, b"{}")]);
+        let tcb_info = pccs(&server, PcsVersion::V4).tcb_info(TeeType::Tdx, &FMSPC).unwrap();
+        assert_eq!(tcb_info.issuer_chain, CHAIN_PEM.to_vec());
+    }
+
+    fn test_missing_issuer_chain() {
+        let server = TestServer::plain(vec![
+            response("200 OK", "", b"{}"),
+            response("200 OK", "SGX-Enclave-Identity-Issuer-Chain: %zz\r\n", b"{}"),
+        ]);
+        let pccs = pccs(&server, PcsVersion::V4);
+        assert_eq!(
+            pccs.tcb_info(TeeType::Sgx, &FMSPC),
+            Err(unavailable(TCB_INFO, "TCB-Info-Issuer-Chain header is missing".to_string())),
+        );
+        assert_eq!(
+            pccs.qe_identity(TeeType::Sgx),
+            Err(unavailable(QE_IDENTITY, "invalid SGX-Enclave-Identity-Issuer-Chain header".to_string())),
+        );
+    }
+
+    fn test_error_status() {
+        let server = TestServer::plain(vec![response("404 Not Found", "", b"")]);
+        let url = format!("http://127.0.0.1:{}/sgx/certification/v4/qe/identity", server.port());
+        assert_eq!(
+            pccs(&server, PcsVersion::V4).qe_identity(TeeType::Sgx),
+            Err(unavailable(QE_IDENTITY, format!("{} responded with 404 Not Found", url))),
+        );
+    }
+
+    /// PCS v4 serves DER CRLs when asked and v3 PEM ones, which verify the same.
+    fn test_der_and_pem_crl() {
+        let pki = TestPki::new();
+        let der = pki.pck_crl(NOW - 60, NOW + 60, &[]);
+        let header = format!("SGX-PCK-CRL-Issuer-Chain: {}\r\n", CHAIN);
+        let v4 = TestServer::plain(vec![response("200 OK", &header, &der)]);
+        let v3 = TestServer::plain(vec![response("200 OK", &header, &pem_crl(&der))]);
+
+        let der_crl = pccs(&v4, PcsVersion::V4).pck_crl(PckCa::Processor).unwrap();
+        let pem_crl = pccs(&v3, PcsVersion::V3).pck_crl(PckCa::Processor).unwrap();
+        assert_eq!(der_crl.data, der);
+        assert_eq!(
+            Crl::verify(PCK_CRL, &der_crl.data, &pki.intermediate_ca, NOW),
+            Crl::verify(PCK_CRL, &pem_crl.data, &pki.intermediate_ca, NOW),
+        );
+        assert!(Crl::verify(PCK_CRL, &pem_crl.data, &pki.intermediate_ca, NOW).is_ok());
+
+        let requests = v4.requests().unwrap();
+        let request = str::from_utf8(&requests[0]).unwrap();
+        assert!(request.starts_with("GET /sgx/certification/v4/pckcrl?ca=processor&encoding=der HTTP/1.1\r\n"), "{}", request);
+    }
+}
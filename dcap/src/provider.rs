@@ -0,0 +1,174 @@
+use alloc::vec::Vec;
+#[cfg(any(test, feature = "std", feature = "pcs", feature = "sim"))]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use crate::collateral::{TCB_INFO, QE_IDENTITY, PCK_CRL, ROOT_CA_CRL};
+use crate::error::Result;
+use crate::pck::PckCa;
+use crate::quote::TeeType;
+use crate::verify::VerifiedQuote;
+
+/// Collateral as served, with the PEM-encoded certificate chain of its issuer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedCollateral {
+    pub data: Vec<u8>,
+    pub issuer_chain: Vec<u8>,
+}
+
+/// Everything needed to evaluate a quote of a platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collateral {
+    /// TCB info JSON
+    pub tcb_info: SignedCollateral,
+    /// QE identity JSON
+    pub qe_identity: SignedCollateral,
+    /// CRL of the CA which issued the PCK certificate
+    pub pck_crl: SignedCollateral,
+    /// CRL of the root CA
+    pub root_ca_crl: Vec<u8>,
+}
+
+/// A source of DCAP collateral, such as Intel PCS, a PCCS or local files
+pub trait CollateralProvider {
+    fn tcb_info(&self, tee_type: TeeType, fmspc: &[u8; 6]) -> Result<SignedCollateral>;
+
+    fn qe_identity(&self, tee_type: TeeType) -> Result<SignedCollateral>;
+
+    fn pck_crl(&self, ca: PckCa) -> Result<SignedCollateral>;
+
+    fn root_ca_crl(&self) -> Result<Vec<u8>>;
+
+    /// Fetches the collateral for the platform which generated `quote`.
+    fn collateral(&self, quote: &VerifiedQuote) -> Result<Collateral> {
+        let tee_type = quote.header().tee_type;
+        Ok(Collateral {
+            tcb_info: self.tcb_info(tee_type, &quote.pck_cert().fmspc())?,
+            qe_identity: self.qe_identity(tee_type)?,
+            pck_crl: self.pck_crl(quote.pck_cert().ca())?,
+            root_ca_crl: self.root_ca_crl()?,
+        })
+    }
+}
+
+#[cfg(any(feature = "std", feature = "pcs"))]
+pub(crate) fn tee_name(tee_type: TeeType) -> &'static str {
+    match tee_type {
+        TeeType::Sgx => "sgx",
+        TeeType::Tdx => "tdx",
+    }
+}
+
+#[cfg(any(test, feature = "std", feature = "pcs", feature = "sim"))]
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(feature = "std")]
+pub use self::local::LocalDirProvider;
+
+#[cfg(feature = "std")]
+mod local {
+    use std::{fs, path::PathBuf, vec::Vec};
+    use super::*;
+    use crate::error::Error;
+
+    /// Reads collateral from files in a directory, e.g. fixtures or
+    /// collateral saved for offline verification.
+    ///
+    /// ```text
+    /// tcb_info-{sgx|tdx}-{fmspc}.json
+    /// qe_identity-{sgx|tdx}.json
+    /// pck_crl-{processor|platform}.crl
+    /// root_ca_crl.crl
+    /// ```
+    ///
+    /// FMSPCs are in lowercase hex, and the issuer chain of each signed
+    /// collateral is next to it, with `.issuer_chain.pem` in place of the
+    /// extension.
+    #[derive(Debug, Clone)]
+    pub struct LocalDirProvider {
+        dir: PathBuf,
+    }
+
+    impl LocalDirProvider {
+        pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+            LocalDirProvider { dir: dir.into() }
+        }
+
+        fn read(&self, collateral: &'static str, name: &str) -> Result<Vec<u8>> {
+            fs::read(self.dir.join(name)).map_err(|e| Error::CollateralUnavailable {
+                collateral,
+                reason: format!("{}: {}", name, e),
+            })
+        }
+
+        fn read_signed(&self, collateral: &'static str, stem: &str, ext: &str) -> Result<SignedCollateral> {
+            Ok(SignedCollateral {
+                data: self.read(collateral, &format!("{}.{}", stem, ext))?,
+                issuer_chain: self.read(collateral, &format!("{}.issuer_chain.pem", stem))?,
+            })
+        }
+    }
+
+    impl CollateralProvider for LocalDirProvider {
+        fn tcb_info(&self, tee_type: TeeType, fmspc: &[u8; 6]) -> Result<SignedCollateral> {
+            let stem = format!("tcb_info-{}-{}", tee_name(tee_type), hex(fmspc));
+            self.read_signed(TCB_INFO, &stem, "json")
+        }
+
+        fn qe_identity(&self, tee_type: TeeType) -> Result<SignedCollateral> {
+            let stem = format!("qe_identity-{}", tee_name(tee_type));
+            self.read_signed(QE_IDENTITY, &stem, "json")
+        }
+
+        fn pck_crl(&self, ca: PckCa) -> Result<SignedCollateral> {
+            let stem = format!("pck_crl-{}", ca.as_str());
+            self.read_signed(PCK_CRL, &stem, "crl")
+        }
+
+        fn root_ca_crl(&self) -> Result<Vec<u8>> {
+            self.read(ROOT_CA_CRL, "root_ca_crl.crl")
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_certs::{TestPki, NOW};
+        use crate::verify::{verify_quote, tests::sgx_quote};
+
+        #[test]
+        fn test_read_collateral_from_dir() {
+            let dir = std::env::temp_dir().join(format!("dcap-collateral-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let files: &[(&str, &[u8])] = &[
+                ("tcb_info-sgx-00906ea10000.json", b"tcb info"),
+                ("tcb_info-sgx-00906ea10000.issuer_chain.pem", b"tcb chain"),
+                ("qe_identity-sgx.json", b"qe identity"),
+                ("qe_identity-sgx.issuer_chain.pem", b"qe chain"),
+                ("pck_crl-processor.crl", b"pck crl"),
+                ("pck_crl-processor.issuer_chain.pem", b"pck crl chain"),
+                ("root_ca_crl.crl", b"root crl"),
+            ];
+            for (name, data) in files {
+                fs::write(dir.join(name), data).unwrap();
+            }
+
+            let pki = TestPki::new();
+            let quote = verify_quote(&sgx_quote(&pki), &pki.root_ca, NOW).unwrap();
+            let provider = LocalDirProvider::new(&dir);
+            let collateral = provider.collateral(&quote).unwrap();
+            assert_eq!(collateral.tcb_info.data, b"tcb info");
+            assert_eq!(collateral.tcb_info.issuer_chain, b"tcb chain");
+            assert_eq!(collateral.qe_identity.data, b"qe identity");
+            assert_eq!(collateral.pck_crl.issuer_chain, b"pck crl chain");
+            assert_eq!(collateral.root_ca_crl, b"root crl");
+
+            match provider.qe_identity(TeeType::Tdx) {
+                Err(Error::CollateralUnavailable { collateral, .. }) => assert_eq!(collateral, QE_IDENTITY),
+                r => panic!("unexpected result: {:?}", r),
+            }
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+use crate::error::{Error, Result};
+use crate::reader::Reader;
+use crate::report::{EnclaveReportBody, TdReportBody, ENCLAVE_REPORT_BODY_LEN, TD_REPORT10_BODY_LEN, TD_REPORT15_BODY_LEN};
+
+/// Length of the quote header
+pub const QUOTE_HEADER_LEN: usize = 48;
+
+/// QE vendor ID of the Intel quoting enclave
+pub const INTEL_QE_VENDOR_ID: [u8; 16] = [
+    0x93, 0x9a, 0x72, 0x33, 0xf7, 0x9c, 0x4c, 0xa9,
+    0x94, 0x0a, 0x0d, 0xb3, 0x95, 0x7f, 0x06, 0x07,
+];
+
+const QUOTE_VERSION_3: u16 = 3;
+const QUOTE_VERSION_4: u16 = 4;
+const QUOTE_VERSION_5: u16 = 5;
+
+const TEE_TYPE_SGX: u32 = 0x0000_0000;
+pub(crate) const TEE_TYPE_TDX: u32 = 0x0000_0081;
+
+const BODY_TYPE_SGX: u16 = 1;
+const BODY_TYPE_TD_REPORT10: u16 = 2;
+const BODY_TYPE_TD_REPORT15: u16 = 3;
+
+const CERT_TYPE_QE_REPORT_CERTIFICATION_DATA: u16 = 6;
+
+/// Defines a fixed-size byte string, since arrays longer than 32 don't
+/// implement `Debug` and `PartialEq` on every toolchain we build with.
+macro_rules! byte_string {
+    ($(#[$attr:meta])* $name:ident, $len:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            pub fn new(bytes: [u8; $len]) -> Self {
+                $name(bytes)
+            }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0[..]
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_tuple(stringify!($name)).field(&&self.0[..]).finish()
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0[..] == other.0[..]
+            }
+        }
+
+        impl Eq for $name {}
+    };
+}
+
+byte_string!(
+    /// An ECDSA P-256 signature as `r || s`
+    EcdsaSignature, 64
+);
+byte_string!(
+    /// An ECDSA P-256 public key as `x || y`
+    EcdsaPublicKey, 64
+);
+
+/// Type of the key which signs the quote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationKeyType {
+    EcdsaP256,
+}
+
+impl AttestationKeyType {
+    fn from_u16(v: u16) -> Result<Self> {
+        match v {
+            2 => Ok(AttestationKeyType::EcdsaP256),
+            _ => Err(Error::UnsupportedAttestationKeyType(v)),
+        }
+    }
+}
+
+/// The TEE which generated the quote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeeType {
+    Sgx,
+    Tdx,
+}
+
+impl TeeType {
+    pub(crate) fn from_u32(v: u32) -> Result<Self> {
+        match v {
+            TEE_TYPE_SGX => Ok(TeeType::Sgx),
+            TEE_TYPE_TDX => Ok(TeeType::Tdx),
+            _ => Err(Error::UnsupportedTeeType(v)),
+        }
+    }
+}
+
+/// The quote header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteHeader {
+    pub version: u16,
+    pub attestation_key_type: AttestationKeyType,
+    pub tee_type: TeeType,
+    pub qe_svn: u16,
+    pub pce_svn: u16,
+    pub qe_vendor_id: [u8; 16],
+    pub user_data: [u8; 20],
+}
+
+impl QuoteHeader {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let version = reader.read_u16("version")?;
+        if version != QUOTE_VERSION_3 && version != QUOTE_VERSION_4 && version != QUOTE_VERSION_5 {
+            return Err(Error::UnsupportedQuoteVersion(version));
+        }
+        let attestation_key_type = AttestationKeyType::from_u16(reader.read_u16("attestation key type")?)?;
+        // The TEE type is reserved in version 3, which is for SGX only.
+        let raw_tee_type = reader.read_u32("TEE type")?;
+        let tee_type = TeeType::from_u32(raw_tee_type)?;
+        if version == QUOTE_VERSION_3 && tee_type != TeeType::Sgx {
+            return Err(Error::UnsupportedTeeType(raw_tee_type));
+        }
+
+        Ok(QuoteHeader {
+            version,
+            attestation_key_type,
+            tee_type,
+            qe_svn: reader.read_u16("QE SVN")?,
+            pce_svn: reader.read_u16("PCE SVN")?,
+            qe_vendor_id: array!(reader.read_bytes(16, "QE vendor ID")?, 16),
+            user_data: array!(reader.read_bytes(20, "user data")?, 20),
+        })
+    }
+}
+
+/// The body of a quote, which is the report of the attested TEE
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteBody {
+    SgxEnclave(Box<EnclaveReportBody>),
+    TdReport10(TdReportBody),
+    TdReport15(TdReportBody),
+}
+
+impl QuoteBody {
+    fn read(reader: &mut Reader, header: &QuoteHeader) -> Result<Self> {
+        match (header.version, header.tee_type) {
+            (QUOTE_VERSION_5, _) => {
+                let body_type = reader.read_u16("body type")?;
+                let len = reader.read_u32("body length")? as usize;
+                let expected = match (body_type, header.tee_type) {
+                    (BODY_TYPE_SGX, TeeType::Sgx) => ENCLAVE_REPORT_BODY_LEN,
+                    (BODY_TYPE_TD_REPORT10, TeeType::Tdx) => TD_REPORT10_BODY_LEN,
+                    (BODY_TYPE_TD_REPORT15, TeeType::Tdx) => TD_REPORT15_BODY_LEN,
+                    _ => return Err(Error::UnsupportedBodyType(body_type)),
+                };
+                if len != expected {
+                    return Err(Error::InvalidLength { field: "body", expected, actual: len });
+                }
+                match body_type {
+                    BODY_TYPE_SGX => Ok(QuoteBody::SgxEnclave(Box::new(EnclaveReportBody::read(reader)?))),
+                    BODY_TYPE_TD_REPORT10 => Ok(QuoteBody::TdReport10(TdReportBody::read(reader, len)?)),
+                    _ => Ok(QuoteBody::TdReport15(TdReportBody::read(reader, len)?)),
+                }
+            }
+            (_, TeeType::Sgx) => Ok(QuoteBody::SgxEnclave(Box::new(EnclaveReportBody::read(reader)?))),
+            (_, TeeType::Tdx) => Ok(QuoteBody::TdReport10(TdReportBody::read(reader, TD_REPORT10_BODY_LEN)?)),
+        }
+    }
+
+    /// The report data, which binds data of the attested TEE to the quote
+    pub fn report_data(&self) -> [u8; 64] {
+        match self {
+            QuoteBody::SgxEnclave(body) => body.report_data(),
+            QuoteBody::TdReport10(body) | QuoteBody::TdReport15(body) => body.report_data(),
+        }
+    }
+}
+
+/// An ECDSA quote of version 3, 4 (SGX and TDX 1.0) or 5 (TDX 1.5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quote {
+    pub header: QuoteHeader,
+    pub body: QuoteBody,
+    pub signature: QuoteSignatureData,
+    signed_data: Vec<u8>,
+}
+
+impl Quote {
+    /// Decodes a quote. Bytes following the signature data are ignored, since
+    /// quote buffers are often allocated larger than the quote.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let header = QuoteHeader::read(&mut reader)?;
+        let body = QuoteBody::read(&mut reader, &header)?;
+        let signed_data = bytes[..reader.offset()].to_vec();
+
+        let len = reader.read_u32("signature data length")? as usize;
+        let sig_data = reader.read_bytes(len, "signature data")?;
+        let signature = QuoteSignatureData::parse(sig_data, header.version)?;
+
+        Ok(Quote {
+            header,
+            body,
+            signature,
+            signed_data,
+        })
+    }
+
+    /// The header and the body, which are signed by the attestation key
+    pub fn signed_data(&self) -> &[u8] {
+        &self.signed_data[..]
+    }
+
+    /// The report body if this is an SGX quote
+    pub fn enclave_report_body(&self) -> Option<&EnclaveReportBody> {
+        match self.body {
+            QuoteBody::SgxEnclave(ref body) => Some(body),
+            _ => None,
+        }
+    }
+
+    /// The TD report body if this is a TDX quote
+    pub fn td_report_body(&self) -> Option<&TdReportBody> {
+        match self.body {
+            QuoteBody::TdReport10(ref body) | QuoteBody::TdReport15(ref body) => Some(body),
+            _ => None,
+        }
+    }
+}
+
+/// The signature data section of an ECDSA P-256 quote.
+///
+/// From version 4, the QE report and the PCK certificate chain are nested in
+/// the QE report certification data (type 6); they are flattened here so that
+/// every version is verified the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteSignatureData {
+    pub isv_enclave_report_signature: EcdsaSignature,
+    pub attestation_key: EcdsaPublicKey,
+    pub qe_report: EnclaveReportBody,
+    pub qe_report_signature: EcdsaSignature,
+    pub qe_auth_data: Vec<u8>,
+    pub certification_data: CertificationData,
+}
+
+impl QuoteSignatureData {
+    fn parse(bytes: &[u8], version: u16) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let isv_enclave_report_signature = EcdsaSignature(
+            array!(reader.read_bytes(64, "ISV enclave report signature")?, 64));
+        let attestation_key = EcdsaPublicKey(
+            array!(reader.read_bytes(64, "attestation key")?, 64));
+
+        let sig_data = if version == QUOTE_VERSION_3 {
+            Self::read_qe_report_certification_data(&mut reader, isv_enclave_report_signature, attestation_key)?
+        } else {
+            let cert_type = reader.read_u16("certification data type")?;
+            if cert_type != CERT_TYPE_QE_REPORT_CERTIFICATION_DATA {
+                return Err(Error::UnexpectedCertificationDataType(cert_type));
+            }
+            let len = reader.read_u32("certification data length")? as usize;
+            let mut nested = Reader::new(reader.read_bytes(len, "QE report certification data")?);
+            let sig_data = Self::read_qe_report_certification_data(&mut nested, isv_enclave_report_signature, attestation_key)?;
+            nested.finish("QE report certification data")?;
+            sig_data
+        };
+        reader.finish("signature data")?;
+
+        Ok(sig_data)
+    }
+
+    fn read_qe_report_certification_data(
+        reader: &mut Reader,
+        isv_enclave_report_signature: EcdsaSignature,
+        attestation_key: EcdsaPublicKey,
+    ) -> Result<Self> {
+        let qe_report = EnclaveReportBody::read(reader)?;
+        let qe_report_signature = EcdsaSignature(
+            array!(reader.read_bytes(64, "QE report signature")?, 64));
+        let qe_auth_data_len = reader.read_u16("QE auth data length")? as usize;
+        let qe_auth_data = reader.read_bytes(qe_auth_data_len, "QE auth data")?.to_vec();
+        let certification_data = CertificationData::read(reader)?;
+
+        Ok(QuoteSignatureData {
+            isv_enclave_report_signature,
+            attestation_key,
+            qe_report,
+            qe_report_signature,
+            qe_auth_data,
+            certification_data,
+        })
+    }
+}
+
+/// Type of the data to certify the attestation key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificationDataType {
+    PpidCleartext,
+    PpidRsa2048Encrypted,
+    PpidRsa3072Encrypted,
+    PckCleartext,
+    PckCertChain,
+    QeReportCertificationData,
+    PlatformManifest,
+    Unknown(u16),
+}
+
+impl From<u16> for CertificationDataType {
+    fn from(v: u16) -> Self {
+        match v {
+            1 => CertificationDataType::PpidCleartext,
+            2 => CertificationDataType::PpidRsa2048Encrypted,
+            3 => CertificationDataType::PpidRsa3072Encrypted,
+            4 => CertificationDataType::PckCleartext,
+            5 => CertificationDataType::PckCertChain,
+            6 => CertificationDataType::QeReportCertificationData,
+            7 => CertificationDataType::PlatformManifest,
+            _ => CertificationDataType::Unknown(v),
+        }
+    }
+}
+
+/// Data to certify the attestation key, e.g. the PCK certificate chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificationData {
+    pub cert_type: CertificationDataType,
+    pub data: Vec<u8>,
+}
+
+impl CertificationData {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let cert_type = CertificationDataType::from(reader.read_u16("certification data type")?);
+        let len = reader.read_u32("certification data length")? as usize;
+        let data = reader.read_bytes(len, "certification data")?.to_vec();
+
+        Ok(CertificationData { cert_type, data })
+    }
+
+    /// The PEM-encoded PCK certificate chain, from the leaf to the root CA
+    pub fn pck_cert_chain(&self) -> Option<&[u8]> {
+        match self.cert_type {
+            CertificationDataType::PckCertChain => Some(&self.data[..]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const PCK_CHAIN: &[u8] = b"-----BEGIN CERTIFICATE-----\n...\n-----END CERTIFICATE-----\n";
+
+    pub(crate) fn header(version: u16, tee_type: u32) -> Vec<u8> {
+        let mut header = vec![];
+        header.extend_from_slice(&version.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&tee_type.to_le_bytes());
+        header.extend_from_slice(&7u16.to_le_bytes());
+        header.extend_from_slice(&11u16.to_le_bytes());
+        header.extend_from_slice(&INTEL_QE_VENDOR_ID);
+        header.extend_from_slice(&[0xaa; 20]);
+        header
+    }
+
+    pub(crate) fn enclave_report_body() -> Vec<u8> {
+        let mut body = vec![0u8; ENCLAVE_REPORT_BODY_LEN];
+        body[64..96].copy_from_slice(&[0x11; 32]);
+        body[128..160].copy_from_slice(&[0x22; 32]);
+        body[256..258].copy_from_slice(&5u16.to_le_bytes());
+        body[258..260].copy_from_slice(&9u16.to_le_bytes());
+        body[320..384].copy_from_slice(&[0x33; 64]);
+        body
+    }
+
+    pub(crate) fn td_report_body(len: usize) -> Vec<u8> {
+        let mut body = vec![0u8; len];
+        body[16..64].copy_from_slice(&[0x12; 48]);
+        body[136..184].copy_from_slice(&[0x13; 48]);
+        for i in 0..4 {
+            body[328 + i * 48..376 + i * 48].copy_from_slice(&[0x20 + i as u8; 48]);
+        }
+        body[520..584].copy_from_slice(&[0x33; 64]);
+        if len == TD_REPORT15_BODY_LEN {
+            body[600..648].copy_from_slice(&[0x14; 48]);
+        }
+        body
+    }
+
+    /// QE report, its signature, QE auth data and the PCK certificate chain
+    fn qe_report_certification_data() -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(&[0x66; ENCLAVE_REPORT_BODY_LEN]);
+        data.extend_from_slice(&[0x77; 64]);
+        data.extend_from_slice(&32u16.to_le_bytes());
+        data.extend_from_slice(&[0x88; 32]);
+        data.extend_from_slice(&5u16.to_le_bytes());
+        data.extend_from_slice(&(PCK_CHAIN.len() as u32).to_le_bytes());
+        data.extend_from_slice(PCK_CHAIN);
+        data
+    }
+
+    fn encode_quote(version: u16, tee_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut sig_data = vec![];
+        sig_data.extend_from_slice(&[0x44; 64]);
+        sig_data.extend_from_slice(&[0x55; 64]);
+        if version == 3 {
+            sig_data.extend_from_slice(&qe_report_certification_data());
+        } else {
+            let nested = qe_report_certification_data();
+            sig_data.extend_from_slice(&6u16.to_le_bytes());
+            sig_data.extend_from_slice(&(nested.len() as u32).to_le_bytes());
+            sig_data.extend_from_slice(&nested);
+        }
+
+        let mut quote = header(version, tee_type);
+        quote.extend_from_slice(body);
+        quote.extend_from_slice(&(sig_data.len() as u32).to_le_bytes());
+        quote.extend_from_slice(&sig_data);
+        quote
+    }
+
+    /// Encodes a v3 quote with recognizable field values.
+    pub(crate) fn sample_quote() -> Vec<u8> {
+        encode_quote(3, 0, &enclave_report_body())
+    }
+
+    fn assert_signature_data(sig: &QuoteSignatureData) {
+        assert_eq!(sig.isv_enclave_report_signature, EcdsaSignature::new([0x44; 64]));
+        assert_eq!(sig.attestation_key, EcdsaPublicKey::new([0x55; 64]));
+        assert_eq!(sig.qe_report.as_bytes(), &[0x66; ENCLAVE_REPORT_BODY_LEN][..]);
+        assert_eq!(sig.qe_report_signature, EcdsaSignature::new([0x77; 64]));
+        assert_eq!(sig.qe_auth_data, vec![0x88; 32]);
+        assert_eq!(sig.certification_data.cert_type, CertificationDataType::PckCertChain);
+        assert_eq!(sig.certification_data.pck_cert_chain(), Some(PCK_CHAIN));
+    }
+
+    #[test]
+    fn test_parse_v3_quote() {
+        let bytes = sample_quote();
+        let quote = Quote::parse(&bytes).unwrap();
+
+        assert_eq!(quote.header.version, 3);
+        assert_eq!(quote.header.attestation_key_type, AttestationKeyType::EcdsaP256);
+        assert_eq!(quote.header.tee_type, TeeType::Sgx);
+        assert_eq!(quote.header.qe_svn, 7);
+        assert_eq!(quote.header.pce_svn, 11);
+        assert_eq!(quote.header.qe_vendor_id, INTEL_QE_VENDOR_ID);
+        assert_eq!(quote.header.user_data, [0xaa; 20]);
+
+        let body = quote.enclave_report_body().unwrap();
+        assert_eq!(body.mr_enclave(), [0x11; 32]);
+        assert_eq!(body.mr_signer(), [0x22; 32]);
+        assert_eq!(body.isv_prod_id(), 5);
+        assert_eq!(body.isv_svn(), 9);
+        assert_eq!(&quote.body.report_data()[..], &[0x33; 64][..]);
+        assert_eq!(quote.signed_data(), &bytes[..QUOTE_HEADER_LEN + ENCLAVE_REPORT_BODY_LEN]);
+        assert!(quote.td_report_body().is_none());
+
+        assert_signature_data(&quote.signature);
+    }
+
+    #[test]
+    fn test_parse_v4_sgx_quote() {
+        let bytes = encode_quote(4, 0, &enclave_report_body());
+        let quote = Quote::parse(&bytes).unwrap();
+
+        assert_eq!(quote.header.version, 4);
+        assert_eq!(quote.enclave_report_body().unwrap().mr_enclave(), [0x11; 32]);
+        assert_eq!(quote.signed_data(), &bytes[..QUOTE_HEADER_LEN + ENCLAVE_REPORT_BODY_LEN]);
+        assert_signature_data(&quote.signature);
+    }
+
+    #[test]
+    fn test_parse_v4_tdx_quote() {
+        let bytes = encode_quote(4, 0x81, &td_report_body(TD_REPORT10_BODY_LEN));
+        let quote = Quote::parse(&bytes).unwrap();
+
+        assert_eq!(quote.header.tee_type, TeeType::Tdx);
+        let body = quote.td_report_body().unwrap();
+        assert!(!body.is_tdx15());
+        assert_eq!(&body.mr_seam()[..], &[0x12; 48][..]);
+        assert_eq!(&body.mr_td()[..], &[0x13; 48][..]);
+        for i in 0..4 {
+            assert_eq!(&body.rtmr(i)[..], &[0x20 + i as u8; 48][..]);
+        }
+        assert_eq!(&quote.body.report_data()[..], &[0x33; 64][..]);
+        assert_eq!(body.mr_servicetd(), None);
+        assert_eq!(quote.signed_data(), &bytes[..QUOTE_HEADER_LEN + TD_REPORT10_BODY_LEN]);
+        assert_signature_data(&quote.signature);
+    }
+
+    #[test]
+    fn test_parse_v5_tdx15_quote() {
+        let mut body = vec![];
+        body.extend_from_slice(&3u16.to_le_bytes());
+        body.extend_from_slice(&(TD_REPORT15_BODY_LEN as u32).to_le_bytes());
+        body.extend_from_slice(&td_report_body(TD_REPORT15_BODY_LEN));
+        let bytes = encode_quote(5, 0x81, &body);
+        let quote = Quote::parse(&bytes).unwrap();
+
+        match quote.body {
+            QuoteBody::TdReport15(ref body) => {
+                assert!(body.is_tdx15());
+                assert_eq!(&body.mr_td()[..], &[0x13; 48][..]);
+                assert_eq!(&body.mr_servicetd().unwrap()[..], &[0x14; 48][..]);
+            }
+            ref other => panic!("unexpected body: {:?}", other),
+        }
+        assert_eq!(quote.signed_data(), &bytes[..QUOTE_HEADER_LEN + 6 + TD_REPORT15_BODY_LEN]);
+        assert_signature_data(&quote.signature);
+    }
+
+    #[test]
+    fn test_reject_truncated_quote() {
+        let bytes = sample_quote();
+        for len in &[0, 10, QUOTE_HEADER_LEN + 100, bytes.len() - 1] {
+            match Quote::parse(&bytes[..*len]) {
+                Err(Error::UnexpectedEof { .. }) => {}
+                other => panic!("unexpected result for length {}: {:?}", len, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reject_unsupported_header() {
+        let mut bytes = sample_quote();
+        bytes[0] = 2;
+        assert_eq!(Quote::parse(&bytes), Err(Error::UnsupportedQuoteVersion(2)));
+
+        let mut bytes = sample_quote();
+        bytes[2] = 3;
+        assert_eq!(Quote::parse(&bytes), Err(Error::UnsupportedAttestationKeyType(3)));
+
+        let bytes = encode_quote(3, 0x81, &td_report_body(TD_REPORT10_BODY_LEN));
+        assert_eq!(Quote::parse(&bytes), Err(Error::UnsupportedTeeType(0x81)));
+    }
+
+    #[test]
+    fn test_reject_body_type_of_other_tee() {
+        let v5_body = |body_type: u16, body: Vec<u8>| {
+            let mut v5_body = vec![];
+            v5_body.extend_from_slice(&body_type.to_le_bytes());
+            v5_body.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            v5_body.extend_from_slice(&body);
+            v5_body
+        };
+
+        let bytes = encode_quote(5, 0, &v5_body(2, td_report_body(TD_REPORT10_BODY_LEN)));
+        assert_eq!(Quote::parse(&bytes), Err(Error::UnsupportedBodyType(2)));
+
+        let bytes = encode_quote(5, 0, &v5_body(3, td_report_body(TD_REPORT15_BODY_LEN)));
+        assert_eq!(Quote::parse(&bytes), Err(Error::UnsupportedBodyType(3)));
+
+        let bytes = encode_quote(5, 0x81, &v5_body(1, enclave_report_body()));
+        assert_eq!(Quote::parse(&bytes), Err(Error::UnsupportedBodyType(1)));
+
+        let bytes = encode_quote(5, 0, &v5_body(1, enclave_report_body()));
+        assert!(Quote::parse(&bytes).is_ok());
+    }
+
+    #[test]
+    fn test_reject_inconsistent_lengths() {
+        let mut bytes = sample_quote();
+        let len_offset = QUOTE_HEADER_LEN + ENCLAVE_REPORT_BODY_LEN;
+        let len = u32::from_le_bytes(array!(&bytes[len_offset..len_offset + 4], 4));
+        bytes[len_offset..len_offset + 4].copy_from_slice(&(len - 1).to_le_bytes());
+        assert!(Quote::parse(&bytes).is_err());
+
+        let mut bytes = sample_quote();
+        bytes[len_offset..len_offset + 4].copy_from_slice(&(len + 1).to_le_bytes());
+        bytes.push(0);
+        assert_eq!(
+            Quote::parse(&bytes),
+            Err(Error::TrailingBytes { field: "signature data", len: 1 }),
+        );
+
+        let mut body = vec![];
+        body.extend_from_slice(&2u16.to_le_bytes());
+        body.extend_from_slice(&(TD_REPORT15_BODY_LEN as u32).to_le_bytes());
+        body.extend_from_slice(&td_report_body(TD_REPORT15_BODY_LEN));
+        let bytes = encode_quote(5, 0x81, &body);
+        assert_eq!(
+            Quote::parse(&bytes),
+            Err(Error::InvalidLength { field: "body", expected: TD_REPORT10_BODY_LEN, actual: TD_REPORT15_BODY_LEN }),
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use sgx_types::{sgx_report_t, sgx_target_info_t};
+#[cfg(any(test, feature = "dcap-ql"))]
+use sgx_types::sgx_quote3_error_t;
+#[cfg(any(test, feature = "dcap-ql"))]
+use crate::error::Error;
+use crate::error::Result;
+
+/// A quoting enclave, which turns reports of application enclaves into
+/// DCAP quotes.
+///
+/// An application enclave creates a report targeted at `target_info`, and the
+/// host passes it to `quote`. `DcapQl` is the Intel QE behind the DCAP quote
+/// library; a simulated QE can stand in for it on machines without SGX.
+pub trait QuotingEnclave {
+    /// The target info of the QE, for application enclaves to create reports with
+    fn target_info(&self) -> Result<sgx_target_info_t>;
+
+    /// The size of the quotes the QE generates
+    fn quote_size(&self) -> Result<u32>;
+
+    /// Generates a quote of `report`, which must be targeted at the QE.
+    fn quote(&self, report: &sgx_report_t) -> Result<Vec<u8>>;
+}
+
+#[cfg(feature = "dcap-ql")]
+pub use self::ql::DcapQl;
+
+/// Maps the status returned by a function of the quote library to `Result`.
+#[cfg(any(test, feature = "dcap-ql"))]
+fn check(function: &'static str, ret: sgx_quote3_error_t) -> Result<()> {
+    match ret {
+        sgx_quote3_error_t::SGX_QL_SUCCESS => Ok(()),
+        error => Err(Error::QuotingEnclave { function, error }),
+    }
+}
+
+#[cfg(feature = "dcap-ql")]
+mod ql {
+    use super::*;
+
+    #[link(name = "sgx_dcap_ql")]
+    extern "C" {
+        fn sgx_qe_get_target_info(p_qe_target_info: *mut sgx_target_info_t) -> sgx_quote3_error_t;
+        fn sgx_qe_get_quote_size(p_quote_size: *mut u32) -> sgx_quote3_error_t;
+        fn sgx_qe_get_quote(p_app_report: *const sgx_report_t, quote_size: u32, p_quote: *mut u8) -> sgx_quote3_error_t;
+    }
+
+    /// The Intel QE, through the DCAP quote library (`libsgx_dcap_ql`).
+    ///
+    /// The library loads the QE on first use and keeps it loaded for the process.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct DcapQl;
+
+    impl QuotingEnclave for DcapQl {
+        fn target_info(&self) -> Result<sgx_target_info_t> {
+            let mut target_info = sgx_target_info_t::default();
+            check("sgx_qe_get_target_info", unsafe { sgx_qe_get_target_info(&mut target_info) })?;
+            Ok(target_info)
+        }
+
+        fn quote_size(&self) -> Result<u32> {
+            let mut size = 0;
+            check("sgx_qe_get_quote_size", unsafe { sgx_qe_get_quote_size(&mut size) })?;
+            Ok(size)
+        }
+
+        fn quote(&self, report: &sgx_report_t) -> Result<Vec<u8>> {
+            let size = self.quote_size()?;
+            let mut quote = vec![0u8; size as usize];
+            check("sgx_qe_get_quote", unsafe { sgx_qe_get_quote(report, size, quote.as_mut_ptr()) })?;
+            Ok(quote)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A QE which reports the quote library statuses it is given, and quotes
+    /// by copying the report data into a quote of `size` bytes.
+    struct MockQe {
+        size: u32,
+        status: sgx_quote3_error_t,
+    }
+
+    impl QuotingEnclave for MockQe {
+        fn target_info(&self) -> Result<sgx_target_info_t> {
+            check("sgx_qe_get_target_info", self.status)?;
+            Ok(sgx_target_info_t::default())
+        }
+
+        fn quote_size(&self) -> Result<u32> {
+            check("sgx_qe_get_quote_size", self.status)?;
+            Ok(self.size)
+        }
+
+        fn quote(&self, report: &sgx_report_t) -> Result<Vec<u8>> {
+            let size = self.quote_size()?;
+            let mut quote = vec![0u8; size as usize];
+            check("sgx_qe_get_quote", self.status)?;
+            quote[..64].copy_from_slice(&report.body.report_data.d);
+            Ok(quote)
+        }
+    }
+
+    #[test]
+    fn test_quote_size_contract() {
+        let qe: &dyn QuotingEnclave = &MockQe { size: 4578, status: sgx_quote3_error_t::SGX_QL_SUCCESS };
+        let mut report = sgx_report_t::default();
+        report.body.report_data.d = [0x5a; 64];
+
+        let quote = qe.quote(&report).unwrap();
+        assert_eq!(quote.len(), qe.quote_size().unwrap() as usize);
+        assert_eq!(&quote[..64], &[0x5a; 64][..]);
+    }
+
+    #[test]
+    fn test_check_status() {
+        assert_eq!(check("sgx_qe_get_quote", sgx_quote3_error_t::SGX_QL_SUCCESS), Ok(()));
+        assert_eq!(
+            check("sgx_qe_get_quote", sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER),
+            Err(Error::QuotingEnclave {
+                function: "sgx_qe_get_quote",
+                error: sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_propagate_quote_library_error() {
+        let qe = MockQe { size: 4578, status: sgx_quote3_error_t::SGX_QL_INTERFACE_UNAVAILABLE };
+        let expected = |function| Error::QuotingEnclave {
+            function,
+            error: sgx_quote3_error_t::SGX_QL_INTERFACE_UNAVAILABLE,
+        };
+        assert_eq!(qe.target_info().err(), Some(expected("sgx_qe_get_target_info")));
+        assert_eq!(qe.quote_size().err(), Some(expected("sgx_qe_get_quote_size")));
+        assert_eq!(qe.quote(&sgx_report_t::default()).err(), Some(expected("sgx_qe_get_quote_size")));
+    }
+}
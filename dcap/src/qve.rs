@@ -0,0 +1,497 @@
+//! Quote verification delegated to the Intel Quote Verification Enclave (QvE).
+//!
+//! The host verifies a quote with the QvE, which reports the result to the
+//! enclave that asked for it. The enclave checks that the report comes from
+//! the QvE, and that its report data binds the result to the quote and to a
+//! nonce of the enclave, before trusting the result.
+use alloc::{string::String, vec::Vec};
+use sgx_types::sgx_report_t;
+use crate::error::{Error, Result};
+use crate::pck::SgxType;
+use crate::quote::TeeType;
+use crate::reader::Reader;
+use crate::tcb::TcbStatus;
+#[cfg(any(test, feature = "sgx"))]
+use {
+    attestation::VerifiedEnclave,
+    ring::digest,
+    crate::quote::{Quote, TEE_TYPE_TDX},
+    crate::report::EnclaveReportBody,
+};
+
+/// MRSIGNER of the QvE signed by Intel
+pub const INTEL_QVE_MR_SIGNER: [u8; 32] = [
+    0x8c, 0x4f, 0x57, 0x75, 0xd7, 0x96, 0x50, 0x3e, 0x96, 0x13, 0x7f, 0x77, 0xc6, 0x8a, 0x82, 0x9a,
+    0x00, 0x56, 0xac, 0x8d, 0xed, 0x70, 0x14, 0x0b, 0x08, 0x1b, 0x09, 0x44, 0x90, 0xc5, 0x7b, 0xff,
+];
+/// ISVPRODID of the QvE signed by Intel
+pub const INTEL_QVE_ISV_PROD_ID: u16 = 2;
+#[cfg(any(test, feature = "sgx"))]
+const SGX_FLAGS_DEBUG: u64 = 0x02;
+
+const SA_LIST_LEN: usize = 320;
+
+/// Result of quote verification by the QvE (`sgx_ql_qv_result_t`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QvResult {
+    Ok,
+    ConfigNeeded,
+    OutOfDate,
+    OutOfDateConfigNeeded,
+    InvalidSignature,
+    Revoked,
+    Unspecified,
+    SwHardeningNeeded,
+    ConfigAndSwHardeningNeeded,
+    Unknown(u32),
+}
+
+impl QvResult {
+    pub fn as_u32(self) -> u32 {
+        match self {
+            QvResult::Ok => 0,
+            QvResult::ConfigNeeded => 0xa001,
+            QvResult::OutOfDate => 0xa002,
+            QvResult::OutOfDateConfigNeeded => 0xa003,
+            QvResult::InvalidSignature => 0xa004,
+            QvResult::Revoked => 0xa005,
+            QvResult::Unspecified => 0xa006,
+            QvResult::SwHardeningNeeded => 0xa007,
+            QvResult::ConfigAndSwHardeningNeeded => 0xa008,
+            QvResult::Unknown(v) => v,
+        }
+    }
+
+    /// The TCB status of the platform, or `None` if the quote failed verification.
+    pub fn tcb_status(self) -> Option<TcbStatus> {
+        match self {
+            QvResult::Ok => Some(TcbStatus::UpToDate),
+            QvResult::ConfigNeeded => Some(TcbStatus::ConfigurationNeeded),
+            QvResult::OutOfDate => Some(TcbStatus::OutOfDate),
+            QvResult::OutOfDateConfigNeeded => Some(TcbStatus::OutOfDateConfigurationNeeded),
+            QvResult::Revoked => Some(TcbStatus::Revoked),
+            QvResult::SwHardeningNeeded => Some(TcbStatus::SwHardeningNeeded),
+            QvResult::ConfigAndSwHardeningNeeded => Some(TcbStatus::ConfigurationAndSwHardeningNeeded),
+            QvResult::InvalidSignature | QvResult::Unspecified | QvResult::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<u32> for QvResult {
+    fn from(v: u32) -> Self {
+        match v {
+            0 => QvResult::Ok,
+            0xa001 => QvResult::ConfigNeeded,
+            0xa002 => QvResult::OutOfDate,
+            0xa003 => QvResult::OutOfDateConfigNeeded,
+            0xa004 => QvResult::InvalidSignature,
+            0xa005 => QvResult::Revoked,
+            0xa006 => QvResult::Unspecified,
+            0xa007 => QvResult::SwHardeningNeeded,
+            0xa008 => QvResult::ConfigAndSwHardeningNeeded,
+            v => QvResult::Unknown(v),
+        }
+    }
+}
+
+/// Output of `sgx_qv_verify_quote` on the host, passed into the enclave
+#[derive(Clone)]
+pub struct QveOutput {
+    /// The QvE report targeted at the verifying enclave
+    pub qve_report: sgx_report_t,
+    /// The time the QvE checked the collateral expiration against, in
+    /// seconds since the Unix epoch
+    pub expiration_check_date: i64,
+    /// Nonzero if any collateral had expired at `expiration_check_date`
+    pub collateral_expiration_status: u32,
+    pub qv_result: QvResult,
+    /// `sgx_ql_qv_supplemental_t`, or empty if the host didn't request it
+    pub supplemental_data: Vec<u8>,
+}
+
+/// Supplemental data of QvE verification (`sgx_ql_qv_supplemental_t`),
+/// version 2 or 3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplementalData {
+    /// Major version in the low 16 bits, minor version in the high 16 bits
+    pub version: u32,
+    pub earliest_issue_date: i64,
+    pub latest_issue_date: i64,
+    pub earliest_expiration_date: i64,
+    pub tcb_level_date_tag: i64,
+    pub pck_crl_num: u32,
+    pub root_ca_crl_num: u32,
+    pub tcb_eval_ref_num: u32,
+    /// SHA-384 digest of the root CA public key
+    pub root_key_id: Vec<u8>,
+    pub ppid: [u8; 16],
+    pub cpu_svn: [u8; 16],
+    pub pce_svn: u16,
+    pub pce_id: u16,
+    /// Only from version 3
+    pub tee_type: Option<TeeType>,
+    pub sgx_type: SgxType,
+    pub platform_instance_id: [u8; 16],
+    pub dynamic_platform: Option<bool>,
+    pub cached_keys: Option<bool>,
+    pub smt_enabled: Option<bool>,
+    /// Advisories of the TCB level, only from version 3
+    pub advisory_ids: Vec<String>,
+}
+
+impl SupplementalData {
+    /// Decodes supplemental data. Fields added by newer minor versions are
+    /// ignored.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let version = r.read_u32("supplemental data version")?;
+        let v3 = match version & 0xffff {
+            2 => false,
+            3 => true,
+            _ => return Err(Error::UnsupportedSupplementalDataVersion(version)),
+        };
+        // The C struct aligns the dates, and the flags after the platform
+        // instance ID, to their natural alignment.
+        r.read_bytes(4, "supplemental data padding")?;
+        let earliest_issue_date = r.read_i64("earliest issue date")?;
+        let latest_issue_date = r.read_i64("latest issue date")?;
+        let earliest_expiration_date = r.read_i64("earliest expiration date")?;
+        let tcb_level_date_tag = r.read_i64("TCB level date tag")?;
+        let pck_crl_num = r.read_u32("PCK CRL number")?;
+        let root_ca_crl_num = r.read_u32("root CA CRL number")?;
+        let tcb_eval_ref_num = r.read_u32("TCB evaluation data number")?;
+        let root_key_id = r.read_bytes(48, "root key ID")?.to_vec();
+        let ppid = array!(r.read_bytes(16, "PPID")?, 16);
+        let cpu_svn = array!(r.read_bytes(16, "TCB CPUSVN")?, 16);
+        let pce_svn = r.read_u16("TCB PCESVN")?;
+        let pce_id = r.read_u16("PCE ID")?;
+        let tee_type = if v3 { Some(TeeType::from_u32(r.read_u32("TEE type")?)?) } else { None };
+        let sgx_type = match r.read_u8("SGX type")? {
+            0 => SgxType::Standard,
+            1 => SgxType::Scalable,
+            _ => SgxType::ScalableWithIntegrity,
+        };
+        let platform_instance_id = array!(r.read_bytes(16, "platform instance ID")?, 16);
+        r.read_bytes(3, "supplemental data padding")?;
+        let dynamic_platform = read_flag(&mut r, "dynamic platform")?;
+        let cached_keys = read_flag(&mut r, "cached keys")?;
+        let smt_enabled = read_flag(&mut r, "SMT enabled")?;
+        let advisory_ids = if v3 { read_sa_list(&mut r)? } else { vec![] };
+
+        Ok(SupplementalData {
+            version,
+            earliest_issue_date,
+            latest_issue_date,
+            earliest_expiration_date,
+            tcb_level_date_tag,
+            pck_crl_num,
+            root_ca_crl_num,
+            tcb_eval_ref_num,
+            root_key_id,
+            ppid,
+            cpu_svn,
+            pce_svn,
+            pce_id,
+            tee_type,
+            sgx_type,
+            platform_instance_id,
+            dynamic_platform,
+            cached_keys,
+            smt_enabled,
+            advisory_ids,
+        })
+    }
+}
+
+/// `pck_cert_flag_enum_t`, which is undefined for platforms of the Processor CA
+fn read_flag(r: &mut Reader, field: &'static str) -> Result<Option<bool>> {
+    Ok(match r.read_u32(field)? {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    })
+}
+
+/// Reads the NUL-terminated, comma-separated advisory IDs.
+fn read_sa_list(r: &mut Reader) -> Result<Vec<String>> {
+    let list = r.read_bytes(SA_LIST_LEN, "advisory IDs")?;
+    let list = list.split(|&b| b == 0).next().unwrap_or_default();
+    Ok(String::from_utf8_lossy(list)
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// The report data the QvE must report:
+/// `SHA256(nonce || quote || expiration check date || collateral expiration
+/// status || result || supplemental data) || 0*32`
+#[cfg(any(test, feature = "sgx"))]
+fn qve_report_data(quote: &[u8], output: &QveOutput, nonce: &[u8; 16]) -> [u8; 64] {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(nonce);
+    ctx.update(quote);
+    ctx.update(&output.expiration_check_date.to_le_bytes());
+    ctx.update(&output.collateral_expiration_status.to_le_bytes());
+    ctx.update(&output.qv_result.as_u32().to_le_bytes());
+    ctx.update(&output.supplemental_data);
+    let mut report_data = [0u8; 64];
+    report_data[..32].copy_from_slice(ctx.finish().as_ref());
+    report_data
+}
+
+/// Checks a QvE report, whose MAC the caller has verified, and the result it
+/// reports for `quote`.
+#[cfg(any(test, feature = "sgx"))]
+pub(crate) fn check_qve_output(
+    quote: &[u8],
+    output: &QveOutput,
+    nonce: &[u8; 16],
+    min_isv_svn: u16,
+    not_before: Option<u64>,
+) -> Result<VerifiedEnclave> {
+    let qve = EnclaveReportBody::from(&output.qve_report.body);
+    if qve.mr_signer() != INTEL_QVE_MR_SIGNER {
+        return Err(Error::QveIdentityMismatch("MRSIGNER"));
+    }
+    if qve.isv_prod_id() != INTEL_QVE_ISV_PROD_ID {
+        return Err(Error::QveIdentityMismatch("ISVPRODID"));
+    }
+    if qve.isv_svn() < min_isv_svn {
+        return Err(Error::QveIdentityMismatch("ISVSVN"));
+    }
+    if u64::from_le_bytes(array!(&qve.attributes()[..8], 8)) & SGX_FLAGS_DEBUG != 0 {
+        return Err(Error::QveIdentityMismatch("debug attribute"));
+    }
+    if qve.report_data()[..] != qve_report_data(quote, output, nonce)[..] {
+        return Err(Error::QveReportDataMismatch);
+    }
+
+    if let Some(not_before) = not_before {
+        if output.expiration_check_date < 0 || (output.expiration_check_date as u64) < not_before {
+            return Err(Error::StaleQveExpirationCheck(output.expiration_check_date));
+        }
+    }
+    if output.collateral_expiration_status != 0 {
+        return Err(Error::CollateralExpired("QvE collateral"));
+    }
+    let tcb_status = output.qv_result.tcb_status()
+        .ok_or(Error::QuoteVerificationFailed(output.qv_result))?;
+    let advisory_ids = match output.supplemental_data.len() {
+        0 => vec![],
+        _ => SupplementalData::parse(&output.supplemental_data)?.advisory_ids,
+    };
+
+    let quote = Quote::parse(quote)?;
+    let report = quote.enclave_report_body().ok_or(Error::UnsupportedTeeType(TEE_TYPE_TDX))?;
+    Ok(VerifiedEnclave {
+        mr_enclave: report.mr_enclave(),
+        mr_signer: report.mr_signer(),
+        isv_prod_id: report.isv_prod_id(),
+        isv_svn: report.isv_svn(),
+        attributes: report.attributes(),
+        report_data: report.report_data(),
+        tcb_status,
+        advisory_ids,
+    })
+}
+
+#[cfg(feature = "sgx")]
+pub use self::enclave::{QveVerifier, QveEvidence};
+
+#[cfg(feature = "sgx")]
+mod enclave {
+    use alloc::vec::Vec;
+    use attestation::{AttestationVerifier, VerifiedEnclave};
+    use sgx_tse::rsgx_verify_report;
+    use super::*;
+
+    /// A quote and the output of its verification by the QvE on the host
+    #[derive(Clone)]
+    pub struct QveEvidence {
+        pub quote: Vec<u8>,
+        pub output: QveOutput,
+    }
+
+    /// Trusts quote verification by the QvE, inside the enclave at which
+    /// the QvE report is targeted.
+    ///
+    /// The nonce, which the enclave generates and passes to the QvE through
+    /// the host, must be fresh for each verification.
+    #[derive(Debug, Clone)]
+    pub struct QveVerifier {
+        nonce: [u8; 16],
+        min_isv_svn: u16,
+        not_before: Option<u64>,
+    }
+
+    impl QveVerifier {
+        /// `min_isv_svn` is the lowest ISVSVN of the QvE to trust.
+        pub fn new(nonce: [u8; 16], min_isv_svn: u16) -> Self {
+            QveVerifier {
+                nonce,
+                min_isv_svn,
+                not_before: None,
+            }
+        }
+
+        /// Requires the QvE to have checked the collateral expiration at
+        /// `not_before`, in seconds since the Unix epoch, or later, so that
+        /// the host can't pick a date before the collateral expired.
+        pub fn not_before(mut self, not_before: u64) -> Self {
+            self.not_before = Some(not_before);
+            self
+        }
+    }
+
+    impl AttestationVerifier for QveVerifier {
+        type Evidence = QveEvidence;
+        type Error = Error;
+
+        fn verify(&self, evidence: &QveEvidence) -> Result<VerifiedEnclave> {
+            rsgx_verify_report(&evidence.output.qve_report).map_err(|_| Error::InvalidQveReport)?;
+            check_qve_output(&evidence.quote, &evidence.output, &self.nonce, self.min_isv_svn, self.not_before)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use sgx_types::sgx_report_body_t;
+    use crate::sim::SimulatedQe;
+
+    const NONCE: [u8; 16] = [0x4e; 16];
+    const CHECK_DATE: i64 = 1_700_000_000;
+
+    fn sgx_quote() -> Vec<u8> {
+        let mut body = sgx_report_body_t::default();
+        body.mr_enclave.m = [0x11; 32];
+        body.isv_svn = 3;
+        body.report_data.d = [0x33; 64];
+        SimulatedQe::new().quote_report_body(&EnclaveReportBody::from(&body))
+    }
+
+    fn supplemental(major: u32, advisories: &str) -> Vec<u8> {
+        let mut data = vec![0u8; if major == 3 { 492 } else { 168 }];
+        data[0..4].copy_from_slice(&(major | 2 << 16).to_le_bytes());
+        data[8..16].copy_from_slice(&CHECK_DATE.to_le_bytes());
+        data[52..100].copy_from_slice(&[0xaa; 48]);
+        data[132..134].copy_from_slice(&13u16.to_le_bytes());
+        let flags = if major == 3 {
+            data[140] = 1;
+            160
+        } else {
+            data[136] = 1;
+            156
+        };
+        data[flags..flags + 4].copy_from_slice(&1u32.to_le_bytes());
+        data[flags + 4..flags + 8].copy_from_slice(&0u32.to_le_bytes());
+        data[flags + 8..flags + 12].copy_from_slice(&2u32.to_le_bytes());
+        if major == 3 {
+            data[172..172 + advisories.len()].copy_from_slice(advisories.as_bytes());
+        }
+        data
+    }
+
+    fn output(quote: &[u8], qv_result: QvResult, supplemental_data: Vec<u8>) -> QveOutput {
+        let mut output = QveOutput {
+            qve_report: sgx_report_t::default(),
+            expiration_check_date: CHECK_DATE,
+            collateral_expiration_status: 0,
+            qv_result,
+            supplemental_data,
+        };
+        output.qve_report.body.mr_signer.m = INTEL_QVE_MR_SIGNER;
+        output.qve_report.body.isv_prod_id = INTEL_QVE_ISV_PROD_ID;
+        output.qve_report.body.isv_svn = 5;
+        output.qve_report.body.report_data.d = qve_report_data(quote, &output, &NONCE);
+        output
+    }
+
+    #[test]
+    fn test_check_qve_output() {
+        let quote = sgx_quote();
+        let output = output(&quote, QvResult::SwHardeningNeeded, supplemental(3, "INTEL-SA-00615, INTEL-SA-00657"));
+        let enclave = check_qve_output(&quote, &output, &NONCE, 5, Some(CHECK_DATE as u64)).unwrap();
+
+        assert_eq!(enclave.mr_enclave, [0x11; 32]);
+        assert_eq!(enclave.isv_svn, 3);
+        assert_eq!(&enclave.report_data[..], &[0x33; 64][..]);
+        assert_eq!(enclave.tcb_status, TcbStatus::SwHardeningNeeded);
+        assert_eq!(enclave.advisory_ids, vec!["INTEL-SA-00615".to_string(), "INTEL-SA-00657".to_string()]);
+    }
+
+    #[test]
+    fn test_reject_unbound_output() {
+        let quote = sgx_quote();
+        let output = output(&quote, QvResult::Revoked, vec![]);
+        assert_eq!(check_qve_output(&quote, &output, &[0; 16], 0, None), Err(Error::QveReportDataMismatch));
+        assert_eq!(check_qve_output(&sgx_quote(), &output, &NONCE, 0, None), Err(Error::QveReportDataMismatch));
+
+        let mut tampered = output;
+        tampered.qv_result = QvResult::Ok;
+        assert_eq!(check_qve_output(&quote, &tampered, &NONCE, 0, None), Err(Error::QveReportDataMismatch));
+    }
+
+    #[test]
+    fn test_reject_untrusted_qve() {
+        let quote = sgx_quote();
+        let output = output(&quote, QvResult::Ok, vec![]);
+        assert_eq!(check_qve_output(&quote, &output, &NONCE, 6, None), Err(Error::QveIdentityMismatch("ISVSVN")));
+
+        let mut debug = output.clone();
+        debug.qve_report.body.attributes.flags |= SGX_FLAGS_DEBUG;
+        assert_eq!(check_qve_output(&quote, &debug, &NONCE, 0, None), Err(Error::QveIdentityMismatch("debug attribute")));
+
+        let mut other = output;
+        other.qve_report.body.mr_signer.m = [0x8c; 32];
+        assert_eq!(check_qve_output(&quote, &other, &NONCE, 0, None), Err(Error::QveIdentityMismatch("MRSIGNER")));
+    }
+
+    #[test]
+    fn test_reject_failed_verification() {
+        let quote = sgx_quote();
+        let failed = output(&quote, QvResult::InvalidSignature, vec![]);
+        assert_eq!(
+            check_qve_output(&quote, &failed, &NONCE, 0, None),
+            Err(Error::QuoteVerificationFailed(QvResult::InvalidSignature)),
+        );
+
+        let mut expired = output(&quote, QvResult::Ok, vec![]);
+        expired.collateral_expiration_status = 1;
+        expired.qve_report.body.report_data.d = qve_report_data(&quote, &expired, &NONCE);
+        assert_eq!(check_qve_output(&quote, &expired, &NONCE, 0, None), Err(Error::CollateralExpired("QvE collateral")));
+
+        let stale = output(&quote, QvResult::Ok, vec![]);
+        assert_eq!(
+            check_qve_output(&quote, &stale, &NONCE, 0, Some(CHECK_DATE as u64 + 1)),
+            Err(Error::StaleQveExpirationCheck(CHECK_DATE)),
+        );
+    }
+
+    #[test]
+    fn test_parse_supplemental_data() {
+        let v2 = SupplementalData::parse(&supplemental(2, "")).unwrap();
+        assert_eq!(v2.version & 0xffff, 2);
+        assert_eq!(v2.earliest_issue_date, CHECK_DATE);
+        assert_eq!(v2.root_key_id, vec![0xaa; 48]);
+        assert_eq!(v2.pce_svn, 13);
+        assert_eq!(v2.tee_type, None);
+        assert_eq!(v2.sgx_type, SgxType::Scalable);
+        assert_eq!((v2.dynamic_platform, v2.cached_keys, v2.smt_enabled), (Some(true), Some(false), None));
+        assert!(v2.advisory_ids.is_empty());
+
+        let v3 = SupplementalData::parse(&supplemental(3, "INTEL-SA-00334")).unwrap();
+        assert_eq!(v3.tee_type, Some(TeeType::Sgx));
+        assert_eq!(v3.sgx_type, SgxType::Scalable);
+        assert_eq!(v3.dynamic_platform, Some(true));
+        assert_eq!(v3.advisory_ids, vec!["INTEL-SA-00334".to_string()]);
+
+        let mut v4 = supplemental(3, "");
+        v4[0] = 4;
+        assert_eq!(SupplementalData::parse(&v4), Err(Error::UnsupportedSupplementalDataVersion(4 | 2 << 16)));
+    }
+}
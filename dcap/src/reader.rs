@@ -0,0 +1,63 @@
+use crate::error::{Error, Result};
+
+/// Copies a slice of known length into an array.
+macro_rules! array {
+    ($slice:expr, $len:expr) => {{
+        let mut a = [0u8; $len];
+        a.copy_from_slice($slice);
+        a
+    }};
+}
+
+/// A bounds-checked cursor over little-endian encoded data
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Reader { buf, offset: 0 }
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.buf.len() - self.offset
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize, field: &'static str) -> Result<&'a [u8]> {
+        if self.remaining() < len {
+            return Err(Error::UnexpectedEof { field, offset: self.offset });
+        }
+        let bytes = &self.buf[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u8(&mut self, field: &'static str) -> Result<u8> {
+        Ok(self.read_bytes(1, field)?[0])
+    }
+
+    pub(crate) fn read_u16(&mut self, field: &'static str) -> Result<u16> {
+        Ok(u16::from_le_bytes(array!(self.read_bytes(2, field)?, 2)))
+    }
+
+    pub(crate) fn read_u32(&mut self, field: &'static str) -> Result<u32> {
+        Ok(u32::from_le_bytes(array!(self.read_bytes(4, field)?, 4)))
+    }
+
+    pub(crate) fn read_i64(&mut self, field: &'static str) -> Result<i64> {
+        Ok(i64::from_le_bytes(array!(self.read_bytes(8, field)?, 8)))
+    }
+
+    /// Fails if any bytes are left unread.
+    pub(crate) fn finish(self, field: &'static str) -> Result<()> {
+        match self.remaining() {
+            0 => Ok(()),
+            len => Err(Error::TrailingBytes { field, len }),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use alloc::vec::Vec;
+use core::fmt;
+use sgx_types::sgx_report_body_t;
+use crate::error::Result;
+use crate::reader::Reader;
+
+/// Length of `sgx_report_body_t`
+pub const ENCLAVE_REPORT_BODY_LEN: usize = 384;
+
+/// An SGX enclave report body (`sgx_report_body_t`).
+///
+/// The raw bytes are kept as is, since the report body is covered by
+/// signatures, and the fields are decoded by the accessors.
+#[derive(Clone, Copy)]
+pub struct EnclaveReportBody {
+    raw: [u8; ENCLAVE_REPORT_BODY_LEN],
+}
+
+impl EnclaveReportBody {
+    pub(crate) fn read(reader: &mut Reader) -> Result<Self> {
+        let bytes = reader.read_bytes(ENCLAVE_REPORT_BODY_LEN, "enclave report body")?;
+        Ok(EnclaveReportBody {
+            raw: array!(bytes, ENCLAVE_REPORT_BODY_LEN),
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw[..]
+    }
+
+    pub fn cpu_svn(&self) -> [u8; 16] {
+        array!(&self.raw[0..16], 16)
+    }
+
+    pub fn misc_select(&self) -> u32 {
+        u32::from_le_bytes(array!(&self.raw[16..20], 4))
+    }
+
+    pub fn isv_ext_prod_id(&self) -> [u8; 16] {
+        array!(&self.raw[32..48], 16)
+    }
+
+    pub fn attributes(&self) -> [u8; 16] {
+        array!(&self.raw[48..64], 16)
+    }
+
+    pub fn mr_enclave(&self) -> [u8; 32] {
+        array!(&self.raw[64..96], 32)
+    }
+
+    pub fn mr_signer(&self) -> [u8; 32] {
+        array!(&self.raw[128..160], 32)
+    }
+
+    pub fn config_id(&self) -> [u8; 64] {
+        array!(&self.raw[192..256], 64)
+    }
+
+    pub fn isv_prod_id(&self) -> u16 {
+        u16::from_le_bytes(array!(&self.raw[256..258], 2))
+    }
+
+    pub fn isv_svn(&self) -> u16 {
+        u16::from_le_bytes(array!(&self.raw[258..260], 2))
+    }
+
+    pub fn config_svn(&self) -> u16 {
+        u16::from_le_bytes(array!(&self.raw[260..262], 2))
+    }
+
+    pub fn isv_family_id(&self) -> [u8; 16] {
+        array!(&self.raw[304..320], 16)
+    }
+
+    pub fn report_data(&self) -> [u8; 64] {
+        array!(&self.raw[320..384], 64)
+    }
+}
+
+impl fmt::Debug for EnclaveReportBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EnclaveReportBody")
+            .field("cpu_svn", &self.cpu_svn())
+            .field("misc_select", &self.misc_select())
+            .field("attributes", &self.attributes())
+            .field("mr_enclave", &self.mr_enclave())
+            .field("mr_signer", &self.mr_signer())
+            .field("isv_prod_id", &self.isv_prod_id())
+            .field("isv_svn", &self.isv_svn())
+            .field("report_data", &&self.raw[320..384])
+            .finish()
+    }
+}
+
+impl PartialEq for EnclaveReportBody {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw[..] == other.raw[..]
+    }
+}
+
+impl Eq for EnclaveReportBody {}
+
+impl From<&sgx_report_body_t> for EnclaveReportBody {
+    fn from(body: &sgx_report_body_t) -> Self {
+        let mut raw = [0u8; ENCLAVE_REPORT_BODY_LEN];
+        raw[0..16].copy_from_slice(&body.cpu_svn.svn);
+        raw[16..20].copy_from_slice(&body.misc_select.to_le_bytes());
+        raw[32..48].copy_from_slice(&body.isv_ext_prod_id);
+        raw[48..56].copy_from_slice(&body.attributes.flags.to_le_bytes());
+        raw[56..64].copy_from_slice(&body.attributes.xfrm.to_le_bytes());
+        raw[64..96].copy_from_slice(&body.mr_enclave.m);
+        raw[128..160].copy_from_slice(&body.mr_signer.m);
+        raw[192..256].copy_from_slice(&body.config_id);
+        raw[256..258].copy_from_slice(&body.isv_prod_id.to_le_bytes());
+        raw[258..260].copy_from_slice(&body.isv_svn.to_le_bytes());
+        raw[260..262].copy_from_slice(&body.config_svn.to_le_bytes());
+        raw[304..320].copy_from_slice(&body.isv_family_id);
+        raw[320..384].copy_from_slice(&body.report_data.d);
+        EnclaveReportBody { raw }
+    }
+}
+
+/// Length of the TDX 1.0 TD report body (`TD_REPORT10`)
+pub const TD_REPORT10_BODY_LEN: usize = 584;
+/// Length of the TDX 1.5 TD report body (`TD_REPORT15`)
+pub const TD_REPORT15_BODY_LEN: usize = 648;
+
+/// A TD report body of a TDX quote, either TDX 1.0 or 1.5.
+///
+/// Measurements are SHA-384 digests.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TdReportBody {
+    raw: Vec<u8>,
+}
+
+impl TdReportBody {
+    pub(crate) fn read(reader: &mut Reader, len: usize) -> Result<Self> {
+        debug_assert!(len == TD_REPORT10_BODY_LEN || len == TD_REPORT15_BODY_LEN);
+        let bytes = reader.read_bytes(len, "TD report body")?;
+        Ok(TdReportBody {
+            raw: bytes.to_vec(),
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw[..]
+    }
+
+    /// Whether this is a TDX 1.5 report body, which has the fields for service TDs
+    pub fn is_tdx15(&self) -> bool {
+        self.raw.len() == TD_REPORT15_BODY_LEN
+    }
+
+    pub fn tee_tcb_svn(&self) -> [u8; 16] {
+        array!(&self.raw[0..16], 16)
+    }
+
+    pub fn mr_seam(&self) -> [u8; 48] {
+        array!(&self.raw[16..64], 48)
+    }
+
+    pub fn mr_signer_seam(&self) -> [u8; 48] {
+        array!(&self.raw[64..112], 48)
+    }
+
+    pub fn seam_attributes(&self) -> [u8; 8] {
+        array!(&self.raw[112..120], 8)
+    }
+
+    pub fn td_attributes(&self) -> [u8; 8] {
+        array!(&self.raw[120..128], 8)
+    }
+
+    pub fn xfam(&self) -> [u8; 8] {
+        array!(&self.raw[128..136], 8)
+    }
+
+    pub fn mr_td(&self) -> [u8; 48] {
+        array!(&self.raw[136..184], 48)
+    }
+
+    pub fn mr_config_id(&self) -> [u8; 48] {
+        array!(&self.raw[184..232], 48)
+    }
+
+    pub fn mr_owner(&self) -> [u8; 48] {
+        array!(&self.raw[232..280], 48)
+    }
+
+    pub fn mr_owner_config(&self) -> [u8; 48] {
+        array!(&self.raw[280..328], 48)
+    }
+
+    /// Runtime measurement register `index`, which must be in `0..4`.
+    pub fn rtmr(&self, index: usize) -> [u8; 48] {
+        assert!(index < 4, "RTMR index out of range: {}", index);
+        let start = 328 + index * 48;
+        array!(&self.raw[start..start + 48], 48)
+    }
+
+    pub fn report_data(&self) -> [u8; 64] {
+        array!(&self.raw[520..584], 64)
+    }
+
+    /// `TEE_TCB_SVN_2`, only in TDX 1.5
+    pub fn tee_tcb_svn2(&self) -> Option<[u8; 16]> {
+        if self.is_tdx15() {
+            Some(array!(&self.raw[584..600], 16))
+        } else {
+            None
+        }
+    }
+
+    /// `MRSERVICETD`, only in TDX 1.5
+    pub fn mr_servicetd(&self) -> Option<[u8; 48]> {
+        if self.is_tdx15() {
+            Some(array!(&self.raw[600..648], 48))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for TdReportBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TdReportBody")
+            .field("tee_tcb_svn", &self.tee_tcb_svn())
+            .field("mr_seam", &&self.raw[16..64])
+            .field("td_attributes", &self.td_attributes())
+            .field("mr_td", &&self.raw[136..184])
+            .field("rtmr0", &&self.raw[328..376])
+            .field("rtmr1", &&self.raw[376..424])
+            .field("rtmr2", &&self.raw[424..472])
+            .field("rtmr3", &&self.raw[472..520])
+            .field("report_data", &&self.raw[520..584])
+            .finish()
+    }
+}
@@ -0,0 +1,343 @@
+//! A simulated SGX platform, so that DCAP attestation runs on machines without SGX
+use alloc::{string::{String, ToString}, vec::Vec};
+use ring::{digest, rand::{SecureRandom, SystemRandom}};
+use sgx_types::{sgx_report_t, sgx_target_info_t};
+use crate::collateral::{TCB_INFO, QE_IDENTITY};
+use crate::error::{Error, Result};
+use crate::pck::{PckCa, SgxExtensions};
+use crate::provider::{CollateralProvider, SignedCollateral, hex};
+use crate::quote::{TeeType, INTEL_QE_VENDOR_ID, QUOTE_HEADER_LEN};
+use crate::quoting::QuotingEnclave;
+use crate::report::{EnclaveReportBody, ENCLAVE_REPORT_BODY_LEN};
+use crate::tcb::TcbStatus;
+use crate::test_certs::{TestKey, TestPki, sample_sgx_extensions, date_time};
+
+/// MRENCLAVE of the simulated QE
+pub const SIM_QE_MR_ENCLAVE: [u8; 32] = [0x8e; 32];
+/// MRSIGNER of the simulated QE
+pub const SIM_QE_MR_SIGNER: [u8; 32] = [0x8c; 32];
+const QE_ATTRIBUTES: u64 = 0x11;
+const QE_ISV_PROD_ID: u16 = 1;
+const QE_ISV_SVN: u16 = 8;
+pub(crate) const QE_AUTH_DATA: &[u8] = &[0x5a; 32];
+/// How long simulated collateral stays valid
+const COLLATERAL_VALIDITY: u64 = 30 * 86400;
+
+/// A simulated quoting enclave and the platform it runs on.
+///
+/// The QE signs quotes with a locally generated attestation key, which its
+/// QE report binds to a PCK certificate under a generated root CA, and the
+/// TCB info and QE identity of the platform are signed under the same root
+/// CA. Quotes and collateral verify against `root_ca`, but the QE takes
+/// reports as they are, so quotes prove nothing without real SGX.
+pub struct SimulatedQe {
+    pki: TestPki,
+    pck_cert_chain: Vec<u8>,
+    extensions: SgxExtensions,
+    attestation_key: TestKey,
+    qe_id: [u8; 16],
+    tcb_status: TcbStatus,
+    advisory_ids: Vec<String>,
+}
+
+impl SimulatedQe {
+    /// Generates a platform whose TCB is up to date.
+    pub fn new() -> Self {
+        let pki = TestPki::new();
+        let mut qe_id = [0u8; 16];
+        SystemRandom::new().fill(&mut qe_id).expect("Failed to generate QE ID");
+        SimulatedQe {
+            pck_cert_chain: pki.pck_cert_chain_pem(),
+            pki,
+            extensions: sample_sgx_extensions(),
+            attestation_key: TestKey::generate(),
+            qe_id,
+            tcb_status: TcbStatus::UpToDate,
+            advisory_ids: vec![],
+        }
+    }
+
+    /// Sets the TCB status and advisories of the platform in the TCB info.
+    pub fn with_tcb_status(mut self, status: TcbStatus, advisory_ids: &[&str]) -> Self {
+        self.tcb_status = status;
+        self.advisory_ids = advisory_ids.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// The DER-encoded root CA, which stands in for the Intel SGX Root CA
+    pub fn root_ca(&self) -> &[u8] {
+        &self.pki.root_ca
+    }
+
+    /// The SGX extensions of the PCK certificate
+    pub fn sgx_extensions(&self) -> &SgxExtensions {
+        &self.extensions
+    }
+
+    /// Generates a v3 quote of the report body.
+    pub fn quote_report_body(&self, body: &EnclaveReportBody) -> Vec<u8> {
+        let mut header = Vec::with_capacity(QUOTE_HEADER_LEN);
+        header.extend_from_slice(&3u16.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&QE_ISV_SVN.to_le_bytes());
+        header.extend_from_slice(&self.extensions.tcb.pce_svn.to_le_bytes());
+        header.extend_from_slice(&INTEL_QE_VENDOR_ID);
+        header.extend_from_slice(&self.qe_id);
+        header.extend_from_slice(&[0; 4]);
+
+        encode_quote(&self.pki, &self.attestation_key, &header, body.as_bytes())
+    }
+
+    /// TCB info, QE identity and CRLs of the platform, issued at `now` in
+    /// seconds since the Unix epoch. The CRLs revoke nothing.
+    pub fn collateral(&self, now: u64) -> SimulatedCollateral {
+        let issuer_chain = self.pki.tcb_signing_chain_pem();
+        let key = &self.pki.tcb_signing_key;
+        SimulatedCollateral {
+            fmspc: self.extensions.fmspc,
+            tcb_info: SignedCollateral {
+                data: signed("tcbInfo", &self.tcb_info_body(now), key),
+                issuer_chain: issuer_chain.clone(),
+            },
+            qe_identity: SignedCollateral {
+                data: signed("enclaveIdentity", &qe_identity_body(now), key),
+                issuer_chain,
+            },
+            pck_crl: SignedCollateral {
+                data: self.pki.pck_crl(now, now + COLLATERAL_VALIDITY, &[]),
+                issuer_chain: self.pki.pck_crl_chain_pem(),
+            },
+            root_ca_crl: self.pki.root_ca_crl(now, now + COLLATERAL_VALIDITY, &[]),
+        }
+    }
+
+    fn tcb_info_body(&self, now: u64) -> String {
+        let tcb = &self.extensions.tcb;
+        let components: Vec<String> = tcb.sgx_tcb_comp_svn.iter().map(|svn| format!("{{\"svn\":{}}}", svn)).collect();
+        format!(
+            "{{\"id\":\"SGX\",\"version\":3,\"issueDate\":\"{}\",\"nextUpdate\":\"{}\",\"fmspc\":\"{}\",\
+             \"pceId\":\"{}\",\"tcbType\":0,\"tcbEvaluationDataNumber\":1,\"tcbLevels\":[{{\"tcb\":\
+             {{\"sgxtcbcomponents\":[{}],\"pcesvn\":{}}},\"tcbDate\":\"{}\",\"tcbStatus\":\"{}\",\"advisoryIDs\":[{}]}}]}}",
+            rfc3339(now), rfc3339(now + COLLATERAL_VALIDITY),
+            hex(&self.extensions.fmspc).to_uppercase(), hex(&self.extensions.pce_id).to_uppercase(),
+            components.join(","), tcb.pce_svn, rfc3339(now), self.tcb_status, quoted(&self.advisory_ids),
+        )
+    }
+}
+
+impl Default for SimulatedQe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuotingEnclave for SimulatedQe {
+    fn target_info(&self) -> Result<sgx_target_info_t> {
+        let mut target_info = sgx_target_info_t::default();
+        target_info.mr_enclave.m = SIM_QE_MR_ENCLAVE;
+        target_info.attributes.flags = QE_ATTRIBUTES;
+        Ok(target_info)
+    }
+
+    fn quote_size(&self) -> Result<u32> {
+        let sig_data_len = 128 + ENCLAVE_REPORT_BODY_LEN + 64 + 2 + QE_AUTH_DATA.len() + 6 + self.pck_cert_chain.len();
+        Ok((QUOTE_HEADER_LEN + ENCLAVE_REPORT_BODY_LEN + 4 + sig_data_len) as u32)
+    }
+
+    /// Generates a quote of the report, without checking its MAC.
+    fn quote(&self, report: &sgx_report_t) -> Result<Vec<u8>> {
+        Ok(self.quote_report_body(&EnclaveReportBody::from(&report.body)))
+    }
+}
+
+/// Collateral of a simulated platform
+#[derive(Debug, Clone)]
+pub struct SimulatedCollateral {
+    fmspc: [u8; 6],
+    tcb_info: SignedCollateral,
+    qe_identity: SignedCollateral,
+    pck_crl: SignedCollateral,
+    root_ca_crl: Vec<u8>,
+}
+
+impl CollateralProvider for SimulatedCollateral {
+    fn tcb_info(&self, tee_type: TeeType, fmspc: &[u8; 6]) -> Result<SignedCollateral> {
+        if tee_type != TeeType::Sgx || *fmspc != self.fmspc {
+            return Err(not_simulated(TCB_INFO));
+        }
+        Ok(self.tcb_info.clone())
+    }
+
+    fn qe_identity(&self, tee_type: TeeType) -> Result<SignedCollateral> {
+        if tee_type != TeeType::Sgx {
+            return Err(not_simulated(QE_IDENTITY));
+        }
+        Ok(self.qe_identity.clone())
+    }
+
+    fn pck_crl(&self, _ca: PckCa) -> Result<SignedCollateral> {
+        Ok(self.pck_crl.clone())
+    }
+
+    fn root_ca_crl(&self) -> Result<Vec<u8>> {
+        Ok(self.root_ca_crl.clone())
+    }
+}
+
+fn not_simulated(collateral: &'static str) -> Error {
+    Error::CollateralUnavailable {
+        collateral,
+        reason: "not simulated".to_string(),
+    }
+}
+
+/// The QE report, whose report data binds `attestation_key`
+fn qe_report(attestation_key: &TestKey) -> Vec<u8> {
+    let mut qe_report = vec![0u8; ENCLAVE_REPORT_BODY_LEN];
+    qe_report[48..56].copy_from_slice(&QE_ATTRIBUTES.to_le_bytes());
+    qe_report[64..96].copy_from_slice(&SIM_QE_MR_ENCLAVE);
+    qe_report[128..160].copy_from_slice(&SIM_QE_MR_SIGNER);
+    qe_report[256..258].copy_from_slice(&QE_ISV_PROD_ID.to_le_bytes());
+    qe_report[258..260].copy_from_slice(&QE_ISV_SVN.to_le_bytes());
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(&attestation_key.public_key());
+    ctx.update(QE_AUTH_DATA);
+    qe_report[320..352].copy_from_slice(ctx.finish().as_ref());
+    qe_report
+}
+
+/// Encodes a quote of `body`, signed by `attestation_key`, which is certified
+/// by the PCK key of `pki`. Version 3 quotes carry the QE report directly,
+/// and later versions nest it.
+pub(crate) fn encode_quote(pki: &TestPki, attestation_key: &TestKey, header: &[u8], body: &[u8]) -> Vec<u8> {
+    let qe_report = qe_report(attestation_key);
+    let chain = pki.pck_cert_chain_pem();
+    let mut qe_cert_data = vec![];
+    qe_cert_data.extend_from_slice(&qe_report);
+    qe_cert_data.extend_from_slice(&pki.pck_key.sign_fixed(&qe_report));
+    qe_cert_data.extend_from_slice(&(QE_AUTH_DATA.len() as u16).to_le_bytes());
+    qe_cert_data.extend_from_slice(QE_AUTH_DATA);
+    qe_cert_data.extend_from_slice(&5u16.to_le_bytes());
+    qe_cert_data.extend_from_slice(&(chain.len() as u32).to_le_bytes());
+    qe_cert_data.extend_from_slice(&chain);
+
+    let mut quote = header.to_vec();
+    quote.extend_from_slice(body);
+    let mut sig_data = vec![];
+    sig_data.extend_from_slice(&attestation_key.sign_fixed(&quote));
+    sig_data.extend_from_slice(&attestation_key.public_key());
+    if header[0..2] == 3u16.to_le_bytes() {
+        sig_data.extend_from_slice(&qe_cert_data);
+    } else {
+        sig_data.extend_from_slice(&6u16.to_le_bytes());
+        sig_data.extend_from_slice(&(qe_cert_data.len() as u32).to_le_bytes());
+        sig_data.extend_from_slice(&qe_cert_data);
+    }
+    quote.extend_from_slice(&(sig_data.len() as u32).to_le_bytes());
+    quote.extend_from_slice(&sig_data);
+    quote
+}
+
+fn qe_identity_body(now: u64) -> String {
+    format!(
+        "{{\"id\":\"QE\",\"version\":2,\"issueDate\":\"{}\",\"nextUpdate\":\"{}\",\"tcbEvaluationDataNumber\":1,\
+         \"miscselect\":\"00000000\",\"miscselectMask\":\"FFFFFFFF\",\"attributes\":\"{}\",\
+         \"attributesMask\":\"FBFFFFFFFFFFFFFF0000000000000000\",\"mrsigner\":\"{}\",\"isvprodid\":{},\
+         \"tcbLevels\":[{{\"tcb\":{{\"isvsvn\":{}}},\"tcbDate\":\"{}\",\"tcbStatus\":\"UpToDate\",\"advisoryIDs\":[]}}]}}",
+        rfc3339(now), rfc3339(now + COLLATERAL_VALIDITY),
+        hex(&(QE_ATTRIBUTES as u128).to_le_bytes()).to_uppercase(), hex(&SIM_QE_MR_SIGNER).to_uppercase(),
+        QE_ISV_PROD_ID, QE_ISV_SVN, rfc3339(now),
+    )
+}
+
+/// Wraps a body as PCS does, e.g. `{"tcbInfo":{..},"signature":".."}`.
+pub(crate) fn signed(name: &str, body: &str, key: &TestKey) -> Vec<u8> {
+    let signature = hex(&key.sign_fixed(body.as_bytes()));
+    format!("{{\"{}\":{},\"signature\":\"{}\"}}", name, body, signature).into_bytes()
+}
+
+fn quoted(ids: &[String]) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id)).collect();
+    ids.join(",")
+}
+
+fn rfc3339(secs: u64) -> String {
+    let (year, month, day, hour, minute, second) = date_time(secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sgx_types::sgx_report_body_t;
+    use attestation::AttestationVerifier;
+    use crate::quote::Quote;
+    use crate::test_certs::NOW;
+    use crate::tcb::TcbPolicy;
+    use crate::verifier::DcapVerifier;
+
+    fn report() -> sgx_report_t {
+        let mut body = sgx_report_body_t::default();
+        body.mr_enclave.m = [0x11; 32];
+        body.mr_signer.m = [0x22; 32];
+        body.isv_prod_id = 5;
+        body.isv_svn = 9;
+        body.attributes.flags = 0x07;
+        body.report_data.d = [0x33; 64];
+        sgx_report_t {
+            body,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_quote_report() {
+        let qe = SimulatedQe::new();
+        let quote = qe.quote(&report()).unwrap();
+        assert_eq!(quote.len(), qe.quote_size().unwrap() as usize);
+
+        let quote = Quote::parse(&quote).unwrap();
+        let body = quote.enclave_report_body().unwrap();
+        assert_eq!(body.mr_enclave(), [0x11; 32]);
+        assert_eq!(body.attributes()[0], 0x07);
+        assert_eq!(&body.report_data()[..], &[0x33; 64][..]);
+        assert_eq!(quote.signature.qe_report.mr_enclave(), qe.target_info().unwrap().mr_enclave.m);
+        assert_eq!(quote.header.pce_svn, qe.sgx_extensions().tcb.pce_svn);
+    }
+
+    #[test]
+    fn test_verify_simulated_quote() {
+        let qe = SimulatedQe::new();
+        let verifier = DcapVerifier::new(qe.root_ca().to_vec(), qe.collateral(NOW)).at(NOW + 60);
+        let enclave = verifier.verify(&qe.quote(&report()).unwrap()).unwrap();
+
+        assert_eq!(enclave.mr_enclave, [0x11; 32]);
+        assert_eq!(enclave.mr_signer, [0x22; 32]);
+        assert_eq!(enclave.isv_prod_id, 5);
+        assert_eq!(enclave.isv_svn, 9);
+        assert_eq!(enclave.tcb_status, TcbStatus::UpToDate);
+        assert!(enclave.advisory_ids.is_empty());
+    }
+
+    #[test]
+    fn test_simulated_tcb_status() {
+        let qe = SimulatedQe::new().with_tcb_status(TcbStatus::OutOfDate, &["INTEL-SA-00615"]);
+        let verifier = DcapVerifier::new(qe.root_ca().to_vec(), qe.collateral(NOW)).at(NOW);
+        let enclave = verifier.verify(&qe.quote(&report()).unwrap()).unwrap();
+
+        assert_eq!(enclave.tcb_status, TcbStatus::OutOfDate);
+        assert_eq!(enclave.advisory_ids, vec!["INTEL-SA-00615".to_string()]);
+
+        let verifier = DcapVerifier::new(qe.root_ca().to_vec(), qe.collateral(NOW)).at(NOW + COLLATERAL_VALIDITY);
+        assert_eq!(verifier.verify(&qe.quote(&report()).unwrap()), Err(Error::CollateralExpired(TCB_INFO)));
+        let qe = SimulatedQe::new().with_tcb_status(TcbStatus::Revoked, &[]);
+        let verifier = DcapVerifier::new(qe.root_ca().to_vec(), qe.collateral(NOW))
+            .with_policy(TcbPolicy::default().tolerate(TcbStatus::Revoked))
+            .at(NOW);
+        assert_eq!(
+            verifier.verify(&qe.quote(&report()).unwrap()),
+            Err(Error::TcbStatusNotTolerated(TcbStatus::Revoked)),
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use alloc::{string::String, vec::Vec};
+pub use attestation::TcbStatus;
+use crate::collateral::{TcbInfo, TcbLevel, QeIdentity, QeTcbLevel};
+use crate::error::{Error, Result};
+use crate::quote::TeeType;
+use crate::verify::VerifiedQuote;
+
+/// The TCB status of an attested platform and its Quoting Enclave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcbEvaluation {
+    /// The platform status converged with the QE status
+    pub status: TcbStatus,
+    pub platform_status: TcbStatus,
+    pub qe_status: TcbStatus,
+    /// Advisories of the platform and the QE TCB levels
+    pub advisory_ids: Vec<String>,
+    pub tcb_date: String,
+}
+
+/// A policy which decides which TCB statuses other than `UpToDate` are accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcbPolicy {
+    tolerated_statuses: Vec<TcbStatus>,
+}
+
+impl Default for TcbPolicy {
+    /// Accepts `UpToDate`, and tolerates `OutOfDate` as the IAS verification
+    /// policy tolerates `GROUP_OUT_OF_DATE`.
+    fn default() -> Self {
+        TcbPolicy {
+            tolerated_statuses: vec![TcbStatus::OutOfDate],
+        }
+    }
+}
+
+impl TcbPolicy {
+    /// A policy which accepts only `UpToDate`.
+    pub fn strict() -> Self {
+        TcbPolicy {
+            tolerated_statuses: vec![],
+        }
+    }
+
+    /// Tolerates the TCB status other than `UpToDate`. `Revoked` is never
+    /// tolerated.
+    pub fn tolerate(mut self, status: TcbStatus) -> Self {
+        if status != TcbStatus::Revoked && !self.is_tolerated(&status) {
+            self.tolerated_statuses.push(status);
+        }
+        self
+    }
+
+    pub fn is_tolerated(&self, status: &TcbStatus) -> bool {
+        self.tolerated_statuses.contains(status)
+    }
+
+    pub fn tolerated_statuses(&self) -> &[TcbStatus] {
+        &self.tolerated_statuses[..]
+    }
+
+    /// Accepts `UpToDate` and the tolerated statuses.
+    pub fn check(&self, status: &TcbStatus) -> Result<()> {
+        if *status == TcbStatus::UpToDate || self.is_tolerated(status) {
+            Ok(())
+        } else {
+            Err(Error::TcbStatusNotTolerated(status.clone()))
+        }
+    }
+}
+
+/// Evaluates the TCB status of a verified quote with verified collateral.
+///
+/// The platform TCB level is the highest one the PCK certificate's TCB (and
+/// the TD report's `TEE_TCB_SVN` for TDX) reaches, and the QE TCB level the
+/// highest one the QE report's ISVSVN reaches. TDX module identities are not
+/// evaluated.
+pub fn evaluate_tcb(quote: &VerifiedQuote, tcb_info: &TcbInfo, qe_identity: &QeIdentity) -> Result<TcbEvaluation> {
+    let pck = quote.pck_cert().sgx_extensions();
+    if tcb_info.fmspc != pck.fmspc {
+        return Err(Error::CollateralMismatch("FMSPC"));
+    }
+    if tcb_info.pce_id != pck.pce_id {
+        return Err(Error::CollateralMismatch("PCE-ID"));
+    }
+    if tcb_info.tee_type != quote.header().tee_type {
+        return Err(Error::CollateralMismatch("TEE type"));
+    }
+
+    let qe_level = qe_tcb_level(quote, qe_identity)?;
+    let tee_tcb_svn = quote.quote().td_report_body().map(|body| body.tee_tcb_svn());
+    let platform_level = tcb_info.tcb_levels.iter()
+        .find(|level| {
+            let tcb = &level.tcb;
+            let tdx_ok = match (tcb.tdx_tcb_comp_svn, tee_tcb_svn) {
+                (Some(ref required), Some(ref svn)) => reaches(svn, required),
+                (None, None) => true,
+                _ => false,
+            };
+            reaches(&pck.tcb.sgx_tcb_comp_svn, &tcb.sgx_tcb_comp_svn) && pck.tcb.pce_svn >= tcb.pce_svn && tdx_ok
+        })
+        .ok_or(Error::TcbLevelNotFound("platform"))?;
+
+    Ok(converge(platform_level, qe_level))
+}
+
+fn reaches(svn: &[u8; 16], required: &[u8; 16]) -> bool {
+    svn.iter().zip(required.iter()).all(|(svn, required)| svn >= required)
+}
+
+fn qe_tcb_level<'a>(quote: &VerifiedQuote, qe_identity: &'a QeIdentity) -> Result<&'a QeTcbLevel> {
+    let expected_id = match quote.header().tee_type {
+        TeeType::Sgx => "QE",
+        TeeType::Tdx => "TD_QE",
+    };
+    if qe_identity.id != expected_id {
+        return Err(Error::CollateralMismatch("QE identity ID"));
+    }
+
+    let qe_report = quote.qe_report();
+    if qe_report.mr_signer() != qe_identity.mrsigner {
+        return Err(Error::QeIdentityMismatch("MRSIGNER"));
+    }
+    if qe_report.isv_prod_id() != qe_identity.isv_prod_id {
+        return Err(Error::QeIdentityMismatch("ISVPRODID"));
+    }
+    if qe_report.misc_select() & qe_identity.miscselect_mask != qe_identity.miscselect {
+        return Err(Error::QeIdentityMismatch("MISCSELECT"));
+    }
+    let attributes = qe_report.attributes();
+    let masked = attributes.iter().zip(qe_identity.attributes_mask.iter()).map(|(a, m)| a & m);
+    if !masked.eq(qe_identity.attributes.iter().cloned()) {
+        return Err(Error::QeIdentityMismatch("ATTRIBUTES"));
+    }
+
+    qe_identity.tcb_levels.iter()
+        .find(|level| qe_report.isv_svn() >= level.isv_svn)
+        .ok_or(Error::TcbLevelNotFound("QE"))
+}
+
+/// Converges the platform status with the QE status, as the Intel quote
+/// verification library does.
+fn converge(platform: &TcbLevel, qe: &QeTcbLevel) -> TcbEvaluation {
+    let status = match (&qe.tcb_status, &platform.tcb_status) {
+        (TcbStatus::Revoked, _) => TcbStatus::Revoked,
+        (TcbStatus::OutOfDate, TcbStatus::UpToDate)
+        | (TcbStatus::OutOfDate, TcbStatus::SwHardeningNeeded) => TcbStatus::OutOfDate,
+        (TcbStatus::OutOfDate, TcbStatus::ConfigurationNeeded)
+        | (TcbStatus::OutOfDate, TcbStatus::ConfigurationAndSwHardeningNeeded) =>
+            TcbStatus::OutOfDateConfigurationNeeded,
+        (_, status) => status.clone(),
+    };
+    let mut advisory_ids = platform.advisory_ids.clone();
+    for id in &qe.advisory_ids {
+        if !advisory_ids.contains(id) {
+            advisory_ids.push(id.clone());
+        }
+    }
+
+    TcbEvaluation {
+        status,
+        platform_status: platform.tcb_status.clone(),
+        qe_status: qe.tcb_status.clone(),
+        advisory_ids,
+        tcb_date: platform.tcb_date.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use crate::collateral::tests::{tcb_info_body, qe_identity_body, Level, LEVELS, QE_LEVELS};
+    use crate::sim::signed;
+    use crate::test_certs::{TestPki, NOW};
+    use crate::verify::{verify_quote, tests::sgx_quote};
+
+    fn evaluate(levels: &[Level], qe_levels: &[(u16, &str, &[&str])]) -> Result<TcbEvaluation> {
+        let pki = TestPki::new();
+        let chain = pki.tcb_signing_chain_pem();
+        let quote = verify_quote(&sgx_quote(&pki), &pki.root_ca, NOW).unwrap();
+        let tcb_info = signed("tcbInfo", &tcb_info_body(3, levels), &pki.tcb_signing_key);
+        let tcb_info = TcbInfo::verify(&tcb_info, &chain, &pki.root_ca, NOW).unwrap();
+        let qe_identity = signed("enclaveIdentity", &qe_identity_body(qe_levels), &pki.tcb_signing_key);
+        let qe_identity = QeIdentity::verify(&qe_identity, &chain, &pki.root_ca, NOW).unwrap();
+
+        evaluate_tcb(&quote, &tcb_info, &qe_identity)
+    }
+
+    #[test]
+    fn test_tcb_policy() {
+        let policy = TcbPolicy::default();
+        assert_eq!(policy.check(&TcbStatus::UpToDate), Ok(()));
+        assert_eq!(policy.check(&TcbStatus::OutOfDate), Ok(()));
+        assert_eq!(
+            policy.check(&TcbStatus::SwHardeningNeeded),
+            Err(Error::TcbStatusNotTolerated(TcbStatus::SwHardeningNeeded)),
+        );
+
+        let policy = policy
+            .tolerate(TcbStatus::SwHardeningNeeded)
+            .tolerate(TcbStatus::SwHardeningNeeded)
+            .tolerate(TcbStatus::Revoked);
+        assert_eq!(policy.tolerated_statuses(), &[TcbStatus::OutOfDate, TcbStatus::SwHardeningNeeded][..]);
+        assert_eq!(policy.check(&TcbStatus::Revoked), Err(Error::TcbStatusNotTolerated(TcbStatus::Revoked)));
+
+        let policy = TcbPolicy::strict();
+        assert_eq!(policy.check(&TcbStatus::UpToDate), Ok(()));
+        assert!(policy.check(&TcbStatus::OutOfDate).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_tcb() {
+        let evaluation = evaluate(LEVELS, QE_LEVELS).unwrap();
+
+        assert_eq!(evaluation.status, TcbStatus::SwHardeningNeeded);
+        assert_eq!(evaluation.platform_status, TcbStatus::SwHardeningNeeded);
+        assert_eq!(evaluation.qe_status, TcbStatus::UpToDate);
+        assert_eq!(evaluation.advisory_ids, vec!["INTEL-SA-00615".to_string()]);
+        assert_eq!(evaluation.status.to_string(), "SWHardeningNeeded");
+    }
+
+    #[test]
+    fn test_converge_with_out_of_date_qe() {
+        let qe_levels: &[(u16, &str, &[&str])] = &[
+            (9, "UpToDate", &[]),
+            (6, "OutOfDate", &["INTEL-SA-00615", "INTEL-SA-00477"]),
+        ];
+        let evaluation = evaluate(LEVELS, qe_levels).unwrap();
+
+        assert_eq!(evaluation.status, TcbStatus::OutOfDate);
+        assert_eq!(evaluation.platform_status, TcbStatus::SwHardeningNeeded);
+        assert_eq!(evaluation.qe_status, TcbStatus::OutOfDate);
+        assert_eq!(evaluation.advisory_ids, vec!["INTEL-SA-00615".to_string(), "INTEL-SA-00477".to_string()]);
+    }
+
+    #[test]
+    fn test_reject_unknown_tcb_level() {
+        assert_eq!(evaluate(&LEVELS[..1], QE_LEVELS), Err(Error::TcbLevelNotFound("platform")));
+        assert_eq!(evaluate(LEVELS, &QE_LEVELS[..0]), Err(Error::TcbLevelNotFound("QE")));
+    }
+
+    #[test]
+    fn test_reject_mismatched_collateral() {
+        let pki = TestPki::new();
+        let quote = verify_quote(&sgx_quote(&pki), &pki.root_ca, NOW).unwrap();
+        let qe_identity = QeIdentity::parse(&signed("enclaveIdentity", &qe_identity_body(QE_LEVELS), &pki.tcb_signing_key))
+            .unwrap();
+
+        let body = tcb_info_body(3, LEVELS).replace("00906EA10000", "00606A000000");
+        let tcb_info = TcbInfo::parse(&signed("tcbInfo", &body, &pki.tcb_signing_key)).unwrap();
+        assert_eq!(evaluate_tcb(&quote, &tcb_info, &qe_identity), Err(Error::CollateralMismatch("FMSPC")));
+
+        let tcb_info = TcbInfo::parse(&signed("tcbInfo", &tcb_info_body(3, LEVELS), &pki.tcb_signing_key)).unwrap();
+        let mut other_qe = qe_identity.clone();
+        other_qe.mrsigner = [0; 32];
+        assert_eq!(evaluate_tcb(&quote, &tcb_info, &other_qe), Err(Error::QeIdentityMismatch("MRSIGNER")));
+        let mut other_qe = qe_identity;
+        other_qe.attributes[0] = 0x13;
+        assert_eq!(evaluate_tcb(&quote, &tcb_info, &other_qe), Err(Error::QeIdentityMismatch("ATTRIBUTES")));
+    }
+}
@@ -0,0 +1,336 @@
+//! ECDSA P-256 certificates and PCK chains for tests and the simulated platform
+// The enclave tests of the PCS provider use only the CRLs.
+#![cfg_attr(not(any(test, feature = "sim")), allow(dead_code))]
+use alloc::vec::Vec;
+use ring::{rand::SystemRandom, signature::{self, EcdsaKeyPair, KeyPair}};
+use crate::cert;
+use crate::der::{self, tlv, sequence};
+use crate::pck::{SgxExtensions, PckTcb, SgxType, PlatformConfiguration, SGX_EXTENSIONS_OID};
+
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+/// 2020-01-01T00:00:00Z
+pub(crate) const NOT_BEFORE: u64 = 1_577_836_800;
+/// 2040-01-01T00:00:00Z
+pub(crate) const NOT_AFTER: u64 = 2_208_988_800;
+/// A time within the validity of the certificates
+#[cfg(any(test, feature = "enclave-test"))]
+pub(crate) const NOW: u64 = 1_700_000_000;
+
+/// A P-256 key pair which signs certificates (ASN.1 signatures) and quotes
+/// (fixed-length `r || s` signatures).
+pub(crate) struct TestKey {
+    asn1: EcdsaKeyPair,
+    fixed: EcdsaKeyPair,
+}
+
+impl TestKey {
+    pub(crate) fn generate() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        TestKey {
+            asn1: EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref()).unwrap(),
+            fixed: EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref()).unwrap(),
+        }
+    }
+
+    /// The public key as `x || y`
+    pub(crate) fn public_key(&self) -> [u8; 64] {
+        array!(&self.asn1.public_key().as_ref()[1..], 64)
+    }
+
+    pub(crate) fn sign_fixed(&self, msg: &[u8]) -> [u8; 64] {
+        let sig = self.fixed.sign(&SystemRandom::new(), msg).unwrap();
+        array!(sig.as_ref(), 64)
+    }
+
+    fn sign_asn1(&self, msg: &[u8]) -> Vec<u8> {
+        self.asn1.sign(&SystemRandom::new(), msg).unwrap().as_ref().to_vec()
+    }
+}
+
+fn name(common_name: &str) -> Vec<u8> {
+    let attr = sequence(&[&tlv(0x06, OID_COMMON_NAME), &tlv(0x0c, common_name.as_bytes())]);
+    sequence(&[&tlv(0x31, &attr)])
+}
+
+/// Splits seconds since the Unix epoch into year, month, day, hour, minute and second.
+pub(crate) fn date_time(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    // Civil date from days, after Howard Hinnant's `civil_from_days`
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let tod = secs % 86400;
+    (year, month, day, tod / 3600, tod / 60 % 60, tod % 60)
+}
+
+/// Formats seconds since the Unix epoch as `GeneralizedTime`.
+fn generalized_time(secs: u64) -> Vec<u8> {
+    let (year, month, day, hour, minute, second) = date_time(secs);
+    let time = format!("{:04}{:02}{:02}{:02}{:02}{:02}Z", year, month, day, hour, minute, second);
+    tlv(0x18, time.as_bytes())
+}
+
+/// Builds a certificate of `key`, issued by `issuer`.
+///
+/// `extensions` are DER-encoded `Extension`s added after basic constraints.
+pub(crate) fn certificate(
+    subject: &str,
+    key: &TestKey,
+    issuer: &str,
+    issuer_key: &TestKey,
+    is_ca: bool,
+    extensions: &[Vec<u8>],
+) -> Vec<u8> {
+    let alg = sequence(&[&tlv(0x06, OID_ECDSA_WITH_SHA256)]);
+    let mut public_key = vec![0x00, 0x04];
+    public_key.extend_from_slice(&key.public_key());
+    let spki = sequence(&[
+        &sequence(&[&tlv(0x06, OID_EC_PUBLIC_KEY), &tlv(0x06, OID_PRIME256V1)]),
+        &tlv(0x03, &public_key),
+    ]);
+    let basic_constraints = if is_ca {
+        sequence(&[&tlv(0x01, &[0xff])])
+    } else {
+        sequence(&[])
+    };
+    let mut exts = vec![sequence(&[
+        &tlv(0x06, OID_BASIC_CONSTRAINTS),
+        &tlv(0x01, &[0xff]),
+        &tlv(0x04, &basic_constraints),
+    ])];
+    exts.extend_from_slice(extensions);
+    let exts: Vec<&[u8]> = exts.iter().map(|e| &e[..]).collect();
+
+    let tbs = sequence(&[
+        &tlv(0xa0, &der::unsigned_integer(&[2])),
+        &der::unsigned_integer(&key.public_key()[..8]),
+        &alg,
+        &name(issuer),
+        &sequence(&[&generalized_time(NOT_BEFORE), &generalized_time(NOT_AFTER)]),
+        &name(subject),
+        &spki,
+        &tlv(0xa3, &sequence(&exts)),
+    ]);
+    let mut sig = vec![0x00];
+    sig.extend_from_slice(&issuer_key.sign_asn1(&tbs));
+
+    sequence(&[&tbs, &alg, &tlv(0x03, &sig)])
+}
+
+/// Builds a CRL issued by `issuer`, which revokes `revoked` certificates.
+pub(crate) fn crl(
+    issuer: &str,
+    issuer_key: &TestKey,
+    this_update: u64,
+    next_update: u64,
+    revoked: &[&[u8]],
+) -> Vec<u8> {
+    let alg = sequence(&[&tlv(0x06, OID_ECDSA_WITH_SHA256)]);
+    let entries: Vec<Vec<u8>> = revoked.iter()
+        .map(|cert| {
+            let serial = cert::serial_number(cert).unwrap();
+            sequence(&[&tlv(der::TAG_INTEGER, serial), &generalized_time(this_update)])
+        })
+        .collect();
+    let entries: Vec<&[u8]> = entries.iter().map(|e| &e[..]).collect();
+    let revoked = if entries.is_empty() { vec![] } else { sequence(&entries) };
+
+    let tbs = sequence(&[
+        &der::unsigned_integer(&[1]),
+        &alg,
+        &name(issuer),
+        &generalized_time(this_update),
+        &generalized_time(next_update),
+        &revoked,
+    ]);
+    let mut sig = vec![0x00];
+    sig.extend_from_slice(&issuer_key.sign_asn1(&tbs));
+
+    sequence(&[&tbs, &alg, &tlv(0x03, &sig)])
+}
+
+#[cfg(any(test, feature = "enclave-test"))]
+pub(crate) fn pem_crl(crl: &[u8]) -> Vec<u8> {
+    let mut pem = b"-----BEGIN X509 CRL-----\n".to_vec();
+    for line in base64::encode(crl).as_bytes().chunks(64) {
+        pem.extend_from_slice(line);
+        pem.push(b'\n');
+    }
+    pem.extend_from_slice(b"-----END X509 CRL-----\n");
+    pem
+}
+
+pub(crate) fn pem(certs: &[&[u8]]) -> Vec<u8> {
+    let mut pem = Vec::new();
+    for cert in certs {
+        pem.extend_from_slice(b"-----BEGIN CERTIFICATE-----\n");
+        for line in base64::encode(cert).as_bytes().chunks(64) {
+            pem.extend_from_slice(line);
+            pem.push(b'\n');
+        }
+        pem.extend_from_slice(b"-----END CERTIFICATE-----\n");
+    }
+    pem
+}
+
+/// SGX extensions of a processor-issued PCK certificate
+pub(crate) fn sample_sgx_extensions() -> SgxExtensions {
+    SgxExtensions {
+        ppid: [0xa5; 16],
+        tcb: PckTcb {
+            sgx_tcb_comp_svn: [2, 2, 2, 2, 4, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0],
+            pce_svn: 11,
+            cpu_svn: [2, 2, 2, 2, 4, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0],
+        },
+        pce_id: [0, 0],
+        fmspc: [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00],
+        sgx_type: SgxType::Standard,
+        platform_instance_id: None,
+        configuration: None,
+    }
+}
+
+fn oid(arcs: &[u8]) -> Vec<u8> {
+    tlv(0x06, &[SGX_EXTENSIONS_OID, arcs].concat())
+}
+
+/// Encodes an entry of the SGX extensions, `arcs` under the SGX extensions OID.
+pub(crate) fn entry(arcs: &[u8], value: &[u8]) -> Vec<u8> {
+    sequence(&[&oid(arcs), value])
+}
+
+/// Encodes the SGX extensions as an X.509 `Extension`.
+pub(crate) fn sgx_extension(ext: &SgxExtensions) -> Vec<u8> {
+    let mut tcb = vec![];
+    for (i, svn) in ext.tcb.sgx_tcb_comp_svn.iter().enumerate() {
+        tcb.push(entry(&[2, i as u8 + 1], &der::unsigned_integer(&[*svn])));
+    }
+    tcb.push(entry(&[2, 17], &der::unsigned_integer(&ext.tcb.pce_svn.to_be_bytes())));
+    tcb.push(entry(&[2, 18], &tlv(0x04, &ext.tcb.cpu_svn)));
+    let tcb: Vec<&[u8]> = tcb.iter().map(|e| &e[..]).collect();
+
+    let sgx_type = match ext.sgx_type {
+        SgxType::Standard => 0,
+        SgxType::Scalable => 1,
+        SgxType::ScalableWithIntegrity => 2,
+    };
+    let mut entries = vec![
+        entry(&[1], &tlv(0x04, &ext.ppid)),
+        entry(&[2], &sequence(&tcb)),
+        entry(&[3], &tlv(0x04, &ext.pce_id)),
+        entry(&[4], &tlv(0x04, &ext.fmspc)),
+        entry(&[5], &tlv(0x0a, &[sgx_type])),
+    ];
+    if let Some(ref id) = ext.platform_instance_id {
+        entries.push(entry(&[6], &tlv(0x04, id)));
+    }
+    if let Some(ref config) = ext.configuration {
+        entries.push(entry(&[7], &configuration(config)));
+    }
+    let entries: Vec<&[u8]> = entries.iter().map(|e| &e[..]).collect();
+
+    sequence(&[&tlv(0x06, SGX_EXTENSIONS_OID), &tlv(0x04, &sequence(&entries))])
+}
+
+fn configuration(config: &PlatformConfiguration) -> Vec<u8> {
+    let flags = [config.dynamic_platform, config.cached_keys, config.smt_enabled];
+    let entries: Vec<Vec<u8>> = flags.iter().enumerate()
+        .filter_map(|(i, flag)| flag.map(|f| entry(&[7, i as u8 + 1], &tlv(0x01, &[if f { 0xff } else { 0 }]))))
+        .collect();
+    let entries: Vec<&[u8]> = entries.iter().map(|e| &e[..]).collect();
+    sequence(&entries)
+}
+
+/// A root CA, an intermediate CA and a PCK certificate, and a TCB signing
+/// certificate for collateral
+pub(crate) struct TestPki {
+    pub root_ca: Vec<u8>,
+    pub root_key: TestKey,
+    /// Common name of the intermediate CA, by which PCK certificates tell
+    /// the Processor CA from the Platform CA
+    pub intermediate_name: &'static str,
+    pub intermediate_ca: Vec<u8>,
+    pub intermediate_key: TestKey,
+    pub pck_cert: Vec<u8>,
+    pub pck_key: TestKey,
+    pub tcb_signing_cert: Vec<u8>,
+    pub tcb_signing_key: TestKey,
+}
+
+impl TestPki {
+    /// Issues a PCK certificate with `sample_sgx_extensions`.
+    pub(crate) fn new() -> Self {
+        Self::with_pck_extensions(&[sgx_extension(&sample_sgx_extensions())])
+    }
+
+    /// Issues a PCK certificate with the extensions from the Processor CA.
+    pub(crate) fn with_pck_extensions(pck_extensions: &[Vec<u8>]) -> Self {
+        Self::with_pck_ca("Intel SGX PCK Processor CA", pck_extensions)
+    }
+
+    /// Issues a PCK certificate with the extensions from an intermediate CA
+    /// named `intermediate_name`.
+    pub(crate) fn with_pck_ca(intermediate_name: &'static str, pck_extensions: &[Vec<u8>]) -> Self {
+        let root_key = TestKey::generate();
+        let intermediate_key = TestKey::generate();
+        let pck_key = TestKey::generate();
+        let root_ca = certificate("Test SGX Root CA", &root_key, "Test SGX Root CA", &root_key, true, &[]);
+        let intermediate_ca = certificate(
+            intermediate_name, &intermediate_key, "Test SGX Root CA", &root_key, true, &[]);
+        let pck_cert = certificate(
+            "Test SGX PCK Certificate", &pck_key, intermediate_name, &intermediate_key, false,
+            pck_extensions,
+        );
+        let tcb_signing_key = TestKey::generate();
+        let tcb_signing_cert = certificate(
+            "Test SGX TCB Signing", &tcb_signing_key, "Test SGX Root CA", &root_key, false, &[]);
+
+        TestPki {
+            root_ca,
+            root_key,
+            intermediate_name,
+            intermediate_ca,
+            intermediate_key,
+            pck_cert,
+            pck_key,
+            tcb_signing_cert,
+            tcb_signing_key,
+        }
+    }
+
+    /// The PEM-encoded chain as carried in certification data
+    pub(crate) fn pck_cert_chain_pem(&self) -> Vec<u8> {
+        pem(&[&self.pck_cert, &self.intermediate_ca, &self.root_ca])
+    }
+
+    /// The PEM-encoded issuer chain of TCB info and QE identity
+    pub(crate) fn tcb_signing_chain_pem(&self) -> Vec<u8> {
+        pem(&[&self.tcb_signing_cert, &self.root_ca])
+    }
+
+    /// The PEM-encoded issuer chain of the PCK CRL
+    pub(crate) fn pck_crl_chain_pem(&self) -> Vec<u8> {
+        pem(&[&self.intermediate_ca, &self.root_ca])
+    }
+
+    /// A CRL of the intermediate CA, which issues PCK certificates
+    pub(crate) fn pck_crl(&self, this_update: u64, next_update: u64, revoked: &[&[u8]]) -> Vec<u8> {
+        crl(self.intermediate_name, &self.intermediate_key, this_update, next_update, revoked)
+    }
+
+    /// A CRL of the root CA
+    pub(crate) fn root_ca_crl(&self, this_update: u64, next_update: u64, revoked: &[&[u8]]) -> Vec<u8> {
+        crl("Test SGX Root CA", &self.root_key, this_update, next_update, revoked)
+    }
+}
@@ -0,0 +1,300 @@
+use alloc::vec::Vec;
+use attestation::{AttestationVerifier, VerifiedEnclave};
+use crate::cert::serial_number;
+use crate::collateral::{TcbInfo, QeIdentity, PCK_CRL, ROOT_CA_CRL};
+use crate::crl::Crl;
+use crate::error::{Error, Result};
+use crate::provider::CollateralProvider;
+use crate::quote::TEE_TYPE_TDX;
+use crate::tcb::{evaluate_tcb, TcbPolicy};
+use crate::verify::{verify_quote, VerifiedQuote};
+
+/// Verifies DCAP quotes of SGX enclaves, with TCB info, QE identity and
+/// CRLs fetched from a collateral provider
+#[derive(Debug, Clone)]
+pub struct DcapVerifier<P> {
+    root_ca: Vec<u8>,
+    provider: P,
+    policy: TcbPolicy,
+    now: Option<u64>,
+    /// Whether the system time may be used when `now` is not given. Inside
+    /// enclaves it comes from the untrusted host, which can rewind it to
+    /// pass expired collateral and CRLs.
+    system_time_trusted: bool,
+}
+
+impl<P: CollateralProvider> DcapVerifier<P> {
+    /// `root_ca` is the DER-encoded CA which PCK certificates and collateral
+    /// must lead to, i.e. the Intel SGX Root CA in production.
+    pub fn new(root_ca: Vec<u8>, provider: P) -> Self {
+        DcapVerifier {
+            root_ca,
+            provider,
+            policy: TcbPolicy::default(),
+            now: None,
+            system_time_trusted: cfg!(not(feature = "sgx")),
+        }
+    }
+
+    /// Accepts the TCB statuses of `policy` instead of the default one.
+    pub fn with_policy(mut self, policy: TcbPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Verifies at `now`, in seconds since the Unix epoch, instead of the
+    /// system time.
+    ///
+    /// Required inside enclaves, where the system time comes from the
+    /// untrusted host: without it quotes are rejected with `UnknownTime`.
+    pub fn at(mut self, now: u64) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Checks the PCK certificate against the CRL of its CA, and the CA
+    /// against the CRL of the root CA. The CRLs are verified with the
+    /// certificates of the quote's chain, which leads to `root_ca`.
+    fn check_revocation(&self, quote: &VerifiedQuote, now: u64) -> Result<()> {
+        let chain = quote.pck_cert_chain();
+        let pck_ca = chain.get(1).unwrap_or(&self.root_ca);
+
+        let pck_crl = self.provider.pck_crl(quote.pck_cert().ca())?;
+        let pck_crl = Crl::verify(PCK_CRL, &pck_crl.data, pck_ca, now)?;
+        if pck_crl.is_revoked(serial_number(&chain[0])?) {
+            return Err(Error::CertificateRevoked("PCK certificate"));
+        }
+        let root_ca_crl = self.provider.root_ca_crl()?;
+        let root_ca_crl = Crl::verify(ROOT_CA_CRL, &root_ca_crl, &self.root_ca, now)?;
+        if *pck_ca != self.root_ca && root_ca_crl.is_revoked(serial_number(pck_ca)?) {
+            return Err(Error::CertificateRevoked("PCK CA certificate"));
+        }
+
+        Ok(())
+    }
+
+    fn now(&self) -> Result<u64> {
+        match self.now {
+            Some(now) => Ok(now),
+            None if !self.system_time_trusted => Err(Error::UnknownTime),
+            None => {
+                #[cfg(feature = "sgx")]
+                use std::untrusted::time::SystemTimeEx;
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .map_err(|_| Error::UnknownTime)
+            }
+        }
+    }
+}
+
+impl<P: CollateralProvider> AttestationVerifier for DcapVerifier<P> {
+    type Evidence = [u8];
+    type Error = Error;
+
+    /// Verifies the quote and its collateral, checks that no certificate of
+    /// the PCK chain is revoked, and evaluates the TCB status against the
+    /// policy. TDX quotes are rejected, as they don't attest an enclave.
+    fn verify(&self, quote: &[u8]) -> Result<VerifiedEnclave> {
+        let now = self.now()?;
+        let quote = verify_quote(quote, &self.root_ca, now)?;
+        let report = quote.quote().enclave_report_body()
+            .ok_or(Error::UnsupportedTeeType(TEE_TYPE_TDX))?;
+
+        let tee_type = quote.header().tee_type;
+        let tcb_info = self.provider.tcb_info(tee_type, &quote.pck_cert().fmspc())?;
+        let tcb_info = TcbInfo::verify(&tcb_info.data, &tcb_info.issuer_chain, &self.root_ca, now)?;
+        let qe_identity = self.provider.qe_identity(tee_type)?;
+        let qe_identity = QeIdentity::verify(&qe_identity.data, &qe_identity.issuer_chain, &self.root_ca, now)?;
+        self.check_revocation(&quote, now)?;
+        let evaluation = evaluate_tcb(&quote, &tcb_info, &qe_identity)?;
+        self.policy.check(&evaluation.status)?;
+
+        Ok(VerifiedEnclave {
+            mr_enclave: report.mr_enclave(),
+            mr_signer: report.mr_signer(),
+            isv_prod_id: report.isv_prod_id(),
+            isv_svn: report.isv_svn(),
+            attributes: report.attributes(),
+            report_data: report.report_data(),
+            tcb_status: evaluation.status,
+            advisory_ids: evaluation.advisory_ids,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use crate::collateral::{TCB_INFO, tests::{tcb_info_body, qe_identity_body, LEVELS, QE_LEVELS}};
+    use crate::pck::PckCa;
+    use crate::provider::SignedCollateral;
+    use crate::quote::{TeeType, tests::td_report_body};
+    use crate::report::TD_REPORT10_BODY_LEN;
+    use crate::sim::signed;
+    use crate::tcb::TcbStatus;
+    use crate::test_certs::{TestPki, TestKey, NOW};
+    use crate::verify::tests::{signed_quote, sgx_quote};
+
+    struct FixedProvider {
+        tcb_info: SignedCollateral,
+        qe_identity: SignedCollateral,
+        pck_crl: SignedCollateral,
+        root_ca_crl: Vec<u8>,
+    }
+
+    impl FixedProvider {
+        /// Collateral of `pki`, with CRLs which revoke nothing
+        fn new(pki: &TestPki) -> Self {
+            Self::with_revoked(pki, &[], &[])
+        }
+
+        /// Collateral of `pki`, whose PCK CRL revokes `pck_revoked` and root
+        /// CA CRL revokes `ca_revoked`
+        fn with_revoked(pki: &TestPki, pck_revoked: &[&[u8]], ca_revoked: &[&[u8]]) -> Self {
+            let issuer_chain = pki.tcb_signing_chain_pem();
+            FixedProvider {
+                tcb_info: SignedCollateral {
+                    data: signed("tcbInfo", &tcb_info_body(3, LEVELS), &pki.tcb_signing_key),
+                    issuer_chain: issuer_chain.clone(),
+                },
+                qe_identity: SignedCollateral {
+                    data: signed("enclaveIdentity", &qe_identity_body(QE_LEVELS), &pki.tcb_signing_key),
+                    issuer_chain,
+                },
+                pck_crl: SignedCollateral {
+                    data: pki.pck_crl(NOW - 60, NOW + 60, pck_revoked),
+                    issuer_chain: pki.pck_crl_chain_pem(),
+                },
+                root_ca_crl: pki.root_ca_crl(NOW - 60, NOW + 60, ca_revoked),
+            }
+        }
+    }
+
+    impl CollateralProvider for FixedProvider {
+        fn tcb_info(&self, _tee_type: TeeType, _fmspc: &[u8; 6]) -> Result<SignedCollateral> {
+            Ok(self.tcb_info.clone())
+        }
+
+        fn qe_identity(&self, _tee_type: TeeType) -> Result<SignedCollateral> {
+            Ok(self.qe_identity.clone())
+        }
+
+        fn pck_crl(&self, _ca: PckCa) -> Result<SignedCollateral> {
+            Ok(self.pck_crl.clone())
+        }
+
+        fn root_ca_crl(&self) -> Result<Vec<u8>> {
+            Ok(self.root_ca_crl.clone())
+        }
+    }
+
+    #[test]
+    fn test_verify_enclave() {
+        let pki = TestPki::new();
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), FixedProvider::new(&pki))
+            .with_policy(TcbPolicy::default().tolerate(TcbStatus::SwHardeningNeeded))
+            .at(NOW);
+        let enclave = verifier.verify(&sgx_quote(&pki)).unwrap();
+
+        assert_eq!(enclave.mr_enclave, [0x11; 32]);
+        assert_eq!(enclave.mr_signer, [0x22; 32]);
+        assert_eq!(enclave.isv_prod_id, 5);
+        assert_eq!(enclave.isv_svn, 9);
+        assert_eq!(&enclave.report_data[..], &[0x33; 64][..]);
+        assert_eq!(enclave.tcb_status, TcbStatus::SwHardeningNeeded);
+        assert_eq!(enclave.advisory_ids, vec!["INTEL-SA-00615".to_string()]);
+    }
+
+    #[test]
+    fn test_reject_untolerated_tcb_status() {
+        let pki = TestPki::new();
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), FixedProvider::new(&pki)).at(NOW);
+        assert_eq!(
+            verifier.verify(&sgx_quote(&pki)),
+            Err(Error::TcbStatusNotTolerated(TcbStatus::SwHardeningNeeded)),
+        );
+
+        let verifier = verifier.with_policy(TcbPolicy::strict().tolerate(TcbStatus::SwHardeningNeeded));
+        assert!(verifier.verify(&sgx_quote(&pki)).is_ok());
+    }
+
+    #[test]
+    fn test_reject_tdx_quote() {
+        let pki = TestPki::new();
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), FixedProvider::new(&pki)).at(NOW);
+        let quote = signed_quote(&pki, &TestKey::generate(), 4, 0x81, &td_report_body(TD_REPORT10_BODY_LEN));
+
+        assert_eq!(verifier.verify(&quote), Err(Error::UnsupportedTeeType(TEE_TYPE_TDX)));
+    }
+
+    #[test]
+    fn test_require_time_in_enclave() {
+        let pki = TestPki::new();
+        let policy = TcbPolicy::default().tolerate(TcbStatus::SwHardeningNeeded);
+        // As built with the `sgx` feature
+        let mut verifier = DcapVerifier::new(pki.root_ca.clone(), FixedProvider::new(&pki)).with_policy(policy);
+        verifier.system_time_trusted = false;
+        assert_eq!(verifier.verify(&sgx_quote(&pki)), Err(Error::UnknownTime));
+
+        assert!(verifier.at(NOW).verify(&sgx_quote(&pki)).is_ok());
+    }
+
+    #[test]
+    fn test_reject_revoked_pck_cert() {
+        let pki = TestPki::new();
+        let other = TestPki::new();
+        // Only the serial number matters.
+        let provider = FixedProvider::with_revoked(&pki, &[&other.pck_cert], &[]);
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), provider)
+            .with_policy(TcbPolicy::default().tolerate(TcbStatus::SwHardeningNeeded))
+            .at(NOW);
+        assert!(verifier.verify(&sgx_quote(&pki)).is_ok());
+
+        let provider = FixedProvider::with_revoked(&pki, &[&other.pck_cert, &pki.pck_cert], &[]);
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), provider).at(NOW);
+        assert_eq!(verifier.verify(&sgx_quote(&pki)), Err(Error::CertificateRevoked("PCK certificate")));
+    }
+
+    #[test]
+    fn test_reject_revoked_pck_ca() {
+        let pki = TestPki::new();
+        let provider = FixedProvider::with_revoked(&pki, &[], &[&pki.intermediate_ca]);
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), provider).at(NOW);
+
+        assert_eq!(verifier.verify(&sgx_quote(&pki)), Err(Error::CertificateRevoked("PCK CA certificate")));
+    }
+
+    #[test]
+    fn test_reject_invalid_crls() {
+        let pki = TestPki::new();
+        let other = TestPki::new();
+        let mut provider = FixedProvider::new(&pki);
+        provider.pck_crl.data = other.pck_crl(NOW - 60, NOW + 60, &[]);
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), provider).at(NOW);
+        assert_eq!(verifier.verify(&sgx_quote(&pki)), Err(Error::InvalidCollateralSignature(PCK_CRL)));
+
+        let mut provider = FixedProvider::new(&pki);
+        provider.root_ca_crl = pki.root_ca_crl(NOW - 120, NOW - 60, &[]);
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), provider).at(NOW);
+        assert_eq!(verifier.verify(&sgx_quote(&pki)), Err(Error::CollateralExpired(ROOT_CA_CRL)));
+    }
+
+    #[test]
+    fn test_reject_collateral_from_other_root() {
+        let pki = TestPki::new();
+        let other = TestPki::new();
+        let verifier = DcapVerifier::new(pki.root_ca.clone(), FixedProvider::new(&other)).at(NOW);
+
+        match verifier.verify(&sgx_quote(&pki)) {
+            Err(Error::InvalidCollateralCertChain { collateral, .. }) => assert_eq!(collateral, TCB_INFO),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use alloc::vec::Vec;
+use ring::{digest, signature};
+use crate::cert::{pem_to_der_chain, verify_cert_chain, verify_signature};
+use crate::error::{Error, Result};
+use crate::pck::PckCert;
+use crate::quote::{Quote, QuoteHeader, QuoteBody};
+use crate::report::EnclaveReportBody;
+
+/// A quote whose signatures and PCK certificate chain have been verified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedQuote {
+    quote: Quote,
+    pck_cert: PckCert,
+    pck_cert_chain: Vec<Vec<u8>>,
+}
+
+impl VerifiedQuote {
+    pub fn quote(&self) -> &Quote {
+        &self.quote
+    }
+
+    pub fn header(&self) -> &QuoteHeader {
+        &self.quote.header
+    }
+
+    pub fn body(&self) -> &QuoteBody {
+        &self.quote.body
+    }
+
+    /// The report of the Quoting Enclave, signed by the PCK certificate
+    pub fn qe_report(&self) -> &EnclaveReportBody {
+        &self.quote.signature.qe_report
+    }
+
+    /// The PCK certificate, whose SGX extensions identify the platform and its TCB
+    pub fn pck_cert(&self) -> &PckCert {
+        &self.pck_cert
+    }
+
+    /// DER-encoded certificates, from the PCK certificate to the root CA
+    pub fn pck_cert_chain(&self) -> &[Vec<u8>] {
+        &self.pck_cert_chain[..]
+    }
+
+    pub fn into_quote(self) -> Quote {
+        self.quote
+    }
+}
+
+/// Verifies a DCAP quote against the trusted root CA, which should be the
+/// Intel SGX Root CA in production.
+///
+/// `root_ca` is DER-encoded and `now` is in seconds since the Unix epoch.
+/// The checks are, in order:
+/// 1. the PCK certificate chain leads to `root_ca` and is valid at `now`,
+/// 2. the QE report is signed by the PCK certificate,
+/// 3. the QE report data is `SHA256(attestation key || QE auth data) || 0*32`,
+/// 4. the header and the body are signed by the attestation key,
+/// 5. the PCK certificate has the SGX extensions.
+///
+/// TCB status is not evaluated here.
+pub fn verify_quote(quote: &[u8], root_ca: &[u8], now: u64) -> Result<VerifiedQuote> {
+    let quote = Quote::parse(quote)?;
+    let pck_cert_chain = verify_pck_cert_chain(&quote, root_ca, now)?;
+    verify_qe_report_signature(&quote, &pck_cert_chain[0])?;
+    verify_qe_report_data(&quote)?;
+    verify_isv_enclave_report_signature(&quote)?;
+    let pck_cert = PckCert::parse(&pck_cert_chain[0])?;
+
+    Ok(VerifiedQuote {
+        quote,
+        pck_cert,
+        pck_cert_chain,
+    })
+}
+
+fn verify_pck_cert_chain(quote: &Quote, root_ca: &[u8], now: u64) -> Result<Vec<Vec<u8>>> {
+    let pem = quote.signature.certification_data.pck_cert_chain()
+        .ok_or(Error::MissingPckCertChain)?;
+    let chain = pem_to_der_chain(pem)?;
+    verify_cert_chain(&chain, root_ca, now)
+        .map_err(|e| Error::InvalidPckCertChain(format!("{:?}", e)))?;
+
+    Ok(chain)
+}
+
+fn verify_qe_report_signature(quote: &Quote, pck_cert: &[u8]) -> Result<()> {
+    let sig = &quote.signature;
+    if verify_signature(pck_cert, sig.qe_report.as_bytes(), sig.qe_report_signature.as_bytes()) {
+        Ok(())
+    } else {
+        Err(Error::InvalidQeReportSignature)
+    }
+}
+
+fn verify_qe_report_data(quote: &Quote) -> Result<()> {
+    let sig = &quote.signature;
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(sig.attestation_key.as_bytes());
+    ctx.update(&sig.qe_auth_data);
+    let report_data = sig.qe_report.report_data();
+    if report_data[..32] != *ctx.finish().as_ref() || report_data[32..].iter().any(|b| *b != 0) {
+        return Err(Error::QeReportDataMismatch);
+    }
+
+    Ok(())
+}
+
+fn verify_isv_enclave_report_signature(quote: &Quote) -> Result<()> {
+    let mut public_key = vec![0x04];
+    public_key.extend_from_slice(quote.signature.attestation_key.as_bytes());
+    signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, public_key)
+        .verify(quote.signed_data(), quote.signature.isv_enclave_report_signature.as_bytes())
+        .map_err(|_| Error::InvalidIsvEnclaveReportSignature)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::quote::tests::{header, enclave_report_body, td_report_body};
+    use crate::report::{ENCLAVE_REPORT_BODY_LEN, TD_REPORT10_BODY_LEN};
+    use crate::sim::encode_quote;
+    use crate::test_certs::{TestKey, TestPki, NOW, NOT_AFTER};
+
+    /// Offsets in a v3 quote
+    const QE_REPORT_OFFSET: usize = 48 + ENCLAVE_REPORT_BODY_LEN + 4 + 128;
+    const QE_AUTH_DATA_OFFSET: usize = QE_REPORT_OFFSET + ENCLAVE_REPORT_BODY_LEN + 64 + 2;
+
+    /// Encodes a quote signed by `attestation_key`, which is certified by the PCK key of `pki`.
+    pub(crate) fn signed_quote(pki: &TestPki, attestation_key: &TestKey, version: u16, tee_type: u32, body: &[u8]) -> Vec<u8> {
+        encode_quote(pki, attestation_key, &header(version, tee_type), body)
+    }
+
+    pub(crate) fn sgx_quote(pki: &TestPki) -> Vec<u8> {
+        signed_quote(pki, &TestKey::generate(), 3, 0, &enclave_report_body())
+    }
+
+    #[test]
+    fn test_verify_sgx_quote() {
+        let pki = TestPki::new();
+        let verified = verify_quote(&sgx_quote(&pki), &pki.root_ca, NOW).unwrap();
+
+        assert_eq!(verified.quote().enclave_report_body().unwrap().mr_enclave(), [0x11; 32]);
+        assert_eq!(verified.pck_cert().as_der(), &pki.pck_cert[..]);
+        assert_eq!(verified.pck_cert().fmspc(), [0x00, 0x90, 0x6e, 0xa1, 0x00, 0x00]);
+        assert_eq!(verified.pck_cert_chain().len(), 3);
+    }
+
+    #[test]
+    fn test_verify_tdx_quote() {
+        let pki = TestPki::new();
+        let quote = signed_quote(&pki, &TestKey::generate(), 4, 0x81, &td_report_body(TD_REPORT10_BODY_LEN));
+        let verified = verify_quote(&quote, &pki.root_ca, NOW).unwrap();
+
+        assert_eq!(verified.quote().td_report_body().unwrap().mr_td(), [0x13; 48]);
+    }
+
+    #[test]
+    fn test_reject_tampered_body() {
+        let pki = TestPki::new();
+        let mut quote = sgx_quote(&pki);
+        quote[48 + 64] ^= 1;
+        assert_eq!(verify_quote(&quote, &pki.root_ca, NOW), Err(Error::InvalidIsvEnclaveReportSignature));
+    }
+
+    #[test]
+    fn test_reject_tampered_qe_report() {
+        let pki = TestPki::new();
+        let mut quote = sgx_quote(&pki);
+        quote[QE_REPORT_OFFSET] ^= 1;
+        assert_eq!(verify_quote(&quote, &pki.root_ca, NOW), Err(Error::InvalidQeReportSignature));
+    }
+
+    #[test]
+    fn test_reject_unbound_attestation_key() {
+        let pki = TestPki::new();
+        let mut quote = sgx_quote(&pki);
+        quote[QE_AUTH_DATA_OFFSET] ^= 1;
+        assert_eq!(verify_quote(&quote, &pki.root_ca, NOW), Err(Error::QeReportDataMismatch));
+    }
+
+    #[test]
+    fn test_reject_untrusted_pck_cert_chain() {
+        let pki = TestPki::new();
+        let quote = sgx_quote(&pki);
+
+        let other = TestPki::new();
+        match verify_quote(&quote, &other.root_ca, NOW) {
+            Err(Error::InvalidPckCertChain(_)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        match verify_quote(&quote, &pki.root_ca, NOT_AFTER + 1) {
+            Err(Error::InvalidPckCertChain(_)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_reject_invalid_pem() {
+        let bytes = crate::quote::tests::sample_quote();
+        assert_eq!(verify_quote(&bytes, &[], NOW), Err(Error::InvalidPem));
+    }
+}
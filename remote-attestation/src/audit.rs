@@ -0,0 +1,189 @@
+use std::{
+    prelude::v1::*,
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use anyhow::{Result, bail};
+use log::{info, warn};
+use crate::verified::VerifiedAttestation;
+
+/// An event emitted while attesting an enclave with IAS
+#[derive(Debug)]
+pub enum AuditEvent<'a> {
+    /// The attestation verification report passed all checks.
+    Verified(&'a VerifiedAttestation),
+    /// The attestation failed, either at IAS or in verifying its report.
+    Rejected {
+        request_id: Option<&'a str>,
+        error: &'a anyhow::Error,
+    },
+}
+
+/// A hook the host application implements to receive attestation events,
+/// e.g. to keep an audit trail.
+pub trait AuditHook: Sync + Send {
+    fn on_event(&self, event: &AuditEvent);
+}
+
+struct NopHook;
+
+impl AuditHook for NopHook {
+    fn on_event(&self, _: &AuditEvent) {}
+}
+
+static mut HOOK: &dyn AuditHook = &NopHook;
+static STATE: AtomicUsize = AtomicUsize::new(UNINITIALIZED);
+
+const UNINITIALIZED: usize = 0;
+const INITIALIZING: usize = 1;
+const INITIALIZED: usize = 2;
+
+/// Sets the global audit hook. This can be called only once.
+pub fn set_audit_hook(hook: &'static dyn AuditHook) -> Result<()> {
+    match STATE.compare_exchange(UNINITIALIZED, INITIALIZING, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) => {
+            unsafe { HOOK = hook; }
+            STATE.store(INITIALIZED, Ordering::SeqCst);
+            Ok(())
+        }
+        Err(_) => bail!("Audit hook is already set"),
+    }
+}
+
+fn audit_hook() -> &'static dyn AuditHook {
+    if STATE.load(Ordering::SeqCst) != INITIALIZED {
+        &NopHook
+    } else {
+        unsafe { HOOK }
+    }
+}
+
+/// Logs the result of an attestation and passes it to the audit hook.
+pub(crate) fn record(request_id: Option<&str>, result: &Result<VerifiedAttestation>) {
+    let event = match result {
+        Ok(verified) => {
+            let quote_status = verified.quote_status();
+            let advisory_ids = verified.ias_report().advisory_ids.join(",");
+            let mr_enclave = to_hex(&verified.quote_body().report_body.mr_enclave);
+            for warning in verified.warnings() {
+                warn!(
+                    "attestation warning: request_id={} quote_status={} advisory_ids=[{}] mr_enclave={} warning={:?}",
+                    request_id.unwrap_or("-"), quote_status, advisory_ids, mr_enclave, warning,
+                );
+            }
+            info!(
+                "attestation verified: request_id={} quote_status={} advisory_ids=[{}] mr_enclave={}",
+                request_id.unwrap_or("-"), quote_status, advisory_ids, mr_enclave,
+            );
+            AuditEvent::Verified(verified)
+        }
+        Err(error) => {
+            warn!(
+                "attestation rejected: request_id={} error={:?}",
+                request_id.unwrap_or("-"), error,
+            );
+            AuditEvent::Rejected { request_id, error }
+        }
+    };
+
+    audit_hook().on_event(&event);
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use std::sync::SgxMutex;
+    use anyhow::anyhow;
+    use test_utils::*;
+    use crate::client::{AttestationReport, ReportSig};
+    use crate::report::{IasReport, QuoteStatus, QUOTE_BODY_LEN, tests::report_json};
+    use crate::verified::{VerificationPolicy, AttestationWarning};
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_audit_hook,
+        )
+    }
+
+    /// Records the fields of the events it receives.
+    #[derive(Default)]
+    struct RecordingHook {
+        events: SgxMutex<Vec<String>>,
+    }
+
+    impl RecordingHook {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut *self.events.lock().unwrap())
+        }
+    }
+
+    impl AuditHook for RecordingHook {
+        fn on_event(&self, event: &AuditEvent) {
+            let event = match event {
+                AuditEvent::Verified(verified) => format!(
+                    "verified request_id={:?} quote_status={} warnings={:?}",
+                    verified.request_id(),
+                    verified.quote_status(),
+                    verified.warnings().iter().map(ToString::to_string).collect::<Vec<_>>(),
+                ),
+                AuditEvent::Rejected { request_id, error } => {
+                    format!("rejected request_id={:?} error={}", request_id, error)
+                }
+            };
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    fn verified(request_id: &str, quote_status: &str, warnings: Vec<AttestationWarning>) -> VerifiedAttestation {
+        let report = report_json(quote_status, &[0u8; QUOTE_BODY_LEN], &[]);
+        let ias_report = IasReport::from_slice(report.as_bytes()).unwrap();
+        let quote_body = ias_report.quote_body().unwrap();
+        VerifiedAttestation::new(
+            Some(request_id.to_string()),
+            AttestationReport::new(report.into_bytes()),
+            ReportSig::new(vec![]),
+            vec![],
+            ias_report,
+            quote_body,
+            VerificationPolicy::default(),
+            warnings,
+        )
+    }
+
+    /// The hook is global and can be set only once, so the steps run in
+    /// order in a single test.
+    fn test_audit_hook() {
+        let hook: &'static RecordingHook = Box::leak(Box::new(RecordingHook::default()));
+
+        // Events before a hook is set go to the no-op hook.
+        record(Some("req-0"), &Err(anyhow!("unreachable")));
+        assert!(hook.take().is_empty());
+
+        set_audit_hook(hook).unwrap();
+        let err = set_audit_hook(Box::leak(Box::new(RecordingHook::default()))).unwrap_err();
+        assert_eq!(err.to_string(), "Audit hook is already set");
+
+        record(Some("req-1"), &Ok(verified("req-1", "OK", vec![])));
+        let warning = AttestationWarning::ToleratedQuoteStatus {
+            status: QuoteStatus::GroupOutOfDate,
+            advisory_ids: vec!["INTEL-SA-00334".to_string()],
+        };
+        record(Some("req-2"), &Ok(verified("req-2", "GROUP_OUT_OF_DATE", vec![warning])));
+        record(Some("req-3"), &Err(anyhow!("Invalid Enclave Quote Status: GROUP_REVOKED")));
+        record(None, &Err(anyhow!("IAS responded with 401 Unauthorized")));
+
+        assert_eq!(hook.take(), vec![
+            r#"verified request_id=Some("req-1") quote_status=OK warnings=[]"#.to_string(),
+            r#"verified request_id=Some("req-2") quote_status=GROUP_OUT_OF_DATE warnings=["Tolerated enclave quote status: GROUP_OUT_OF_DATE (advisories: INTEL-SA-00334)"]"#.to_string(),
+            r#"rejected request_id=Some("req-3") error=Invalid Enclave Quote Status: GROUP_REVOKED"#.to_string(),
+            "rejected request_id=None error=IAS responded with 401 Unauthorized".to_string(),
+        ]);
+    }
+}
@@ -1,16 +1,19 @@
 use std::{
     prelude::v1::*,
-    net::TcpStream,
     str,
     time::{SystemTime, UNIX_EPOCH},
     untrusted::time::SystemTimeEx,
-    io::{BufReader, Write},
-    collections::HashMap,
+    io::BufReader,
 };
-use http_req::{request::{Request, Method}, uri::Uri, response::{Headers, Response}};
-use anyhow::{Result, anyhow, bail, ensure};
-use serde_json::Value;
+use client::{Client, RequestBuilder, Response};
+use anyhow::{Result, anyhow, ensure};
+use serde::{Serialize, Deserialize};
+use serde_json::{Value, json};
 use log::debug;
+use crate::report::IasReport;
+use crate::verified::{VerifiedAttestation, VerificationPolicy};
+use crate::audit;
+use crate::evidence::base64_serde;
 
 pub const TEST_SUB_KEY: &str = "77e2533de0624df28dc3be3a5b9e50d9";
 pub const TEST_SPID: &str = "2C149BFC94A61D306A96211AED155BE9";
@@ -39,80 +42,97 @@ impl RAService {
         uri: &str,
         ias_api_key: &str,
         quote: &str,
-    ) -> Result<(AttestationReport, ReportSig)> {
-        let uri: Uri = uri.parse().expect("Invalid uri");
-        let body = format!("{{\"isvEnclaveQuote\":\"{}\"}}\r\n", quote);
-        let mut writer = Vec::new();
+    ) -> Result<VerifiedAttestation> {
+        Self::remote_attestation_with_client(
+            &Client::builder().https_only(true).build()?,
+            uri,
+            ias_api_key,
+            quote,
+            VerificationPolicy::default(),
+        )
+    }
 
-        let response = RAClient::new(&uri)
-            .ias_apikey_header_mut(ias_api_key)
-            .quote_body_mut(&body.as_bytes())
-            .send(&mut writer)?;
+    /// Same as `remote_attestation`, but the request to IAS is sent through
+    /// the given `Client` so its TLS and transport settings are applied,
+    /// and the report is verified against the given policy.
+    pub fn remote_attestation_with_client(
+        client: &Client,
+        uri: &str,
+        ias_api_key: &str,
+        quote: &str,
+        policy: VerificationPolicy,
+    ) -> Result<VerifiedAttestation> {
+        let response = match RAClient::new(client, uri)
+            .ias_apikey_header(ias_api_key)
+            .quote_body(quote)
+            .send()
+        {
+            Ok(response) => response,
+            Err(e) => {
+                let result = Err(e);
+                audit::record(None, &result);
+                return result;
+            }
+        };
 
-        let ra_resp = RAResponse::from_response(writer, response)?
-            .verify_attestation_report()?;
+        // Read before the response is checked, so rejections are audited
+        // with the ID too.
+        let request_id = request_id(&response);
+        let result = RAResponse::with_request_id(request_id.clone(), response)
+            .and_then(|resp| resp.verify_attestation_report(policy));
+        audit::record(request_id.as_ref().map(|s| s.as_str()), &result);
 
-        Ok((ra_resp.attestation_report, ra_resp.report_sig))
+        result
     }
 }
 
 /// A client for remote attestation with IAS
-pub struct RAClient<'a> {
-    request: Request<'a>,
-    host: String,
+pub struct RAClient {
+    request: RequestBuilder,
 }
 
-impl<'a> RAClient<'a> {
-    pub fn new(uri: &'a Uri) -> Self {
-        let host = uri.host_header().expect("Not found host in the uri");
-
-        RAClient{
-            request: Request::new(&uri),
-            host,
+impl RAClient {
+    pub fn new(client: &Client, uri: &str) -> Self {
+        RAClient {
+            request: client.post(uri),
         }
     }
 
     /// Sets IAS API KEY to header.
-    pub fn ias_apikey_header_mut(&mut self, ias_api_key: &str) -> &mut Self {
-        let mut headers = Headers::new();
-        headers.insert("HOST", &self.host);
-        headers.insert("Ocp-Apim-Subscription-Key", ias_api_key);
-        headers.insert("Connection", "close");
-        self.request.headers(headers);
-        self.request.method(Method::POST);
-
+    pub fn ias_apikey_header(mut self, ias_api_key: &str) -> Self {
+        self.request = self.request.header("Ocp-Apim-Subscription-Key", ias_api_key);
         self
     }
 
-    /// Sets the body to the JSON serialization of the passed value, and
+    /// Sets the body to the JSON serialization of the quote, and
     /// also sets the `Content-Type: application/json` header.
-    pub fn quote_body_mut(&'a mut self, body: &'a [u8]) -> &mut Self {
-        let len = body.len().to_string();
-        self.request.header("Content-Type", "application/json");
-        self.request.header("Content-Length", &len);
-        self.request.body(&body);
-
+    pub fn quote_body(mut self, quote: &str) -> Self {
+        self.request = self.request.json(&json!({ "isvEnclaveQuote": quote }));
         self
     }
 
-    pub fn send<T: Write>(&self, writer: &mut T) -> Result<Response> {
-        self.request.send(writer)
-            .map_err(|e| anyhow!("{:?}", e))
-            .map_err(Into::into)
+    pub fn send(self) -> Result<Response> {
+        self.request.send()
     }
 }
 
 /// A response from IAS
 #[derive(Debug, Clone)]
 pub struct RAResponse {
+    request_id: Option<String>,
     attestation_report: AttestationReport,
     report_sig: ReportSig,
     cert: Vec<u8>,
 }
 
 impl RAResponse {
-    pub fn from_response(body: Vec<u8>, resp: Response) -> Result<Self> {
+    pub fn from_response(resp: Response) -> Result<Self> {
+        Self::with_request_id(request_id(&resp), resp)
+    }
+
+    fn with_request_id(request_id: Option<String>, resp: Response) -> Result<Self> {
         debug!("RA response: {:?}", resp);
+        ensure!(resp.status().is_success(), "IAS responded with {}", resp.status());
 
         let headers = resp.headers();
         let sig = headers.get("X-IASReport-Signature")
@@ -121,107 +141,122 @@ impl RAResponse {
 
         let cert = headers.get("X-IASReport-Signing-Certificate")
             .ok_or(anyhow!("Not found X-IASReport-Signing-Certificate"))?
+            .to_str()?
             .replace("%0A", "");
         let cert = percent_decode(cert)?;
 
         Ok(RAResponse {
-            attestation_report: AttestationReport::new(body),
+            request_id,
+            attestation_report: AttestationReport::new(resp.into_bytes()),
             report_sig,
             cert,
         })
     }
 
-    /// Verify that
-    /// 1. TLS server certificate
-    /// 2. report's signature
-    /// 3. report's timestamp
-    /// 4. quote status
-    fn verify_attestation_report(self) -> Result<Self> {
-        let now_func = webpki::Time::try_from(SystemTime::now())?;
-
-        let mut ca_reader = BufReader::new(&IAS_REPORT_CA[..]);
-        let mut root_store = rustls::RootCertStore::empty();
-        root_store.add_pem_file(&mut ca_reader).expect("Failed to add CA");
-
-        let trust_anchors: Vec<webpki::TrustAnchor> = root_store
-            .roots
-            .iter()
-            .map(|cert| cert.to_trust_anchor())
-            .collect();
-
-        let ias_cert_dec = Self::decode_ias_report_ca()?;
-        let mut chain:Vec<&[u8]> = Vec::new();
-        chain.push(&ias_cert_dec);
-
-        let sig_cert = webpki::EndEntityCert::from(&self.cert)?;
-
-        sig_cert.verify_is_valid_tls_server_cert(
-            SUPPORTED_SIG_ALGS,
-            &webpki::TLSServerTrustAnchors(&trust_anchors),
-            &chain,
-            now_func,
-        )?;
-
-        sig_cert.verify_signature(
-            &webpki::RSA_PKCS1_2048_8192_SHA256,
-            &self.attestation_report.as_bytes(),
-            &self.report_sig.as_bytes(),
-        )?;
-
-        let attn_report = self.attestation_report.as_json()?;
-        self.verify_timestamp(&attn_report)?;
-        self.verify_quote_status(&attn_report)?;
-
-        Ok(self)
+    /// `Request-ID` of the response, which IAS support asks for
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_ref().map(|s| s.as_str())
     }
 
-
-    /// Verify report's timestamp is within 24H (90day is recommended by Intel)
-    fn verify_timestamp(&self, attn_report: &Value) -> Result<()> {
-        if let Value::String(time) = &attn_report["timestamp"] {
-            Ok(())
-            // TODO
-            // let time_fixed = time.clone() + "+0000";
-            // let ts = DateTime::parse_from_str(&time_fixed, "%Y-%m-%dT%H:%M:%S%.f%z").unwrap().timestamp();
-            // let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-            // ensure!(now - ts > 0, "")
-        } else {
-            bail!("Failed to fetch timestamp from attestation report");
-        }
+    fn verify_attestation_report(self, policy: VerificationPolicy) -> Result<VerifiedAttestation> {
+        verify_report(self.request_id, self.attestation_report, self.report_sig, self.cert, policy)
     }
+}
 
-    /// Verify the quote status included the attestation report is OK
-    fn verify_quote_status(&self, attn_report: &Value) -> Result<()> {
-        if let Value::String(quote_status) = &attn_report["isvEnclaveQuoteStatus"] {
-            match quote_status.as_ref() {
-                "OK" => Ok(()),
-                "GROUP_OUT_OF_DATE" => {
-                    println!("Enclave Quote Status: GROUP_OUT_OF_DATE");
-                    Ok(())
-                },
-                _ => bail!("Invalid Enclave Quote Status: {}", quote_status),
-            }
-        } else {
-            bail!("Failed to fetch isvEnclaveQuoteStatus from attestation report");
-        }
-    }
+fn request_id(resp: &Response) -> Option<String> {
+    resp.headers().get("Request-ID")
+        .and_then(|id| id.to_str().ok())
+        .map(ToString::to_string)
+}
 
-    fn decode_ias_report_ca() -> Result<Vec<u8>> {
-        let mut ias_ca_stripped = IAS_REPORT_CA.to_vec();
-        ias_ca_stripped.retain(|&x| x != 0x0d && x != 0x0a);
-        let head_len = "-----BEGIN CERTIFICATE-----".len();
-        let tail_len = "-----END CERTIFICATE-----".len();
+/// Verify that
+/// 1. TLS server certificate
+/// 2. report's signature
+/// 3. report's timestamp
+/// 4. quote status
+pub(crate) fn verify_report(
+    request_id: Option<String>,
+    report: AttestationReport,
+    report_sig: ReportSig,
+    cert: Vec<u8>,
+    policy: VerificationPolicy,
+) -> Result<VerifiedAttestation> {
+    let now_func = webpki::Time::try_from(SystemTime::now())?;
+
+    let mut ca_reader = BufReader::new(&IAS_REPORT_CA[..]);
+    let mut root_store = rustls::RootCertStore::empty();
+    root_store.add_pem_file(&mut ca_reader).expect("Failed to add CA");
+
+    let trust_anchors: Vec<webpki::TrustAnchor> = root_store
+        .roots
+        .iter()
+        .map(|cert| cert.to_trust_anchor())
+        .collect();
+
+    let ias_cert_dec = decode_ias_report_ca()?;
+    let mut chain:Vec<&[u8]> = Vec::new();
+    chain.push(&ias_cert_dec);
+
+    let sig_cert = webpki::EndEntityCert::from(&cert)?;
+
+    sig_cert.verify_is_valid_tls_server_cert(
+        SUPPORTED_SIG_ALGS,
+        &webpki::TLSServerTrustAnchors(&trust_anchors),
+        &chain,
+        now_func,
+    )?;
+
+    sig_cert.verify_signature(
+        &webpki::RSA_PKCS1_2048_8192_SHA256,
+        &report.as_bytes(),
+        &report_sig.as_bytes(),
+    )?;
+
+    let ias_report = IasReport::from_slice(report.as_bytes())?;
+    verify_timestamp(&ias_report)?;
+    let warnings = policy.check(&ias_report)?;
+    let quote_body = ias_report.quote_body()?;
+
+    Ok(VerifiedAttestation::new(
+        request_id,
+        report,
+        report_sig,
+        vec![cert, ias_cert_dec],
+        ias_report,
+        quote_body,
+        policy,
+        warnings,
+    ))
+}
 
-        let full_len = ias_ca_stripped.len();
-        let ias_ca_core : &[u8] = &ias_ca_stripped[head_len..full_len - tail_len];
-        let ias_cert_dec = base64::decode(ias_ca_core)?;
-        Ok(ias_cert_dec)
-    }
+/// Verify report's timestamp is within 24H (90day is recommended by Intel)
+fn verify_timestamp(ias_report: &IasReport) -> Result<()> {
+    ensure!(!ias_report.timestamp.is_empty(), "Failed to fetch timestamp from attestation report");
+    Ok(())
+    // TODO
+    // let time_fixed = ias_report.timestamp.clone() + "+0000";
+    // let ts = DateTime::parse_from_str(&time_fixed, "%Y-%m-%dT%H:%M:%S%.f%z").unwrap().timestamp();
+    // let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    // ensure!(now - ts > 0, "")
+}
+
+/// Verify the quote status included the attestation report is OK,
+/// or tolerated by the policy
+fn decode_ias_report_ca() -> Result<Vec<u8>> {
+    let mut ias_ca_stripped = IAS_REPORT_CA.to_vec();
+    ias_ca_stripped.retain(|&x| x != 0x0d && x != 0x0a);
+    let head_len = "-----BEGIN CERTIFICATE-----".len();
+    let tail_len = "-----END CERTIFICATE-----".len();
+
+    let full_len = ias_ca_stripped.len();
+    let ias_ca_core : &[u8] = &ias_ca_stripped[head_len..full_len - tail_len];
+    let ias_cert_dec = base64::decode(ias_ca_core)?;
+    Ok(ias_cert_dec)
 }
 
 /// A report returned from IAS
-#[derive(Debug, Clone, Default)]
-pub struct AttestationReport(Vec<u8>);
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationReport(#[serde(with = "base64_serde")] Vec<u8>);
 
 impl AttestationReport {
     pub fn new(report: Vec<u8>) -> Self {
@@ -242,8 +277,8 @@ impl AttestationReport {
 }
 
 /// Signature of the attestation report
-#[derive(Debug, Clone, Default)]
-pub struct ReportSig(Vec<u8>);
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportSig(#[serde(with = "base64_serde")] Vec<u8>);
 
 impl ReportSig {
     pub fn base64_decode(v: &[u8]) -> Result<Self> {
@@ -278,3 +313,79 @@ fn percent_decode(orig: String) -> Result<Vec<u8>> {
     let v: Vec<&str> = ret.split("-----").collect();
     base64::decode(v[2]).map_err(Into::into)
 }
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use client::{Certificate, test_server::{TestServer, CA_PEM}};
+    use test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_send_quote_to_ias,
+            test_reject_ias_error,
+            test_response_request_id,
+        )
+    }
+
+    const QUOTE: &str = "AgAAAMYLAAAHAAYAAAAAAA";
+
+    fn attest(server: &TestServer) -> Result<VerifiedAttestation> {
+        let client = Client::builder()
+            .add_root_certificate(Certificate::from_pem(CA_PEM).unwrap())
+            .build()
+            .unwrap();
+        let uri = format!("https://localhost:{}/sgx/dev/attestation/v4/report", server.port());
+        RAService::remote_attestation_with_client(
+            &client,
+            &uri,
+            TEST_SUB_KEY,
+            QUOTE,
+            VerificationPolicy::default(),
+        )
+    }
+
+    fn test_send_quote_to_ias() {
+        let server = TestServer::tls(vec![
+            b"HTTP/1.1 200 OK\r\nRequest-ID: 1\r\nContent-Length: 2\r\n\r\n{}".to_vec(),
+        ]);
+        let err = attest(&server).unwrap_err();
+        assert_eq!(err.to_string(), "Not found X-IASReport-Signature header");
+
+        let requests = server.requests().unwrap();
+        let request = str::from_utf8(&requests[0]).unwrap();
+        let (head, body) = request.split_at(request.find("\r\n\r\n").unwrap() + 4);
+        assert!(head.starts_with("POST /sgx/dev/attestation/v4/report HTTP/1.1\r\n"), "{}", head);
+        let head = head.to_lowercase();
+        assert!(head.contains(&format!("\r\nocp-apim-subscription-key: {}\r\n", TEST_SUB_KEY)), "{}", head);
+        assert!(head.contains("\r\ncontent-type: application/json\r\n"), "{}", head);
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body, json!({ "isvEnclaveQuote": QUOTE }));
+    }
+
+    fn test_reject_ias_error() {
+        let server = TestServer::tls(vec![
+            b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n".to_vec(),
+        ]);
+        let err = attest(&server).unwrap_err();
+        assert_eq!(err.to_string(), "IAS responded with 401 Unauthorized");
+        assert_eq!(server.requests().unwrap().len(), 1);
+    }
+
+    fn test_response_request_id() {
+        let server = TestServer::tls(vec![
+            b"HTTP/1.1 200 OK\r\n\
+              Request-ID: 6bc5d1bd7cbd4c5e8a8b8b5b2e1ac0c1\r\n\
+              X-IASReport-Signature: q6s=\r\n\
+              X-IASReport-Signing-Certificate: -----BEGIN%20CERTIFICATE-----%0AMIIB%0A-----END%20CERTIFICATE-----%0A\r\n\
+              Content-Length: 2\r\n\r\n{}".to_vec(),
+        ]);
+        let client = Client::builder()
+            .add_root_certificate(Certificate::from_pem(CA_PEM).unwrap())
+            .build()
+            .unwrap();
+        let uri = format!("https://localhost:{}/sgx/dev/attestation/v4/report", server.port());
+        let response = RAResponse::from_response(client.post(&uri).send().unwrap()).unwrap();
+        assert_eq!(response.request_id(), Some("6bc5d1bd7cbd4c5e8a8b8b5b2e1ac0c1"));
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    prelude::v1::*,
+    convert::{TryFrom, TryInto},
+};
+use anyhow::{Error, Result, bail, ensure};
+use serde::{Serialize, Deserialize};
+use crate::client::{AttestationReport, ReportSig};
+
+/// The current version of `EvidenceBundle` encodings
+pub const EVIDENCE_BUNDLE_VERSION: u16 = 1;
+
+/// Attestation evidence which can be shipped between nodes and verified again.
+///
+/// The binary encoding is canonical: a bundle has exactly one encoding, so it
+/// can be hashed or stored on-chain. All integers are little endian.
+///
+/// ```text
+/// version: u16
+/// report: u32 length || bytes
+/// report_sig: u32 length || bytes
+/// cert_chain: u32 count || (u32 length || bytes)*
+/// collateral: u8 0 | u8 1 || u32 length || bytes
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedEvidenceBundle")]
+pub struct EvidenceBundle {
+    version: u16,
+    report: AttestationReport,
+    report_sig: ReportSig,
+    #[serde(with = "base64_serde::vec")]
+    cert_chain: Vec<Vec<u8>>,
+    #[serde(with = "base64_serde::option", default)]
+    collateral: Option<Vec<u8>>,
+}
+
+impl EvidenceBundle {
+    pub fn new(report: AttestationReport, report_sig: ReportSig, cert_chain: Vec<Vec<u8>>) -> Self {
+        EvidenceBundle {
+            version: EVIDENCE_BUNDLE_VERSION,
+            report,
+            report_sig,
+            cert_chain,
+            collateral: None,
+        }
+    }
+
+    /// Attaches collateral needed to verify the evidence, e.g. DCAP TCB info.
+    pub fn with_collateral(mut self, collateral: Vec<u8>) -> Self {
+        self.collateral = Some(collateral);
+        self
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn report(&self) -> &AttestationReport {
+        &self.report
+    }
+
+    pub fn report_sig(&self) -> &ReportSig {
+        &self.report_sig
+    }
+
+    /// DER-encoded certificates, from the report signing certificate to the root CA
+    pub fn cert_chain(&self) -> &[Vec<u8>] {
+        &self.cert_chain[..]
+    }
+
+    pub fn collateral(&self) -> Option<&[u8]> {
+        self.collateral.as_ref().map(|c| &c[..])
+    }
+
+    /// Encodes the bundle in the canonical binary format.
+    ///
+    /// Fails if a field or the certificate chain is too long for its
+    /// `u32` length.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.version.to_le_bytes());
+        write_bytes(&mut buf, self.report.as_bytes())?;
+        write_bytes(&mut buf, self.report_sig.as_bytes())?;
+        buf.extend_from_slice(&u32::try_from(self.cert_chain.len())?.to_le_bytes());
+        for cert in &self.cert_chain {
+            write_bytes(&mut buf, cert)?;
+        }
+        match self.collateral {
+            Some(ref collateral) => {
+                buf.push(1);
+                write_bytes(&mut buf, collateral)?;
+            }
+            None => buf.push(0),
+        }
+
+        Ok(buf)
+    }
+
+    /// Decodes a bundle from the canonical binary format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        let version = u16::from_le_bytes(reader.take(2)?.try_into()?);
+        check_version(version)?;
+
+        let report = AttestationReport::new(reader.take_bytes()?.to_vec());
+        let report_sig = ReportSig::new(reader.take_bytes()?.to_vec());
+        let count = reader.take_u32()? as usize;
+        // Every certificate takes at least its 4-byte length.
+        ensure!(count <= reader.0.len() / 4, "Invalid certificate count: {}", count);
+        let mut cert_chain = Vec::with_capacity(count);
+        for _ in 0..count {
+            cert_chain.push(reader.take_bytes()?.to_vec());
+        }
+        let collateral = match reader.take(1)?[0] {
+            0 => None,
+            1 => Some(reader.take_bytes()?.to_vec()),
+            flag => bail!("Invalid collateral flag: {}", flag),
+        };
+        ensure!(reader.0.is_empty(), "Trailing bytes after evidence bundle");
+
+        Ok(EvidenceBundle {
+            version,
+            report,
+            report_sig,
+            cert_chain,
+            collateral,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Into::into)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(Into::into)
+    }
+}
+
+/// The fields of a bundle as deserialized, before the version is checked
+#[derive(Deserialize)]
+struct UncheckedEvidenceBundle {
+    version: u16,
+    report: AttestationReport,
+    report_sig: ReportSig,
+    #[serde(with = "base64_serde::vec")]
+    cert_chain: Vec<Vec<u8>>,
+    #[serde(with = "base64_serde::option", default)]
+    collateral: Option<Vec<u8>>,
+}
+
+impl TryFrom<UncheckedEvidenceBundle> for EvidenceBundle {
+    type Error = Error;
+
+    fn try_from(bundle: UncheckedEvidenceBundle) -> Result<Self> {
+        check_version(bundle.version)?;
+        Ok(EvidenceBundle {
+            version: bundle.version,
+            report: bundle.report,
+            report_sig: bundle.report_sig,
+            cert_chain: bundle.cert_chain,
+            collateral: bundle.collateral,
+        })
+    }
+}
+
+fn check_version(version: u16) -> Result<()> {
+    ensure!(version == EVIDENCE_BUNDLE_VERSION, "Unsupported evidence bundle version: {}", version);
+    Ok(())
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+    buf.extend_from_slice(&u32::try_from(bytes.len())?.to_le_bytes());
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.0.len() >= len, "Unexpected end of evidence bundle");
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn take_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn take_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.take_u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// Serializes bytes as base64 strings in human-readable formats such as JSON,
+/// and as raw bytes otherwise.
+pub(crate) mod base64_serde {
+    use std::{
+        prelude::v1::*,
+        fmt,
+    };
+    use serde::{Serialize, Deserialize, Serializer, Deserializer, de};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&base64::encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Base64Visitor)
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    struct Base64Visitor;
+
+    impl<'de> de::Visitor<'de> for Base64Visitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a base64 string")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+            base64::decode(v).map_err(E::custom)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("bytes")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(b) = seq.next_element()? {
+                v.push(b);
+            }
+            Ok(v)
+        }
+    }
+
+    struct BytesRef<'a>(&'a [u8]);
+
+    impl Serialize for BytesRef<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(self.0, serializer)
+        }
+    }
+
+    struct ByteBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(ByteBuf)
+        }
+    }
+
+    pub mod vec {
+        use std::prelude::v1::*;
+        use serde::{Deserialize, Serializer, Deserializer};
+        use super::{BytesRef, ByteBuf};
+
+        pub fn serialize<S: Serializer>(v: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(v.iter().map(|b| BytesRef(b)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+            let v: Vec<ByteBuf> = Deserialize::deserialize(deserializer)?;
+            Ok(v.into_iter().map(|b| b.0).collect())
+        }
+    }
+
+    pub mod option {
+        use std::prelude::v1::*;
+        use serde::{Serialize, Deserialize, Serializer, Deserializer};
+        use super::{BytesRef, ByteBuf};
+
+        pub fn serialize<S: Serializer>(v: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+            v.as_ref().map(|b| BytesRef(b)).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+            let v: Option<ByteBuf> = Deserialize::deserialize(deserializer)?;
+            Ok(v.map(|b| b.0))
+        }
+    }
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use test_utils::*;
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_binary_round_trip,
+            test_json_round_trip,
+            test_golden_bytes,
+            test_golden_json,
+            test_reject_unsupported_version,
+            test_reject_non_canonical_encoding,
+        )
+    }
+
+    fn sample_bundle() -> EvidenceBundle {
+        EvidenceBundle::new(
+            AttestationReport::new(br#"{"id":"1","isvEnclaveQuoteStatus":"OK"}"#.to_vec()),
+            ReportSig::new(vec![0xab; 256]),
+            vec![vec![0x30, 0x82, 0x01], vec![0x30, 0x82, 0x02, 0x03]],
+        )
+    }
+
+    fn test_binary_round_trip() {
+        let bundle = sample_bundle();
+        let decoded = EvidenceBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, bundle);
+
+        let bundle = sample_bundle().with_collateral(b"collateral".to_vec());
+        let decoded = EvidenceBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, bundle);
+        assert_eq!(decoded.to_bytes().unwrap(), bundle.to_bytes().unwrap());
+    }
+
+    fn test_json_round_trip() {
+        let bundle = sample_bundle().with_collateral(b"collateral".to_vec());
+        let json = bundle.to_json().unwrap();
+        assert_eq!(EvidenceBundle::from_json(&json).unwrap(), bundle);
+
+        let bundle = sample_bundle();
+        let json = bundle.to_json().unwrap();
+        assert_eq!(EvidenceBundle::from_json(&json).unwrap(), bundle);
+    }
+
+    /// A bundle whose encodings are pinned, so any change to them is caught
+    fn golden_bundle() -> EvidenceBundle {
+        EvidenceBundle::new(
+            AttestationReport::new(br#"{"id":"1"}"#.to_vec()),
+            ReportSig::new(vec![0xab, 0xcd]),
+            vec![vec![0x30, 0x82, 0x01]],
+        )
+        .with_collateral(b"tcb".to_vec())
+    }
+
+    const GOLDEN_JSON: &str =
+        r#"{"version":1,"report":"eyJpZCI6IjEifQ==","report_sig":"q80=","cert_chain":["MIIB"],"collateral":"dGNi"}"#;
+
+    fn test_golden_bytes() {
+        let mut expected = vec![0x01, 0x00];
+        expected.extend_from_slice(&[0x0a, 0x00, 0x00, 0x00]);
+        expected.extend_from_slice(br#"{"id":"1"}"#);
+        expected.extend_from_slice(&[0x02, 0x00, 0x00, 0x00, 0xab, 0xcd]);
+        expected.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+        expected.extend_from_slice(&[0x03, 0x00, 0x00, 0x00, 0x30, 0x82, 0x01]);
+        expected.extend_from_slice(&[0x01, 0x03, 0x00, 0x00, 0x00]);
+        expected.extend_from_slice(b"tcb");
+
+        assert_eq!(golden_bundle().to_bytes().unwrap(), expected);
+        assert_eq!(EvidenceBundle::from_bytes(&expected).unwrap(), golden_bundle());
+    }
+
+    fn test_golden_json() {
+        assert_eq!(golden_bundle().to_json().unwrap(), GOLDEN_JSON);
+        assert_eq!(EvidenceBundle::from_json(GOLDEN_JSON).unwrap(), golden_bundle());
+    }
+
+    fn test_reject_unsupported_version() {
+        let json = GOLDEN_JSON.replace(r#""version":1"#, r#""version":2"#);
+        let err = EvidenceBundle::from_json(&json).unwrap_err();
+        assert!(err.to_string().contains("Unsupported evidence bundle version: 2"), "{}", err);
+        // Deserializing directly doesn't bypass the check.
+        assert!(serde_json::from_str::<EvidenceBundle>(&json).is_err());
+    }
+
+    fn test_reject_non_canonical_encoding() {
+        let mut bytes = sample_bundle().to_bytes().unwrap();
+        bytes.push(0);
+        assert!(EvidenceBundle::from_bytes(&bytes).is_err());
+
+        let mut bytes = sample_bundle().to_bytes().unwrap();
+        *bytes.last_mut().unwrap() = 2;
+        assert!(EvidenceBundle::from_bytes(&bytes).is_err());
+
+        let mut bytes = sample_bundle().to_bytes().unwrap();
+        bytes[0] = 0xff;
+        assert!(EvidenceBundle::from_bytes(&bytes).is_err());
+
+        let bytes = sample_bundle().to_bytes().unwrap();
+        assert!(EvidenceBundle::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -3,6 +3,31 @@
 #[macro_use]
 extern crate sgx_tstd as std;
 
+mod audit;
 mod client;
+mod evidence;
+mod report;
+mod verified;
 
+pub use crate::audit::{AuditHook, AuditEvent, set_audit_hook};
 pub use crate::client::{RAService, AttestationReport, ReportSig};
+pub use crate::evidence::{EvidenceBundle, EVIDENCE_BUNDLE_VERSION};
+pub use crate::report::{IasReport, QuoteStatus, QuoteBody, EnclaveReportBody};
+pub use crate::verified::{VerifiedAttestation, VerificationPolicy, AttestationWarning, IasVerifier};
+pub use attestation::{AttestationVerifier, VerifiedEnclave, TcbStatus};
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use std::prelude::v1::*;
+    use test_utils::check_all_passed;
+
+    /// Runs the tests of this crate inside an enclave.
+    pub fn run_tests() -> bool {
+        check_all_passed!(
+            crate::audit::tests::run_tests(),
+            crate::client::tests::run_tests(),
+            crate::evidence::tests::run_tests(),
+            crate::verified::tests::run_tests(),
+        )
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    prelude::v1::*,
+    fmt,
+    convert::TryInto,
+};
+use anyhow::{Result, ensure};
+use serde::{Serialize, Deserialize};
+use attestation::TcbStatus;
+
+/// Length of `isvEnclaveQuoteBody`, which is `sgx_quote_t` without the signature.
+pub const QUOTE_BODY_LEN: usize = 432;
+/// Length of `sgx_report_body_t`
+pub const REPORT_BODY_LEN: usize = 384;
+
+/// The contents of an attestation verification report returned from IAS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IasReport {
+    pub id: String,
+    pub timestamp: String,
+    pub version: u64,
+    pub isv_enclave_quote_status: String,
+    pub isv_enclave_quote_body: String,
+    #[serde(default)]
+    pub revocation_reason: Option<u64>,
+    #[serde(default)]
+    pub pse_manifest_status: Option<String>,
+    #[serde(default)]
+    pub pse_manifest_hash: Option<String>,
+    #[serde(default)]
+    pub platform_info_blob: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub epid_pseudonym: Option<String>,
+    #[serde(rename = "advisoryURL", default)]
+    pub advisory_url: Option<String>,
+    #[serde(rename = "advisoryIDs", default)]
+    pub advisory_ids: Vec<String>,
+}
+
+impl IasReport {
+    pub fn from_slice(report: &[u8]) -> Result<Self> {
+        serde_json::from_slice(report).map_err(Into::into)
+    }
+
+    pub fn quote_status(&self) -> QuoteStatus {
+        QuoteStatus::from(self.isv_enclave_quote_status.as_str())
+    }
+
+    pub fn quote_body(&self) -> Result<QuoteBody> {
+        let body = base64::decode(&self.isv_enclave_quote_body)?;
+        QuoteBody::from_bytes(&body)
+    }
+}
+
+/// `isvEnclaveQuoteStatus` in the attestation verification report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteStatus {
+    Ok,
+    SignatureInvalid,
+    GroupRevoked,
+    SignatureRevoked,
+    KeyRevoked,
+    SigrlVersionMismatch,
+    GroupOutOfDate,
+    ConfigurationNeeded,
+    SwHardeningNeeded,
+    ConfigurationAndSwHardeningNeeded,
+    Unknown(String),
+}
+
+impl QuoteStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            QuoteStatus::Ok => "OK",
+            QuoteStatus::SignatureInvalid => "SIGNATURE_INVALID",
+            QuoteStatus::GroupRevoked => "GROUP_REVOKED",
+            QuoteStatus::SignatureRevoked => "SIGNATURE_REVOKED",
+            QuoteStatus::KeyRevoked => "KEY_REVOKED",
+            QuoteStatus::SigrlVersionMismatch => "SIGRL_VERSION_MISMATCH",
+            QuoteStatus::GroupOutOfDate => "GROUP_OUT_OF_DATE",
+            QuoteStatus::ConfigurationNeeded => "CONFIGURATION_NEEDED",
+            QuoteStatus::SwHardeningNeeded => "SW_HARDENING_NEEDED",
+            QuoteStatus::ConfigurationAndSwHardeningNeeded => "CONFIGURATION_AND_SW_HARDENING_NEEDED",
+            QuoteStatus::Unknown(s) => s,
+        }
+    }
+
+    /// The TCB status the quote status stands for. Statuses which don't
+    /// describe the TCB, e.g. `SIGNATURE_INVALID`, are `Unknown`.
+    pub fn tcb_status(&self) -> TcbStatus {
+        match self {
+            QuoteStatus::Ok => TcbStatus::UpToDate,
+            QuoteStatus::GroupOutOfDate => TcbStatus::OutOfDate,
+            QuoteStatus::ConfigurationNeeded => TcbStatus::ConfigurationNeeded,
+            QuoteStatus::SwHardeningNeeded => TcbStatus::SwHardeningNeeded,
+            QuoteStatus::ConfigurationAndSwHardeningNeeded => TcbStatus::ConfigurationAndSwHardeningNeeded,
+            QuoteStatus::GroupRevoked
+            | QuoteStatus::SignatureRevoked
+            | QuoteStatus::KeyRevoked => TcbStatus::Revoked,
+            status => TcbStatus::Unknown(status.as_str().to_string()),
+        }
+    }
+}
+
+impl From<&str> for QuoteStatus {
+    fn from(s: &str) -> Self {
+        match s {
+            "OK" => QuoteStatus::Ok,
+            "SIGNATURE_INVALID" => QuoteStatus::SignatureInvalid,
+            "GROUP_REVOKED" => QuoteStatus::GroupRevoked,
+            "SIGNATURE_REVOKED" => QuoteStatus::SignatureRevoked,
+            "KEY_REVOKED" => QuoteStatus::KeyRevoked,
+            "SIGRL_VERSION_MISMATCH" => QuoteStatus::SigrlVersionMismatch,
+            "GROUP_OUT_OF_DATE" => QuoteStatus::GroupOutOfDate,
+            "CONFIGURATION_NEEDED" => QuoteStatus::ConfigurationNeeded,
+            "SW_HARDENING_NEEDED" => QuoteStatus::SwHardeningNeeded,
+            "CONFIGURATION_AND_SW_HARDENING_NEEDED" => QuoteStatus::ConfigurationAndSwHardeningNeeded,
+            _ => QuoteStatus::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for QuoteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The quote body (`sgx_quote_t` without the signature) included in the report
+#[derive(Debug, Clone)]
+pub struct QuoteBody {
+    pub version: u16,
+    pub sign_type: u16,
+    pub epid_group_id: [u8; 4],
+    pub qe_svn: u16,
+    pub pce_svn: u16,
+    pub xeid: u32,
+    pub basename: [u8; 32],
+    pub report_body: EnclaveReportBody,
+}
+
+impl QuoteBody {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() >= QUOTE_BODY_LEN, "Invalid quote body length: {}", bytes.len());
+
+        Ok(QuoteBody {
+            version: u16::from_le_bytes(bytes[0..2].try_into()?),
+            sign_type: u16::from_le_bytes(bytes[2..4].try_into()?),
+            epid_group_id: bytes[4..8].try_into()?,
+            qe_svn: u16::from_le_bytes(bytes[8..10].try_into()?),
+            pce_svn: u16::from_le_bytes(bytes[10..12].try_into()?),
+            xeid: u32::from_le_bytes(bytes[12..16].try_into()?),
+            basename: bytes[16..48].try_into()?,
+            report_body: EnclaveReportBody::from_bytes(&bytes[48..QUOTE_BODY_LEN])?,
+        })
+    }
+}
+
+/// The enclave's report body (`sgx_report_body_t`)
+#[derive(Clone)]
+pub struct EnclaveReportBody {
+    pub cpu_svn: [u8; 16],
+    pub misc_select: u32,
+    pub isv_ext_prod_id: [u8; 16],
+    pub attributes: [u8; 16],
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub config_id: [u8; 64],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub config_svn: u16,
+    pub isv_family_id: [u8; 16],
+    pub report_data: [u8; 64],
+}
+
+impl EnclaveReportBody {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() == REPORT_BODY_LEN, "Invalid report body length: {}", bytes.len());
+
+        let mut config_id = [0u8; 64];
+        config_id.copy_from_slice(&bytes[192..256]);
+        let mut report_data = [0u8; 64];
+        report_data.copy_from_slice(&bytes[320..384]);
+
+        Ok(EnclaveReportBody {
+            cpu_svn: bytes[0..16].try_into()?,
+            misc_select: u32::from_le_bytes(bytes[16..20].try_into()?),
+            isv_ext_prod_id: bytes[32..48].try_into()?,
+            attributes: bytes[48..64].try_into()?,
+            mr_enclave: bytes[64..96].try_into()?,
+            mr_signer: bytes[128..160].try_into()?,
+            config_id,
+            isv_prod_id: u16::from_le_bytes(bytes[256..258].try_into()?),
+            isv_svn: u16::from_le_bytes(bytes[258..260].try_into()?),
+            config_svn: u16::from_le_bytes(bytes[260..262].try_into()?),
+            isv_family_id: bytes[304..320].try_into()?,
+            report_data,
+        })
+    }
+}
+
+// Arrays longer than 32 don't implement `Debug` in the SGX toolchain.
+impl fmt::Debug for EnclaveReportBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EnclaveReportBody")
+            .field("cpu_svn", &self.cpu_svn)
+            .field("misc_select", &self.misc_select)
+            .field("isv_ext_prod_id", &self.isv_ext_prod_id)
+            .field("attributes", &self.attributes)
+            .field("mr_enclave", &self.mr_enclave)
+            .field("mr_signer", &self.mr_signer)
+            .field("config_id", &&self.config_id[..])
+            .field("isv_prod_id", &self.isv_prod_id)
+            .field("isv_svn", &self.isv_svn)
+            .field("config_svn", &self.config_svn)
+            .field("isv_family_id", &self.isv_family_id)
+            .field("report_data", &&self.report_data[..])
+            .finish()
+    }
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// An attestation verification report as IAS returns it, for tests
+    /// which don't need the optional fields.
+    pub fn report_json(quote_status: &str, quote_body: &[u8], advisory_ids: &[&str]) -> String {
+        let mut report = json!({
+            "id": "1",
+            "timestamp": "2020-01-01T00:00:00.000000",
+            "version": 4,
+            "isvEnclaveQuoteStatus": quote_status,
+            "isvEnclaveQuoteBody": base64::encode(quote_body),
+        });
+        if !advisory_ids.is_empty() {
+            report["advisoryIDs"] = json!(advisory_ids);
+        }
+        report.to_string()
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    prelude::v1::*,
+    fmt,
+};
+use anyhow::{Error, Result, anyhow, bail};
+use attestation::{AttestationVerifier, TcbStatus, VerifiedEnclave};
+use crate::client::{AttestationReport, ReportSig, verify_report};
+use crate::report::{IasReport, QuoteBody, QuoteStatus};
+use crate::evidence::EvidenceBundle;
+
+/// A policy which decides the verdict on an attestation verification report
+#[derive(Debug, Clone)]
+pub struct VerificationPolicy {
+    tolerated_statuses: Vec<QuoteStatus>,
+}
+
+impl Default for VerificationPolicy {
+    /// Accepts `OK`, and tolerates `GROUP_OUT_OF_DATE` with a warning.
+    fn default() -> Self {
+        VerificationPolicy {
+            tolerated_statuses: vec![QuoteStatus::GroupOutOfDate],
+        }
+    }
+}
+
+impl VerificationPolicy {
+    /// A policy which accepts only `OK`.
+    pub fn strict() -> Self {
+        VerificationPolicy {
+            tolerated_statuses: vec![],
+        }
+    }
+
+    /// Tolerates the quote status other than `OK` with a warning.
+    ///
+    /// `SIGNATURE_INVALID` and the revoked statuses mean the quote can't be
+    /// trusted at all, so they are never tolerated.
+    pub fn tolerate(mut self, status: QuoteStatus) -> Self {
+        let untolerable = match status {
+            QuoteStatus::SignatureInvalid => true,
+            ref status => status.tcb_status() == TcbStatus::Revoked,
+        };
+        if !untolerable && !self.is_tolerated(&status) {
+            self.tolerated_statuses.push(status);
+        }
+        self
+    }
+
+    pub fn is_tolerated(&self, status: &QuoteStatus) -> bool {
+        self.tolerated_statuses.contains(status)
+    }
+
+    pub fn tolerated_statuses(&self) -> &[QuoteStatus] {
+        &self.tolerated_statuses[..]
+    }
+
+    /// Accepts the quote status of the report, with a warning if it is only
+    /// tolerated.
+    pub(crate) fn check(&self, ias_report: &IasReport) -> Result<Vec<AttestationWarning>> {
+        match ias_report.quote_status() {
+            QuoteStatus::Ok => Ok(vec![]),
+            status if self.is_tolerated(&status) => {
+                Ok(vec![AttestationWarning::ToleratedQuoteStatus {
+                    status,
+                    advisory_ids: ias_report.advisory_ids.clone(),
+                }])
+            },
+            status => bail!("Invalid Enclave Quote Status: {}", status),
+        }
+    }
+}
+
+/// A condition which didn't fail the verification but should be noted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttestationWarning {
+    /// The quote status is not `OK` but tolerated by the policy.
+    ToleratedQuoteStatus {
+        status: QuoteStatus,
+        advisory_ids: Vec<String>,
+    },
+}
+
+impl fmt::Display for AttestationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttestationWarning::ToleratedQuoteStatus { status, advisory_ids } => {
+                write!(f, "Tolerated enclave quote status: {}", status)?;
+                if !advisory_ids.is_empty() {
+                    write!(f, " (advisories: {})", advisory_ids.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of a successful remote attestation with IAS, carrying the
+/// evidence and what was checked to accept it.
+#[derive(Debug, Clone)]
+pub struct VerifiedAttestation {
+    request_id: Option<String>,
+    report: AttestationReport,
+    report_sig: ReportSig,
+    cert_chain: Vec<Vec<u8>>,
+    ias_report: IasReport,
+    quote_body: QuoteBody,
+    policy: VerificationPolicy,
+    warnings: Vec<AttestationWarning>,
+}
+
+impl VerifiedAttestation {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        request_id: Option<String>,
+        report: AttestationReport,
+        report_sig: ReportSig,
+        cert_chain: Vec<Vec<u8>>,
+        ias_report: IasReport,
+        quote_body: QuoteBody,
+        policy: VerificationPolicy,
+        warnings: Vec<AttestationWarning>,
+    ) -> Self {
+        VerifiedAttestation {
+            request_id,
+            report,
+            report_sig,
+            cert_chain,
+            ias_report,
+            quote_body,
+            policy,
+            warnings,
+        }
+    }
+
+    /// `Request-ID` of the response from IAS
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_ref().map(|s| s.as_str())
+    }
+
+    /// The raw attestation verification report
+    pub fn report(&self) -> &AttestationReport {
+        &self.report
+    }
+
+    pub fn report_sig(&self) -> &ReportSig {
+        &self.report_sig
+    }
+
+    /// DER-encoded certificates, from the report signing certificate to the root CA
+    pub fn cert_chain(&self) -> &[Vec<u8>] {
+        &self.cert_chain[..]
+    }
+
+    pub fn ias_report(&self) -> &IasReport {
+        &self.ias_report
+    }
+
+    pub fn quote_body(&self) -> &QuoteBody {
+        &self.quote_body
+    }
+
+    pub fn quote_status(&self) -> QuoteStatus {
+        self.ias_report.quote_status()
+    }
+
+    pub fn policy(&self) -> &VerificationPolicy {
+        &self.policy
+    }
+
+    pub fn warnings(&self) -> &[AttestationWarning] {
+        &self.warnings[..]
+    }
+
+    /// Packs the evidence so that it can be shipped to and verified by other nodes.
+    pub fn evidence_bundle(&self) -> EvidenceBundle {
+        EvidenceBundle::new(self.report.clone(), self.report_sig.clone(), self.cert_chain.clone())
+    }
+
+    pub fn into_report_and_sig(self) -> (AttestationReport, ReportSig) {
+        (self.report, self.report_sig)
+    }
+
+    /// The attested enclave, in the form shared with DCAP
+    pub fn enclave(&self) -> VerifiedEnclave {
+        let body = &self.quote_body.report_body;
+        VerifiedEnclave {
+            mr_enclave: body.mr_enclave,
+            mr_signer: body.mr_signer,
+            isv_prod_id: body.isv_prod_id,
+            isv_svn: body.isv_svn,
+            attributes: body.attributes,
+            report_data: body.report_data,
+            tcb_status: self.quote_status().tcb_status(),
+            advisory_ids: self.ias_report.advisory_ids.clone(),
+        }
+    }
+}
+
+/// Verifies evidence bundles received from other nodes against the IAS
+/// report signing CA
+#[derive(Debug, Clone, Default)]
+pub struct IasVerifier {
+    policy: VerificationPolicy,
+}
+
+impl IasVerifier {
+    pub fn new(policy: VerificationPolicy) -> Self {
+        IasVerifier { policy }
+    }
+
+    /// Verifies the bundle, keeping the evidence.
+    pub fn verify_bundle(&self, bundle: &EvidenceBundle) -> Result<VerifiedAttestation> {
+        let cert = bundle.cert_chain().first()
+            .ok_or_else(|| anyhow!("Not found report signing certificate in evidence bundle"))?;
+        verify_report(None, bundle.report().clone(), bundle.report_sig().clone(), cert.clone(), self.policy.clone())
+    }
+}
+
+impl AttestationVerifier for IasVerifier {
+    type Evidence = EvidenceBundle;
+    type Error = Error;
+
+    fn verify(&self, bundle: &EvidenceBundle) -> Result<VerifiedEnclave> {
+        self.verify_bundle(bundle).map(|verified| verified.enclave())
+    }
+}
+
+#[cfg(feature = "enclave-test")]
+pub mod tests {
+    use super::*;
+    use test_utils::*;
+    use crate::report::{QUOTE_BODY_LEN, tests::report_json};
+
+    pub fn run_tests() -> bool {
+        run_tests!(
+            test_default_policy,
+            test_strict_policy,
+            test_display_warning,
+            test_check_quote_status,
+            test_reject_untrusted_quote_status,
+            test_verified_attestation,
+            test_tcb_status_of_quote_status,
+            test_reject_bundle_without_signing_cert,
+        )
+    }
+
+    fn test_default_policy() {
+        let policy = VerificationPolicy::default();
+        assert!(policy.is_tolerated(&QuoteStatus::GroupOutOfDate));
+        assert!(!policy.is_tolerated(&QuoteStatus::GroupRevoked));
+
+        let policy = policy
+            .tolerate(QuoteStatus::SwHardeningNeeded)
+            .tolerate(QuoteStatus::SwHardeningNeeded);
+        assert_eq!(
+            policy.tolerated_statuses(),
+            &[QuoteStatus::GroupOutOfDate, QuoteStatus::SwHardeningNeeded][..],
+        );
+    }
+
+    fn test_strict_policy() {
+        let policy = VerificationPolicy::strict();
+        assert!(policy.tolerated_statuses().is_empty());
+        assert!(!policy.is_tolerated(&QuoteStatus::GroupOutOfDate));
+    }
+
+    fn test_display_warning() {
+        let warning = AttestationWarning::ToleratedQuoteStatus {
+            status: QuoteStatus::GroupOutOfDate,
+            advisory_ids: vec!["INTEL-SA-00334".to_string(), "INTEL-SA-00219".to_string()],
+        };
+        assert_eq!(
+            warning.to_string(),
+            "Tolerated enclave quote status: GROUP_OUT_OF_DATE (advisories: INTEL-SA-00334, INTEL-SA-00219)",
+        );
+
+        let warning = AttestationWarning::ToleratedQuoteStatus {
+            status: QuoteStatus::SwHardeningNeeded,
+            advisory_ids: vec![],
+        };
+        assert_eq!(warning.to_string(), "Tolerated enclave quote status: SW_HARDENING_NEEDED");
+    }
+
+    fn test_check_quote_status() {
+        let report = |status| IasReport::from_slice(report_json(status, &[], &["INTEL-SA-00334"]).as_bytes()).unwrap();
+        let policy = VerificationPolicy::default();
+
+        assert_eq!(policy.check(&report("OK")).unwrap(), vec![]);
+        assert_eq!(policy.check(&report("GROUP_OUT_OF_DATE")).unwrap(), vec![
+            AttestationWarning::ToleratedQuoteStatus {
+                status: QuoteStatus::GroupOutOfDate,
+                advisory_ids: vec!["INTEL-SA-00334".to_string()],
+            },
+        ]);
+        assert_eq!(
+            policy.check(&report("SW_HARDENING_NEEDED")).unwrap_err().to_string(),
+            "Invalid Enclave Quote Status: SW_HARDENING_NEEDED",
+        );
+        assert!(VerificationPolicy::strict().check(&report("GROUP_OUT_OF_DATE")).is_err());
+    }
+
+    fn test_reject_untrusted_quote_status() {
+        let report = |status| IasReport::from_slice(report_json(status, &[], &["INTEL-SA-00334"]).as_bytes()).unwrap();
+        let policy = VerificationPolicy::default()
+            .tolerate(QuoteStatus::SignatureInvalid)
+            .tolerate(QuoteStatus::GroupRevoked)
+            .tolerate(QuoteStatus::KeyRevoked);
+        assert_eq!(policy.tolerated_statuses(), &[QuoteStatus::GroupOutOfDate][..]);
+
+        assert_eq!(
+            policy.check(&report("SIGNATURE_INVALID")).unwrap_err().to_string(),
+            "Invalid Enclave Quote Status: SIGNATURE_INVALID",
+        );
+        assert_eq!(
+            policy.check(&report("GROUP_REVOKED")).unwrap_err().to_string(),
+            "Invalid Enclave Quote Status: GROUP_REVOKED",
+        );
+    }
+
+    /// Fields of the quote body are decoded from their offsets in
+    /// `sgx_quote_t`, whose report body starts at 48.
+    fn test_verified_attestation() {
+        let mut quote_body = vec![0u8; QUOTE_BODY_LEN];
+        quote_body[0..2].copy_from_slice(&2u16.to_le_bytes());
+        quote_body[4..8].copy_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd]);
+        quote_body[48 + 48..48 + 64].copy_from_slice(&[0x07; 16]);
+        quote_body[48 + 64..48 + 96].copy_from_slice(&[0x11; 32]);
+        quote_body[48 + 128..48 + 160].copy_from_slice(&[0x22; 32]);
+        quote_body[48 + 256..48 + 258].copy_from_slice(&5u16.to_le_bytes());
+        quote_body[48 + 258..48 + 260].copy_from_slice(&3u16.to_le_bytes());
+        quote_body[48 + 320..48 + 384].copy_from_slice(&[0x33; 64]);
+        let report = report_json("GROUP_OUT_OF_DATE", &quote_body, &["INTEL-SA-00334"]);
+        let ias_report = IasReport::from_slice(report.as_bytes()).unwrap();
+        let warnings = VerificationPolicy::default().check(&ias_report).unwrap();
+        let verified = VerifiedAttestation::new(
+            Some("6bc5d1bd7cbd4c5e8a8b8b5b2e1ac0c1".to_string()),
+            AttestationReport::new(report.clone().into_bytes()),
+            ReportSig::new(vec![0xab; 256]),
+            vec![vec![0x30, 0x82, 0x01]],
+            ias_report.clone(),
+            ias_report.quote_body().unwrap(),
+            VerificationPolicy::default(),
+            warnings.clone(),
+        );
+
+        assert_eq!(verified.request_id(), Some("6bc5d1bd7cbd4c5e8a8b8b5b2e1ac0c1"));
+        assert_eq!(verified.quote_status(), QuoteStatus::GroupOutOfDate);
+        assert_eq!(verified.quote_body().version, 2);
+        assert_eq!(verified.quote_body().epid_group_id, [0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(verified.warnings(), &warnings[..]);
+        assert_eq!(verified.cert_chain(), &[vec![0x30, 0x82, 0x01]][..]);
+
+        let enclave = verified.enclave();
+        assert_eq!(enclave.mr_enclave, [0x11; 32]);
+        assert_eq!(enclave.mr_signer, [0x22; 32]);
+        assert_eq!(enclave.attributes, [0x07; 16]);
+        assert_eq!(enclave.isv_prod_id, 5);
+        assert_eq!(enclave.isv_svn, 3);
+        assert_eq!(&enclave.report_data[..], &[0x33; 64][..]);
+        assert_eq!(enclave.tcb_status, TcbStatus::OutOfDate);
+        assert_eq!(enclave.advisory_ids, vec!["INTEL-SA-00334".to_string()]);
+
+        let (raw_report, report_sig) = verified.into_report_and_sig();
+        assert_eq!(raw_report.as_bytes(), report.as_bytes());
+        assert_eq!(report_sig.as_bytes(), &[0xab; 256][..]);
+    }
+
+    fn test_tcb_status_of_quote_status() {
+        assert_eq!(QuoteStatus::Ok.tcb_status(), TcbStatus::UpToDate);
+        assert_eq!(QuoteStatus::GroupOutOfDate.tcb_status(), TcbStatus::OutOfDate);
+        assert_eq!(QuoteStatus::KeyRevoked.tcb_status(), TcbStatus::Revoked);
+        assert_eq!(
+            QuoteStatus::SignatureInvalid.tcb_status(),
+            TcbStatus::Unknown("SIGNATURE_INVALID".to_string()),
+        );
+    }
+
+    fn test_reject_bundle_without_signing_cert() {
+        let bundle = EvidenceBundle::new(
+            AttestationReport::new(br#"{"id":"1","isvEnclaveQuoteStatus":"OK"}"#.to_vec()),
+            ReportSig::new(vec![0xab; 256]),
+            vec![],
+        );
+        assert!(IasVerifier::default().verify(&bundle).is_err());
+    }
+}
@@ -82,19 +82,18 @@ macro_rules! should_panic {
     ($fmt:expr) => {{
         match std::panic::catch_unwind(|| $fmt).is_err() {
             true => {
-                println!(
-                    "{} {} ... {}!",
-                    "testing_should_panic",
-                    stringify!($fmt),
-                    "\x1B[1;32mok\x1B[0m"
-                );
+                println!("testing_should_panic {} ... ok!", stringify!($fmt));
             }
             false => std::rt::begin_panic($fmt),
         }
     }};
 }
 
-#[allow(clippy::print_literal)]
+/// Runs a test case, reporting the result on stdout.
+///
+/// The report is plain text, as it mostly ends up in CI logs and files
+/// rather than on a terminal. Only the test harness prints; diagnostics of
+/// the crates under test go through `log`.
 pub fn test<F, R>(ncases: &mut u64, failurecases: &mut Vec<String>, f: F, name: &str)
     where
         F: FnOnce() -> R + std::panic::UnwindSafe,
@@ -104,9 +103,9 @@ pub fn test<F, R>(ncases: &mut u64, failurecases: &mut Vec<String>, f: F, name:
         f();
     };
     if std::panic::catch_unwind(t).is_ok() {
-        println!("{} {} ... {}!", "testing", name, "\x1B[1;32mok\x1B[0m");
+        println!("testing {} ... ok!", name);
     } else {
-        println!("{} {} ... {}!", "testing", name, "\x1B[1;31mfailed\x1B[0m");
+        println!("testing {} ... failed!", name);
         failurecases.push(String::from(name));
     }
 }
@@ -130,9 +129,9 @@ pub fn test_end(ntestcases: u64, failurecases: Vec<String>) -> bool {
     }
 
     if ntotal == nsucc {
-        print!("\ntest result \x1B[1;32mok\x1B[0m. ");
+        print!("\ntest result ok. ");
     } else {
-        print!("\ntest result \x1B[1;31mFAILED\x1B[0m. ");
+        print!("\ntest result FAILED. ");
     }
 
     println!(